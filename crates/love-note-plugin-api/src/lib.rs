@@ -36,6 +36,14 @@ pub struct TextElement {
     pub italic: bool,
     #[serde(default)]
     pub code: bool,
+    #[serde(default)]
+    pub strikethrough: bool,
+    /// Target URL when the text is a link
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    /// Text color as `#rrggbb`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,6 +100,25 @@ pub struct BlockUpdate {
     pub content: Option<serde_json::Value>,
 }
 
+/// Formats a document can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Markdown,
+    /// An HTML fragment placed in the exported page as is
+    Html,
+    /// LaTeX placed in the document body as is
+    Latex,
+}
+
+/// Optional hook for writing a plugin's blocks into exported documents.
+///
+/// Blocks of kinds without a hook, or for which the hook returns `None`,
+/// are exported as their plain text.
+pub trait ExportHook: Send + Sync {
+    fn export(&self, block: &Block, format: ExportFormat) -> Option<String>;
+}
+
 /// Plugin metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginInfo {
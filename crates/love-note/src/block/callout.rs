@@ -0,0 +1,143 @@
+use gpui::*;
+use gpui_component::{
+    input::{Input, InputState},
+    IconName,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{BlockContent, Marks};
+
+/// The severity of a callout, which picks its icon and color
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CalloutStyle {
+    #[default]
+    Info,
+    Warning,
+    Danger,
+}
+
+impl CalloutStyle {
+    /// Get the display name for UI
+    pub fn display_name(self) -> &'static str {
+        match self {
+            CalloutStyle::Info => "Info",
+            CalloutStyle::Warning => "Warning",
+            CalloutStyle::Danger => "Danger",
+        }
+    }
+
+    /// Get the next style, for cycling by clicking the icon
+    pub fn next(self) -> Self {
+        match self {
+            CalloutStyle::Info => CalloutStyle::Warning,
+            CalloutStyle::Warning => CalloutStyle::Danger,
+            CalloutStyle::Danger => CalloutStyle::Info,
+        }
+    }
+
+    pub fn icon(self) -> IconName {
+        match self {
+            CalloutStyle::Info => IconName::Info,
+            CalloutStyle::Warning => IconName::TriangleAlert,
+            CalloutStyle::Danger => IconName::CircleX,
+        }
+    }
+
+    /// Accent color as 0xRRGGBB
+    pub fn color(self) -> u32 {
+        match self {
+            CalloutStyle::Info => 0x89b4fa,
+            CalloutStyle::Warning => 0xf9e2af,
+            CalloutStyle::Danger => 0xf38ba8,
+        }
+    }
+
+    /// Faint tint of the accent color for the callout background
+    pub fn background(self) -> Rgba {
+        rgba((self.color() << 8) | 0x1a)
+    }
+}
+
+/// A highlighted note such as a safety warning
+pub struct CalloutBlock {
+    style: CalloutStyle,
+    marks: Marks,
+}
+
+impl CalloutBlock {
+    pub fn new(style: CalloutStyle) -> Self {
+        Self {
+            style,
+            marks: Marks::default(),
+        }
+    }
+
+    /// Restore a callout from stored properties, defaulting to info
+    pub fn from_props(props: &Value) -> Self {
+        let style = props
+            .get("style")
+            .cloned()
+            .and_then(|style| serde_json::from_value(style).ok())
+            .unwrap_or_default();
+        Self::new(style)
+    }
+}
+
+impl Default for CalloutBlock {
+    fn default() -> Self {
+        Self::new(CalloutStyle::default())
+    }
+}
+
+impl BlockContent for CalloutBlock {
+    fn type_name(&self) -> &'static str {
+        "Callout"
+    }
+
+    fn placeholder(&self) -> &'static str {
+        "Callout..."
+    }
+
+    fn splits_on_enter(&self) -> bool {
+        true
+    }
+
+    fn props(&self) -> Value {
+        self.marks.add_to_props(json!({ "style": self.style }))
+    }
+
+    fn on_text_changed(&mut self, text: &str) -> Option<String> {
+        self.marks.update_text(text);
+        None
+    }
+
+    fn marks(&self) -> Option<&Marks> {
+        Some(&self.marks)
+    }
+
+    fn marks_mut(&mut self) -> Option<&mut Marks> {
+        Some(&mut self.marks)
+    }
+
+    fn callout_style(&self) -> Option<CalloutStyle> {
+        Some(self.style)
+    }
+
+    fn set_callout_style(&mut self, style: CalloutStyle) {
+        self.style = style;
+    }
+
+    fn render_view(&self, text: &str) -> AnyElement {
+        div()
+            .child(self.marks.render("marks".into(), text))
+            .into_any_element()
+    }
+
+    fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
+        div()
+            .child(Input::new(input))
+            .into_any_element()
+    }
+}
@@ -0,0 +1,715 @@
+use anyhow::{Context as _, Result};
+use chrono::{Datelike, NaiveDate};
+use gpui::*;
+use gpui_component::input::{Input, InputState};
+use resvg::{tiny_skia, usvg};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::{BlockContent, Column, ColumnType, TableData, DATE_FORMAT, MONO_FONT};
+
+/// Height of the plot area in the editor
+const PLOT_HEIGHT: f32 = 240.;
+/// Width reserved for y-axis tick labels
+const Y_LABEL_WIDTH: f32 = 56.;
+/// Width of an x-axis tick label, centered on its tick
+const X_LABEL_WIDTH: f32 = 80.;
+/// Approximate number of ticks per axis
+const TICK_COUNT: f64 = 5.;
+
+const SERIES_COLOR: u32 = 0x89b4fa;
+const GRID_COLOR: u32 = 0x313244;
+const AXIS_COLOR: u32 = 0x45475a;
+
+/// Size of exported charts, which use a light palette for print
+const SVG_WIDTH: f32 = 640.;
+const SVG_HEIGHT: f32 = 400.;
+/// Fonts for exported text; PNG rendering needs a family that is installed
+const SVG_FONTS: &str = "Helvetica, Arial, 'DejaVu Sans', 'Noto Sans', sans-serif";
+/// PNG exports are rendered at this multiple of the SVG size
+const PNG_SCALE: f32 = 2.;
+
+/// The kind of chart to draw
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChartKind {
+    #[default]
+    Line,
+    Scatter,
+    Bar,
+    Histogram,
+}
+
+impl ChartKind {
+    /// Get the display name for UI
+    pub fn display_name(self) -> &'static str {
+        match self {
+            ChartKind::Line => "Line",
+            ChartKind::Scatter => "Scatter",
+            ChartKind::Bar => "Bar",
+            ChartKind::Histogram => "Histogram",
+        }
+    }
+
+    /// Get the next kind, for cycling through kinds in the toolbar
+    pub fn next(self) -> Self {
+        match self {
+            ChartKind::Line => ChartKind::Scatter,
+            ChartKind::Scatter => ChartKind::Bar,
+            ChartKind::Bar => ChartKind::Histogram,
+            ChartKind::Histogram => ChartKind::Line,
+        }
+    }
+}
+
+/// What a chart plots and how
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChartSpec {
+    #[serde(default)]
+    pub kind: ChartKind,
+    /// Table block to plot; the block text holds inline CSV data when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Uuid>,
+    /// Column for the x axis (the values for histograms)
+    #[serde(default)]
+    pub x: usize,
+    /// Column for the y axis
+    #[serde(default = "ChartSpec::default_y")]
+    pub y: usize,
+    /// Number of histogram bins
+    #[serde(default = "ChartSpec::default_bins")]
+    pub bins: usize,
+}
+
+impl ChartSpec {
+    pub const MIN_BINS: usize = 1;
+    pub const MAX_BINS: usize = 100;
+
+    fn default_y() -> usize {
+        1
+    }
+
+    fn default_bins() -> usize {
+        10
+    }
+}
+
+impl Default for ChartSpec {
+    fn default() -> Self {
+        Self {
+            kind: ChartKind::default(),
+            source: None,
+            x: 0,
+            y: Self::default_y(),
+            bins: Self::default_bins(),
+        }
+    }
+}
+
+/// How axis values are stepped and labelled
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Scale {
+    Real,
+    /// Whole numbers, such as histogram counts
+    Count,
+    /// Days since the common era
+    Date,
+}
+
+/// One chart axis: its value range and labelled ticks
+#[derive(Clone, Debug)]
+pub struct Axis {
+    pub title: String,
+    pub min: f64,
+    pub max: f64,
+    pub ticks: Vec<(f64, String)>,
+}
+
+impl Axis {
+    /// Numeric axis covering `min..=max`, widened to round tick values
+    fn numeric(title: String, min: f64, max: f64, scale: Scale) -> Self {
+        let (min, max) = if min < max {
+            (min, max)
+        } else {
+            (min - 1., max + 1.)
+        };
+        let mut step = nice_step((max - min) / TICK_COUNT);
+        if scale != Scale::Real {
+            step = step.max(1.).round();
+        }
+        let min = (min / step).floor() * step;
+        let max = (max / step).ceil() * step;
+
+        let count = ((max - min) / step).round() as usize;
+        let ticks = (0..=count)
+            .map(|i| {
+                let value = min + step * i as f64;
+                (value, format_tick(value, step, scale))
+            })
+            .collect();
+
+        Self {
+            title,
+            min,
+            max,
+            ticks,
+        }
+    }
+
+    /// Axis with one slot per category, labelled at slot centers
+    fn categorical(title: String, labels: Vec<String>) -> Self {
+        Self {
+            title,
+            min: 0.,
+            max: labels.len().max(1) as f64,
+            ticks: labels
+                .into_iter()
+                .enumerate()
+                .map(|(i, label)| (i as f64 + 0.5, label))
+                .collect(),
+        }
+    }
+
+    /// Position of a value along the axis, from 0 at `min` to 1 at `max`
+    fn fraction(&self, value: f64) -> f32 {
+        ((value - self.min) / (self.max - self.min)) as f32
+    }
+}
+
+/// Round a raw tick step to 1, 2 or 5 times a power of ten
+fn nice_step(raw: f64) -> f64 {
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let residual = raw / magnitude;
+    let nice = if residual <= 1. {
+        1.
+    } else if residual <= 2. {
+        2.
+    } else if residual <= 5. {
+        5.
+    } else {
+        10.
+    };
+    nice * magnitude
+}
+
+fn format_tick(value: f64, step: f64, scale: Scale) -> String {
+    if scale == Scale::Date {
+        return NaiveDate::from_num_days_from_ce_opt(value as i32)
+            .map(|date| date.format(DATE_FORMAT).to_string())
+            .unwrap_or_default();
+    }
+    let decimals = (-step.log10().floor()).max(0.) as usize;
+    // Adding zero turns -0 into 0
+    format!("{:.*}", decimals, value + 0.)
+}
+
+/// Split a column name like `Time [s]` into its label and unit
+fn split_unit(name: &str) -> (&str, Option<&str>) {
+    let name = name.trim();
+    for (open, close) in [('[', ']'), ('(', ')')] {
+        if let Some(rest) = name.strip_suffix(close)
+            && let Some(start) = rest.rfind(open)
+        {
+            return (rest[..start].trim(), Some(rest[start + 1..].trim()));
+        }
+    }
+    (name, None)
+}
+
+/// Axis title for a column, with its unit in brackets
+fn axis_title(column: &Column) -> String {
+    match split_unit(&column.name) {
+        (label, Some(unit)) if !unit.is_empty() => format!("{} [{}]", label, unit),
+        (label, _) => label.to_string(),
+    }
+}
+
+/// Numeric values of a column, and the scale to plot them on
+fn column_values(data: &TableData, column: usize) -> (Vec<Option<f64>>, Scale) {
+    if data.columns[column].kind != ColumnType::Date {
+        return (data.numbers(column), Scale::Real);
+    }
+    let values = data
+        .rows
+        .iter()
+        .map(|row| {
+            NaiveDate::parse_from_str(row[column].trim(), DATE_FORMAT)
+                .ok()
+                .map(|date| f64::from(date.num_days_from_ce()))
+        })
+        .collect();
+    (values, Scale::Date)
+}
+
+fn value_range(values: impl Iterator<Item = f64>) -> Option<(f64, f64)> {
+    values.fold(None, |range, v| match range {
+        None => Some((v, v)),
+        Some((min, max)) => Some((f64::min(min, v), f64::max(max, v))),
+    })
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A chart laid out in data coordinates, ready to draw on screen or export
+pub struct Plot {
+    pub kind: ChartKind,
+    pub x_axis: Axis,
+    pub y_axis: Axis,
+    /// Data points of line and scatter charts
+    pub points: Vec<(f64, f64)>,
+    /// Bars as (left, right, height) of bar charts and histograms
+    pub bars: Vec<(f64, f64, f64)>,
+}
+
+impl Plot {
+    /// Lay out a chart of the given data, or explain why it can't be drawn
+    pub fn new(spec: &ChartSpec, data: &TableData) -> Result<Self, String> {
+        let Some(last) = data.column_count().checked_sub(1) else {
+            return Err("No data to plot".to_string());
+        };
+        let x = spec.x.min(last);
+        let y = spec.y.min(last);
+        let no_numbers = |column: usize| {
+            format!("Column \"{}\" has no numeric values", data.columns[column].name)
+        };
+
+        match spec.kind {
+            ChartKind::Line | ChartKind::Scatter => {
+                let (ys, y_scale) = column_values(data, y);
+                let (mut xs, mut x_scale) = column_values(data, x);
+                let mut x_title = axis_title(&data.columns[x]);
+                // Fall back to row numbers when the x column isn't numeric
+                if xs.iter().all(Option::is_none) {
+                    xs = (1..=data.row_count()).map(|i| Some(i as f64)).collect();
+                    x_scale = Scale::Count;
+                    x_title = "Row".to_string();
+                }
+
+                let points: Vec<_> = xs
+                    .into_iter()
+                    .zip(ys)
+                    .filter_map(|(x, y)| Some((x?, y?)))
+                    .collect();
+                let (x_min, x_max) =
+                    value_range(points.iter().map(|p| p.0)).ok_or_else(|| no_numbers(y))?;
+                let (y_min, y_max) =
+                    value_range(points.iter().map(|p| p.1)).ok_or_else(|| no_numbers(y))?;
+
+                Ok(Self {
+                    kind: spec.kind,
+                    x_axis: Axis::numeric(x_title, x_min, x_max, x_scale),
+                    y_axis: Axis::numeric(axis_title(&data.columns[y]), y_min, y_max, y_scale),
+                    points,
+                    bars: Vec::new(),
+                })
+            }
+            ChartKind::Bar => {
+                let (ys, _) = column_values(data, y);
+                let (labels, heights): (Vec<_>, Vec<_>) = data
+                    .rows
+                    .iter()
+                    .zip(ys)
+                    .filter_map(|(row, height)| Some((row[x].clone(), height?)))
+                    .unzip();
+                let (min, max) =
+                    value_range(heights.iter().copied()).ok_or_else(|| no_numbers(y))?;
+
+                Ok(Self {
+                    kind: spec.kind,
+                    x_axis: Axis::categorical(axis_title(&data.columns[x]), labels),
+                    // Bars grow from zero, so keep it in range
+                    y_axis: Axis::numeric(
+                        axis_title(&data.columns[y]),
+                        min.min(0.),
+                        max.max(0.),
+                        Scale::Real,
+                    ),
+                    points: Vec::new(),
+                    bars: heights
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, height)| (i as f64, i as f64 + 1., height))
+                        .collect(),
+                })
+            }
+            ChartKind::Histogram => {
+                let (values, scale) = column_values(data, x);
+                let values: Vec<f64> = values.into_iter().flatten().collect();
+                let (min, max) =
+                    value_range(values.iter().copied()).ok_or_else(|| no_numbers(x))?;
+
+                let x_axis = Axis::numeric(axis_title(&data.columns[x]), min, max, scale);
+                let bins = spec.bins.clamp(ChartSpec::MIN_BINS, ChartSpec::MAX_BINS);
+                let width = (x_axis.max - x_axis.min) / bins as f64;
+                let mut counts = vec![0usize; bins];
+                for value in values {
+                    let bin = ((value - x_axis.min) / width) as usize;
+                    counts[bin.min(bins - 1)] += 1;
+                }
+                let highest = counts.iter().copied().max().unwrap_or(0);
+
+                Ok(Self {
+                    kind: spec.kind,
+                    bars: counts
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, count)| {
+                            let left = x_axis.min + width * i as f64;
+                            (left, left + width, count as f64)
+                        })
+                        .collect(),
+                    x_axis,
+                    y_axis: Axis::numeric("Count".to_string(), 0., highest as f64, Scale::Count),
+                    points: Vec::new(),
+                })
+            }
+        }
+    }
+
+    /// Map a data point into the unit square, with the origin at the top left
+    pub fn project(&self, x: f64, y: f64) -> (f32, f32) {
+        (self.x_axis.fraction(x), 1. - self.y_axis.fraction(y))
+    }
+
+    /// Bar rectangles as (left, top, right, bottom) in unit coordinates
+    fn bar_rects(&self) -> impl Iterator<Item = (f32, f32, f32, f32)> + '_ {
+        let base = 0f64.clamp(self.y_axis.min, self.y_axis.max);
+        // Histogram bins touch; bar chart bars are spaced apart
+        let inset = match self.kind {
+            ChartKind::Bar => 0.15,
+            _ => 0.,
+        };
+        self.bars.iter().map(move |&(left, right, height)| {
+            let pad = (right - left) * inset;
+            let (x0, y0) = self.project(left + pad, height);
+            let (x1, y1) = self.project(right - pad, base);
+            (x0, y0.min(y1), x1, y0.max(y1))
+        })
+    }
+
+    /// Render the chart with GPUI: a canvas for the data plus tick labels
+    pub fn render(self) -> AnyElement {
+        let y_labels = div()
+            .relative()
+            .w(px(Y_LABEL_WIDTH))
+            .h(px(PLOT_HEIGHT))
+            .children(self.y_axis.ticks.iter().map(|(value, label)| {
+                let (_, top) = self.project(self.x_axis.min, *value);
+                div()
+                    .absolute()
+                    .right(px(6.))
+                    .top(relative(top))
+                    .mt(px(-8.))
+                    .h(px(16.))
+                    .child(label.clone())
+            }));
+
+        let x_labels = div()
+            .relative()
+            .h(px(20.))
+            .ml(px(Y_LABEL_WIDTH))
+            .children(self.x_axis.ticks.iter().map(|(value, label)| {
+                let (left, _) = self.project(*value, self.y_axis.min);
+                div()
+                    .absolute()
+                    .left(relative(left))
+                    .ml(px(-X_LABEL_WIDTH / 2.))
+                    .w(px(X_LABEL_WIDTH))
+                    .top(px(4.))
+                    .flex()
+                    .justify_center()
+                    .overflow_hidden()
+                    .child(label.clone())
+            }));
+
+        let x_title = self.x_axis.title.clone();
+        let y_title = self.y_axis.title.clone();
+
+        let area = div()
+            .relative()
+            .flex_1()
+            .h(px(PLOT_HEIGHT))
+            .border_l_1()
+            .border_b_1()
+            .border_color(rgb(AXIS_COLOR))
+            .child(
+                canvas(|_, _, _| {}, move |bounds, _, window, _cx| self.paint(bounds, window))
+                    .absolute()
+                    .size_full(),
+            );
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .text_xs()
+            .text_color(rgb(0x9399b2))
+            .child(div().child(y_title))
+            .child(div().flex().child(y_labels).child(area))
+            .child(x_labels)
+            .child(div().flex().justify_center().child(x_title))
+            .into_any_element()
+    }
+
+    fn paint(&self, bounds: Bounds<Pixels>, window: &mut Window) {
+        let to_screen = |(u, v): (f32, f32)| {
+            point(
+                bounds.origin.x + bounds.size.width * u,
+                bounds.origin.y + bounds.size.height * v,
+            )
+        };
+
+        let mut grid = PathBuilder::stroke(px(1.));
+        for (value, _) in &self.y_axis.ticks {
+            grid.move_to(to_screen(self.project(self.x_axis.min, *value)));
+            grid.line_to(to_screen(self.project(self.x_axis.max, *value)));
+        }
+        if let Ok(path) = grid.build() {
+            window.paint_path(path, rgb(GRID_COLOR));
+        }
+
+        match self.kind {
+            ChartKind::Line => {
+                let mut line = PathBuilder::stroke(px(2.));
+                for (i, &(x, y)) in self.points.iter().enumerate() {
+                    let at = to_screen(self.project(x, y));
+                    if i == 0 {
+                        line.move_to(at);
+                    } else {
+                        line.line_to(at);
+                    }
+                }
+                if let Ok(path) = line.build() {
+                    window.paint_path(path, rgb(SERIES_COLOR));
+                }
+            }
+            ChartKind::Scatter => {
+                for &(x, y) in &self.points {
+                    let center = to_screen(self.project(x, y));
+                    window.paint_quad(
+                        fill(Bounds::centered_at(center, size(px(6.), px(6.))), rgb(SERIES_COLOR))
+                            .corner_radii(px(3.)),
+                    );
+                }
+            }
+            ChartKind::Bar | ChartKind::Histogram => {
+                for (left, top, right, bottom) in self.bar_rects() {
+                    let rect =
+                        Bounds::from_corners(to_screen((left, top)), to_screen((right, bottom)));
+                    window.paint_quad(
+                        fill(rect, rgb(SERIES_COLOR))
+                            .border_widths(px(1.))
+                            .border_color(rgb(0x1e1e2e)),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Export the chart as a standalone SVG document
+    pub fn to_svg(&self) -> String {
+        let (left, right, top, bottom) = (72., 24., 32., 56.);
+        let width = SVG_WIDTH - left - right;
+        let height = SVG_HEIGHT - top - bottom;
+        let at = |(u, v): (f32, f32)| (left + width * u, top + height * v);
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\" font-family=\"{font}\" font-size=\"12\">\n\
+             <rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n",
+            w = SVG_WIDTH,
+            h = SVG_HEIGHT,
+            font = SVG_FONTS,
+        );
+
+        for (value, label) in &self.y_axis.ticks {
+            let (_, y) = at(self.project(self.x_axis.min, *value));
+            svg.push_str(&format!(
+                "<line x1=\"{}\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\" stroke=\"#dce0e8\"/>\n\
+                 <text x=\"{}\" y=\"{}\" text-anchor=\"end\" fill=\"#4c4f69\">{}</text>\n",
+                left,
+                left + width,
+                left - 6.,
+                y + 4.,
+                escape_xml(label),
+            ));
+        }
+        for (value, label) in &self.x_axis.ticks {
+            let (x, _) = at(self.project(*value, self.y_axis.min));
+            svg.push_str(&format!(
+                "<text x=\"{x}\" y=\"{}\" text-anchor=\"middle\" fill=\"#4c4f69\">{}</text>\n",
+                top + height + 16.,
+                escape_xml(label),
+            ));
+        }
+
+        match self.kind {
+            ChartKind::Line => {
+                let points: Vec<String> = self
+                    .points
+                    .iter()
+                    .map(|&(x, y)| {
+                        let (x, y) = at(self.project(x, y));
+                        format!("{x:.2},{y:.2}")
+                    })
+                    .collect();
+                svg.push_str(&format!(
+                    "<polyline points=\"{}\" fill=\"none\" stroke=\"#1e66f5\" stroke-width=\"2\"/>\n",
+                    points.join(" "),
+                ));
+            }
+            ChartKind::Scatter => {
+                for &(x, y) in &self.points {
+                    let (x, y) = at(self.project(x, y));
+                    svg.push_str(&format!(
+                        "<circle cx=\"{x:.2}\" cy=\"{y:.2}\" r=\"3\" fill=\"#1e66f5\"/>\n"
+                    ));
+                }
+            }
+            ChartKind::Bar | ChartKind::Histogram => {
+                for (u0, v0, u1, v1) in self.bar_rects() {
+                    let (x0, y0) = at((u0, v0));
+                    let (x1, y1) = at((u1, v1));
+                    svg.push_str(&format!(
+                        "<rect x=\"{x0:.2}\" y=\"{y0:.2}\" width=\"{:.2}\" height=\"{:.2}\" \
+                         fill=\"#1e66f5\" stroke=\"#ffffff\"/>\n",
+                        x1 - x0,
+                        y1 - y0,
+                    ));
+                }
+            }
+        }
+
+        let middle_y = top + height / 2.;
+        svg.push_str(&format!(
+            "<path d=\"M{left} {top}V{}H{}\" fill=\"none\" stroke=\"#4c4f69\"/>\n\
+             <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" fill=\"#4c4f69\">{}</text>\n\
+             <text x=\"16\" y=\"{middle_y}\" text-anchor=\"middle\" fill=\"#4c4f69\" \
+             transform=\"rotate(-90 16 {middle_y})\">{}</text>\n</svg>\n",
+            top + height,
+            left + width,
+            left + width / 2.,
+            SVG_HEIGHT - 12.,
+            escape_xml(&self.x_axis.title),
+            escape_xml(&self.y_axis.title),
+        ));
+        svg
+    }
+
+    /// Export the chart as a PNG image, rasterizing the SVG export
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mut options = usvg::Options::default();
+        options.fontdb_mut().load_system_fonts();
+        let tree = usvg::Tree::from_str(&self.to_svg(), &options)?;
+
+        let mut pixmap = tiny_skia::Pixmap::new(
+            (SVG_WIDTH * PNG_SCALE) as u32,
+            (SVG_HEIGHT * PNG_SCALE) as u32,
+        )
+        .context("Invalid image size")?;
+        resvg::render(
+            &tree,
+            tiny_skia::Transform::from_scale(PNG_SCALE, PNG_SCALE),
+            &mut pixmap.as_mut(),
+        );
+        Ok(pixmap.encode_png()?)
+    }
+}
+
+/// A chart of a table block in the same document, or of inline CSV data
+/// written in the block text. Units are taken from column names written
+/// as `Name [unit]`.
+pub struct ChartBlock {
+    spec: ChartSpec,
+}
+
+impl ChartBlock {
+    pub fn new(spec: ChartSpec) -> Self {
+        Self { spec }
+    }
+
+    /// Restore a chart block from stored properties
+    pub fn from_props(props: &Value) -> Self {
+        Self::new(serde_json::from_value(props.clone()).unwrap_or_default())
+    }
+
+    /// Parse inline chart data: CSV with a header row, or a single column
+    /// of values for histograms
+    pub fn parse_inline(text: &str) -> Option<TableData> {
+        if let Some(data) = TableData::from_delimited(text) {
+            return Some(data);
+        }
+
+        let mut values: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let first = values.first()?;
+        let name = if first.parse::<f64>().is_err() {
+            values.remove(0).to_string()
+        } else {
+            "Value".to_string()
+        };
+
+        Some(TableData {
+            columns: vec![Column::new(name, ColumnType::Number)],
+            rows: values.into_iter().map(|v| vec![v.to_string()]).collect(),
+        })
+    }
+}
+
+impl Default for ChartBlock {
+    fn default() -> Self {
+        Self::new(ChartSpec::default())
+    }
+}
+
+impl BlockContent for ChartBlock {
+    fn type_name(&self) -> &'static str {
+        "Chart"
+    }
+
+    fn placeholder(&self) -> &'static str {
+        "x, y [unit]"
+    }
+
+    fn max_rows(&self) -> usize {
+        12
+    }
+
+    fn props(&self) -> Value {
+        serde_json::to_value(&self.spec).unwrap_or_default()
+    }
+
+    fn chart(&self) -> Option<&ChartSpec> {
+        Some(&self.spec)
+    }
+
+    fn chart_mut(&mut self) -> Option<&mut ChartSpec> {
+        Some(&mut self.spec)
+    }
+
+    fn render_view(&self, text: &str) -> AnyElement {
+        div()
+            .font_family(MONO_FONT)
+            .text_xs()
+            .text_color(rgb(0x6c7086))
+            .child(text.to_string())
+            .into_any_element()
+    }
+
+    fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
+        div()
+            .font_family(MONO_FONT)
+            .text_sm()
+            .child(Input::new(input))
+            .into_any_element()
+    }
+}
@@ -0,0 +1,284 @@
+use gpui::*;
+use gpui_component::input::{Input, InputState};
+
+use super::parse::chem::{self, Part, Reaction, Species};
+use super::{BlockContent, MONO_FONT};
+
+/// Font size for typeset formulas
+const CHEM_SIZE: f32 = 18.;
+/// Scale applied to subscripts and charges
+const SCRIPT_SCALE: f32 = 0.65;
+
+/// A chemical formula or reaction equation, typeset with its molar
+/// masses and, for reactions, a mass balance check
+pub struct ChemistryBlock;
+
+impl ChemistryBlock {
+    /// Typeset a formula, falling back to the raw source on parse errors
+    fn render_chemistry(source: &str) -> AnyElement {
+        match chem::parse(source) {
+            Ok(reaction) => div()
+                .flex()
+                .flex_col()
+                .items_center()
+                .gap_1()
+                .child(render_reaction(&reaction))
+                .child(render_summary(&reaction))
+                .into_any_element(),
+            Err(message) => div()
+                .flex()
+                .flex_col()
+                .items_center()
+                .font_family(MONO_FONT)
+                .text_sm()
+                .child(source.to_string())
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0xf38ba8))
+                        .child(message),
+                )
+                .into_any_element(),
+        }
+    }
+}
+
+impl BlockContent for ChemistryBlock {
+    fn type_name(&self) -> &'static str {
+        "Chemistry"
+    }
+
+    fn placeholder(&self) -> &'static str {
+        "2H2 + O2 -> 2H2O"
+    }
+
+    fn max_rows(&self) -> usize {
+        1
+    }
+
+    fn render_view(&self, text: &str) -> AnyElement {
+        div()
+            .py_2()
+            .child(Self::render_chemistry(text))
+            .into_any_element()
+    }
+
+    fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
+        div()
+            .font_family(MONO_FONT)
+            .text_sm()
+            .child(Input::new(input))
+            .into_any_element()
+    }
+
+    fn render_preview(&self, text: &str) -> Option<AnyElement> {
+        if text.trim().is_empty() {
+            return None;
+        }
+        Some(
+            div()
+                .py_2()
+                .border_t_1()
+                .border_color(rgb(0x313244))
+                .child(Self::render_chemistry(text))
+                .into_any_element(),
+        )
+    }
+}
+
+fn render_reaction(reaction: &Reaction) -> Div {
+    let mut row = div()
+        .flex()
+        .flex_row()
+        .items_center()
+        .text_size(px(CHEM_SIZE))
+        .children(render_side(&reaction.reactants));
+    if let Some(arrow) = reaction.arrow {
+        row = row
+            .child(operator(arrow.symbol()))
+            .children(render_side(&reaction.products));
+    }
+    row
+}
+
+fn render_side(side: &[Species]) -> Vec<AnyElement> {
+    let mut elements = Vec::new();
+    for (i, species) in side.iter().enumerate() {
+        if i > 0 {
+            elements.push(operator("+"));
+        }
+        elements.push(render_species(species));
+    }
+    elements
+}
+
+fn operator(symbol: &str) -> AnyElement {
+    div()
+        .px(px(CHEM_SIZE * 0.4))
+        .child(symbol.to_string())
+        .into_any_element()
+}
+
+fn script(text: String, raise: f32) -> AnyElement {
+    div()
+        .relative()
+        .top(px(CHEM_SIZE * raise))
+        .text_size(px(CHEM_SIZE * SCRIPT_SCALE))
+        .child(text)
+        .into_any_element()
+}
+
+fn subscript(text: String) -> AnyElement {
+    script(text, 0.3)
+}
+
+fn superscript(text: String) -> AnyElement {
+    script(text, -0.4)
+}
+
+fn render_species(species: &Species) -> AnyElement {
+    let coefficient = (species.coefficient != 1.)
+        .then(|| div().pr(px(2.)).child(chem::format_number(species.coefficient)));
+    let charge = (species.charge != 0).then(|| superscript(chem::charge_text(species.charge)));
+    let state = species.state.as_ref().map(|state| {
+        div()
+            .pl(px(2.))
+            .text_size(px(CHEM_SIZE * SCRIPT_SCALE))
+            .child(format!("({})", state))
+    });
+
+    div()
+        .flex()
+        .flex_row()
+        .items_center()
+        .children(coefficient)
+        .children(render_parts(&species.parts))
+        .children(charge)
+        .children(state)
+        .into_any_element()
+}
+
+fn render_parts(parts: &[Part]) -> Vec<AnyElement> {
+    let mut elements = Vec::new();
+    for part in parts {
+        match part {
+            Part::Element { symbol, count } => {
+                elements.push(div().child(symbol.clone()).into_any_element());
+                if *count > 1 {
+                    elements.push(subscript(count.to_string()));
+                }
+            }
+            Part::Group {
+                open,
+                close,
+                parts,
+                count,
+            } => {
+                elements.push(div().child(open.to_string()).into_any_element());
+                elements.extend(render_parts(parts));
+                elements.push(div().child(close.to_string()).into_any_element());
+                if *count > 1 {
+                    elements.push(subscript(count.to_string()));
+                }
+            }
+            Part::Adduct { coefficient, parts } => {
+                elements.push(div().px(px(2.)).child("·").into_any_element());
+                if *coefficient > 1 {
+                    elements.push(div().child(coefficient.to_string()).into_any_element());
+                }
+                elements.extend(render_parts(parts));
+            }
+            Part::Electron => elements.push(div().italic().child("e").into_any_element()),
+        }
+    }
+    elements
+}
+
+/// Formula as plain text with Unicode subscripts, for summaries
+fn plain_formula(species: &Species) -> String {
+    fn push_parts(out: &mut String, parts: &[Part]) {
+        let subscript = |n: u32| -> String {
+            if n == 1 {
+                return String::new();
+            }
+            n.to_string()
+                .chars()
+                .filter_map(|d| d.to_digit(10))
+                .filter_map(|d| char::from_u32(0x2080 + d))
+                .collect()
+        };
+        for part in parts {
+            match part {
+                Part::Element { symbol, count } => {
+                    out.push_str(symbol);
+                    out.push_str(&subscript(*count));
+                }
+                Part::Group {
+                    open,
+                    close,
+                    parts,
+                    count,
+                } => {
+                    out.push(*open);
+                    push_parts(out, parts);
+                    out.push(*close);
+                    out.push_str(&subscript(*count));
+                }
+                Part::Adduct { coefficient, parts } => {
+                    out.push('·');
+                    if *coefficient > 1 {
+                        out.push_str(&coefficient.to_string());
+                    }
+                    push_parts(out, parts);
+                }
+                Part::Electron => out.push('e'),
+            }
+        }
+    }
+
+    let mut out = String::new();
+    push_parts(&mut out, &species.parts);
+    if species.charge != 0 {
+        out.push_str(&chem::charge_text(species.charge));
+    }
+    out
+}
+
+/// Molar masses of the species and, for reactions, the balance check
+fn render_summary(reaction: &Reaction) -> Div {
+    let all: Vec<&Species> = reaction
+        .reactants
+        .iter()
+        .chain(&reaction.products)
+        .collect();
+
+    let mut masses: Vec<String> = Vec::new();
+    for species in &all {
+        let line = format!("{} {:.3} g/mol", plain_formula(species), species.molar_mass());
+        if !masses.contains(&line) {
+            masses.push(line);
+        }
+    }
+    let masses = match all.as_slice() {
+        [single] => format!("M = {:.3} g/mol", single.molar_mass()),
+        _ => masses.join(" · "),
+    };
+
+    let balance = reaction.arrow.map(|_| match reaction.imbalances() {
+        imbalances if imbalances.is_empty() => div()
+            .text_color(rgb(0xa6e3a1))
+            .child("Balanced"),
+        imbalances => div()
+            .text_color(rgb(0xf38ba8))
+            .child(format!("Not balanced: {}", imbalances.join(", "))),
+    });
+
+    div()
+        .flex()
+        .flex_col()
+        .items_center()
+        .text_xs()
+        .text_color(rgb(0x9399b2))
+        .child(masses)
+        .children(balance)
+}
@@ -0,0 +1,233 @@
+use gpui::*;
+use gpui_component::input::{Input, InputState};
+
+use super::{BlockContent, MONO_FONT};
+
+/// A reference parsed from a BibTeX entry
+#[derive(Clone, Debug, PartialEq)]
+pub struct Citation {
+    /// Entry type such as `article` or `book`, in lowercase
+    pub entry_type: String,
+    pub key: String,
+    /// Field names in lowercase with their values as written
+    pub fields: Vec<(String, String)>,
+}
+
+impl Citation {
+    /// Parse a single BibTeX entry such as
+    /// `@article{key, author = {...}, title = {...}, year = 2024}`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let rest = text
+            .strip_prefix('@')
+            .ok_or("A citation starts with @ and an entry type")?;
+        let open = rest
+            .find(['{', '('])
+            .ok_or("Expected { after the entry type")?;
+        let entry_type = rest[..open].trim().to_lowercase();
+        if entry_type.is_empty() || !entry_type.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err("Expected an entry type such as @article".to_string());
+        }
+
+        let body = &rest[open + 1..];
+        let comma = body.find(',').ok_or("Expected a comma after the key")?;
+        let key = body[..comma].trim().to_string();
+        if key.is_empty() || key.contains(|c: char| c.is_whitespace() || "{}\",#%".contains(c)) {
+            return Err("Expected a citation key".to_string());
+        }
+
+        let mut fields = Vec::new();
+        let mut chars = body[comma + 1..].chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+            match chars.peek() {
+                None => return Err("Missing closing }".to_string()),
+                Some('}' | ')') => break,
+                Some(_) => {}
+            }
+            let mut name = String::new();
+            while let Some(c) = chars.next_if(|c| *c != '=') {
+                name.push(c);
+            }
+            if chars.next().is_none() {
+                return Err(format!("Expected = after {}", name.trim()));
+            }
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            let value = match chars.next() {
+                Some('{') => delimited(&mut chars, '}')?,
+                Some('"') => delimited(&mut chars, '"')?,
+                Some(c) => {
+                    let mut value = c.to_string();
+                    while let Some(c) = chars.next_if(|c| !matches!(c, ',' | '}' | ')')) {
+                        value.push(c);
+                    }
+                    value.trim().to_string()
+                }
+                None => return Err("Missing closing }".to_string()),
+            };
+            fields.push((name.trim().to_lowercase(), value));
+        }
+
+        Ok(Self {
+            entry_type,
+            key,
+            fields,
+        })
+    }
+
+    /// A field's value with braces removed and whitespace collapsed
+    pub fn field(&self, name: &str) -> Option<String> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| {
+                let value: String = value.chars().filter(|c| !matches!(c, '{' | '}')).collect();
+                value.split_whitespace().collect::<Vec<_>>().join(" ")
+            })
+            .filter(|value| !value.is_empty())
+    }
+
+    /// The reference written out for reading, as in
+    /// "Curie and Curie (1898). On a new radioactive substance. Comptes rendus."
+    pub fn reference(&self) -> String {
+        let mut parts = Vec::new();
+        let authors = self.field("author").map(|authors| {
+            let names: Vec<&str> = authors.split(" and ").map(surname).collect();
+            match names.as_slice() {
+                [one] => one.to_string(),
+                [one, two] => format!("{} and {}", one, two),
+                [first, ..] => format!("{} et al.", first),
+                [] => String::new(),
+            }
+        });
+        match (authors, self.field("year")) {
+            (Some(authors), Some(year)) => parts.push(format!("{} ({})", authors, year)),
+            (Some(authors), None) => parts.push(authors),
+            (None, Some(year)) => parts.push(format!("({})", year)),
+            (None, None) => {}
+        }
+        if let Some(title) = self.field("title") {
+            parts.push(title);
+        }
+        if let Some(venue) = ["journal", "booktitle", "publisher", "howpublished"]
+            .iter()
+            .find_map(|name| self.field(name))
+        {
+            parts.push(venue);
+        }
+        if parts.is_empty() {
+            return self.key.clone();
+        }
+        let mut reference = parts
+            .iter()
+            .map(|part| part.trim_end_matches('.'))
+            .collect::<Vec<_>>()
+            .join(". ");
+        reference.push('.');
+        reference
+    }
+}
+
+/// Read a value up to the closing `end`, keeping nested braces
+fn delimited(chars: &mut impl Iterator<Item = char>, end: char) -> Result<String, String> {
+    let mut value = String::new();
+    let mut depth = 0;
+    for c in chars.by_ref() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            c if c == end && depth == 0 => return Ok(value),
+            _ => {}
+        }
+        value.push(c);
+    }
+    Err("Unclosed field value".to_string())
+}
+
+/// The family name of a BibTeX name, written "Last, First" or "First Last"
+fn surname(name: &str) -> &str {
+    let name = name.trim();
+    match name.split_once(',') {
+        Some((last, _)) => last.trim(),
+        None => name.rsplit(' ').next().unwrap_or(name),
+    }
+}
+
+/// A bibliography entry written in BibTeX, shown as a formatted reference
+pub struct CitationBlock;
+
+impl CitationBlock {
+    fn render_reference(source: &str) -> AnyElement {
+        match Citation::parse(source) {
+            Ok(citation) => div()
+                .flex()
+                .gap_2()
+                .child(
+                    div()
+                        .flex_none()
+                        .font_family(MONO_FONT)
+                        .text_sm()
+                        .text_color(rgb(0x6c7086))
+                        .child(format!("[{}]", citation.key)),
+                )
+                .child(div().child(citation.reference()))
+                .into_any_element(),
+            Err(message) => div()
+                .flex()
+                .flex_col()
+                .font_family(MONO_FONT)
+                .text_sm()
+                .child(source.to_string())
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0xf38ba8))
+                        .child(message),
+                )
+                .into_any_element(),
+        }
+    }
+}
+
+impl BlockContent for CitationBlock {
+    fn type_name(&self) -> &'static str {
+        "Citation"
+    }
+
+    fn placeholder(&self) -> &'static str {
+        "@article{key, author = {Name}, title = {Title}, year = {2024}}"
+    }
+
+    fn max_rows(&self) -> usize {
+        12
+    }
+
+    fn render_view(&self, text: &str) -> AnyElement {
+        div()
+            .py_1()
+            .child(Self::render_reference(text))
+            .into_any_element()
+    }
+
+    fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
+        div()
+            .font_family(MONO_FONT)
+            .text_sm()
+            .child(Input::new(input))
+            .into_any_element()
+    }
+
+    fn render_preview(&self, text: &str) -> Option<AnyElement> {
+        if text.trim().is_empty() {
+            return None;
+        }
+        Some(
+            div()
+                .py_2()
+                .border_t_1()
+                .border_color(rgb(0x313244))
+                .child(Self::render_reference(text))
+                .into_any_element(),
+        )
+    }
+}
@@ -0,0 +1,193 @@
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use gpui::*;
+use gpui_component::input::{Input, InputState, TabSize};
+use serde_json::{json, Value};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use super::BlockContent;
+
+/// Monospace font used for code
+#[cfg(target_os = "macos")]
+pub const MONO_FONT: &str = "Menlo";
+#[cfg(target_os = "windows")]
+pub const MONO_FONT: &str = "Consolas";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub const MONO_FONT: &str = "DejaVu Sans Mono";
+
+/// Languages offered in the selector: (storage id, display name, syntect token)
+pub const LANGUAGES: &[(&str, &str, &str)] = &[
+    ("plain", "Plain Text", "txt"),
+    ("python", "Python", "py"),
+    ("r", "R", "r"),
+    ("rust", "Rust", "rs"),
+    ("c", "C", "c"),
+    ("cpp", "C++", "cpp"),
+    ("java", "Java", "java"),
+    ("javascript", "JavaScript", "js"),
+    ("bash", "Shell", "sh"),
+    ("sql", "SQL", "sql"),
+    ("matlab", "MATLAB", "matlab"),
+    ("latex", "LaTeX", "tex"),
+    ("json", "JSON", "json"),
+    ("yaml", "YAML", "yaml"),
+];
+
+static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+/// Editor theme; code is left unhighlighted if it's missing
+static THEME: LazyLock<Option<Theme>> = LazyLock::new(|| bundled_theme("base16-mocha.dark"));
+
+/// Theme for exports read on a light background
+static LIGHT_THEME: LazyLock<Option<Theme>> = LazyLock::new(|| bundled_theme("InspiredGitHub"));
+
+fn bundled_theme(name: &str) -> Option<Theme> {
+    ThemeSet::load_defaults().themes.remove(name)
+}
+
+/// A code block with syntax highlighting
+pub struct CodeBlock {
+    language: String,
+}
+
+impl CodeBlock {
+    pub fn new(language: impl Into<String>) -> Self {
+        Self {
+            language: language.into(),
+        }
+    }
+
+    /// Restore a code block from stored properties
+    pub fn from_props(props: &Value) -> Self {
+        let language = props
+            .get("language")
+            .and_then(Value::as_str)
+            .unwrap_or("plain");
+        Self::new(language)
+    }
+
+    /// Get the display name for a language id
+    pub fn language_name(language: &str) -> &str {
+        LANGUAGES
+            .iter()
+            .find(|(id, _, _)| *id == language)
+            .map_or(language, |(_, name, _)| name)
+    }
+
+    /// The id of a language given by id or file extension, ignoring case
+    pub fn language_id(name: &str) -> Option<&'static str> {
+        let name = name.to_lowercase();
+        LANGUAGES
+            .iter()
+            .find(|(id, _, token)| *id == name || *token == name)
+            .map(|(id, _, _)| *id)
+    }
+
+    /// Compute syntax highlight ranges for the given source
+    fn highlights(&self, text: &str) -> Vec<(Range<usize>, HighlightStyle)> {
+        Self::highlight_colors(&self.language, text, false)
+            .into_iter()
+            .map(|(range, color)| {
+                let highlight = HighlightStyle {
+                    color: Some(rgb(color).into()),
+                    ..Default::default()
+                };
+                (range, highlight)
+            })
+            .collect()
+    }
+
+    /// Byte ranges of `text` with their colors as 0xRRGGBB, in the
+    /// editor's dark theme or in a light theme for exports
+    pub fn highlight_colors(language: &str, text: &str, light: bool) -> Vec<(Range<usize>, u32)> {
+        let token = LANGUAGES
+            .iter()
+            .find(|(id, _, _)| *id == language)
+            .map_or(language, |(_, _, token)| token);
+        let syntax = SYNTAX_SET
+            .find_syntax_by_token(token)
+            .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+        let theme = if light { &*LIGHT_THEME } else { &*THEME };
+        let Some(theme) = theme else {
+            return Vec::new();
+        };
+
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let mut highlights = Vec::new();
+        let mut offset = 0;
+        for line in LinesWithEndings::from(text) {
+            let Ok(ranges) = highlighter.highlight_line(line, &SYNTAX_SET) else {
+                break;
+            };
+            for (style, piece) in ranges {
+                let color = style.foreground;
+                let color = u32::from_be_bytes([0, color.r, color.g, color.b]);
+                highlights.push((offset..offset + piece.len(), color));
+                offset += piece.len();
+            }
+        }
+        highlights
+    }
+}
+
+impl Default for CodeBlock {
+    fn default() -> Self {
+        Self::new("plain")
+    }
+}
+
+impl BlockContent for CodeBlock {
+    fn type_name(&self) -> &'static str {
+        "Code"
+    }
+
+    fn placeholder(&self) -> &'static str {
+        "Paste or type code..."
+    }
+
+    fn max_rows(&self) -> usize {
+        40
+    }
+
+    fn props(&self) -> Value {
+        json!({ "language": self.language })
+    }
+
+    fn configure_input(&self, state: InputState) -> InputState {
+        state.tab_size(TabSize {
+            tab_size: 4,
+            hard_tabs: false,
+        })
+    }
+
+    fn code_language(&self) -> Option<&str> {
+        Some(&self.language)
+    }
+
+    fn set_code_language(&mut self, language: &str) {
+        self.language = language.to_string();
+    }
+
+    fn render_view(&self, text: &str) -> AnyElement {
+        div()
+            .font_family(MONO_FONT)
+            .text_sm()
+            .px_3()
+            .py_2()
+            .bg(rgb(0x181825))
+            .rounded_md()
+            .child(StyledText::new(text.to_string()).with_highlights(self.highlights(text)))
+            .into_any_element()
+    }
+
+    fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
+        div()
+            .font_family(MONO_FONT)
+            .text_sm()
+            .child(Input::new(input))
+            .into_any_element()
+    }
+}
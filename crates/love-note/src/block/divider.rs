@@ -0,0 +1,39 @@
+use gpui::*;
+use gpui_component::input::InputState;
+
+use super::BlockContent;
+
+/// A horizontal rule separating sections. It has no text; while focused
+/// the rule is highlighted so it can be selected and deleted.
+pub struct DividerBlock;
+
+impl DividerBlock {
+    fn rule(color: u32) -> AnyElement {
+        div()
+            .py_2()
+            .child(div().h(px(1.)).w_full().bg(rgb(color)))
+            .into_any_element()
+    }
+}
+
+impl BlockContent for DividerBlock {
+    fn type_name(&self) -> &'static str {
+        "Divider"
+    }
+
+    fn placeholder(&self) -> &'static str {
+        ""
+    }
+
+    fn max_rows(&self) -> usize {
+        1
+    }
+
+    fn render_view(&self, _text: &str) -> AnyElement {
+        Self::rule(0x45475a)
+    }
+
+    fn render_edit(&self, _input: &Entity<InputState>) -> AnyElement {
+        Self::rule(0x89b4fa)
+    }
+}
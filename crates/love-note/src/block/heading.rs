@@ -1,10 +1,76 @@
 use gpui::*;
 use gpui_component::input::{Input, InputState};
+use serde_json::{json, Value};
 
-use super::BlockContent;
+use super::{BlockContent, Marks};
 
 /// A heading block for titles and section headers
-pub struct HeadingBlock;
+pub struct HeadingBlock {
+    level: u8,
+    marks: Marks,
+}
+
+impl HeadingBlock {
+    pub const MIN_LEVEL: u8 = 1;
+    pub const MAX_LEVEL: u8 = 6;
+
+    /// Create a heading of the given level (clamped to h1..h6)
+    pub fn new(level: u8) -> Self {
+        Self {
+            level: level.clamp(Self::MIN_LEVEL, Self::MAX_LEVEL),
+            marks: Marks::default(),
+        }
+    }
+
+    /// Restore a heading from stored properties, defaulting to h1
+    pub fn from_props(props: &Value) -> Self {
+        let level = props
+            .get("level")
+            .and_then(Value::as_u64)
+            .unwrap_or(Self::MIN_LEVEL as u64);
+        Self::new(level.min(Self::MAX_LEVEL as u64) as u8)
+    }
+
+    /// Get the heading level (1-6)
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Split a Markdown-style `#`..`######` prefix off the text.
+    ///
+    /// Returns the level and the remaining text when `text` starts with
+    /// one to six `#` characters followed by a space.
+    pub fn parse_shortcut(text: &str) -> Option<(u8, &str)> {
+        let hashes = text.bytes().take_while(|&b| b == b'#').count();
+        if !(1..=Self::MAX_LEVEL as usize).contains(&hashes) {
+            return None;
+        }
+        text[hashes..]
+            .strip_prefix(' ')
+            .map(|rest| (hashes as u8, rest))
+    }
+
+    /// Apply the typography for this heading level
+    fn styled(&self, element: Div) -> Div {
+        match self.level {
+            1 => element.text_3xl().font_weight(FontWeight::BOLD),
+            2 => element.text_2xl().font_weight(FontWeight::BOLD),
+            3 => element.text_xl().font_weight(FontWeight::SEMIBOLD),
+            4 => element.text_lg().font_weight(FontWeight::SEMIBOLD),
+            5 => element.text_base().font_weight(FontWeight::SEMIBOLD),
+            _ => element
+                .text_sm()
+                .font_weight(FontWeight::SEMIBOLD)
+                .text_color(rgb(0xa6adc8)),
+        }
+    }
+}
+
+impl Default for HeadingBlock {
+    fn default() -> Self {
+        Self::new(Self::MIN_LEVEL)
+    }
+}
 
 impl BlockContent for HeadingBlock {
     fn type_name(&self) -> &'static str {
@@ -19,19 +85,69 @@ impl BlockContent for HeadingBlock {
         1 // Headings are single-line
     }
 
+    fn splits_on_enter(&self) -> bool {
+        true
+    }
+
+    fn props(&self) -> Value {
+        self.marks.add_to_props(json!({ "level": self.level }))
+    }
+
+    fn on_text_changed(&mut self, text: &str) -> Option<String> {
+        let Some((level, rest)) = Self::parse_shortcut(text) else {
+            self.marks.update_text(text);
+            return None;
+        };
+        self.level = level;
+        self.marks.update_text(rest);
+        Some(rest.to_string())
+    }
+
+    fn marks(&self) -> Option<&Marks> {
+        Some(&self.marks)
+    }
+
+    fn marks_mut(&mut self) -> Option<&mut Marks> {
+        Some(&mut self.marks)
+    }
+
     fn render_view(&self, text: &str) -> AnyElement {
-        div()
-            .text_2xl()
-            .font_weight(FontWeight::BOLD)
-            .child(text.to_string())
+        self.styled(div())
+            .child(self.marks.render("marks".into(), text))
             .into_any_element()
     }
 
     fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
-        div()
-            .text_2xl()
-            .font_weight(FontWeight::BOLD)
+        self.styled(div())
             .child(Input::new(input))
             .into_any_element()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::HeadingBlock;
+
+    #[test]
+    fn parses_shortcuts_for_each_level() {
+        assert_eq!(HeadingBlock::parse_shortcut("# Title"), Some((1, "Title")));
+        assert_eq!(HeadingBlock::parse_shortcut("###### Small"), Some((6, "Small")));
+        assert_eq!(HeadingBlock::parse_shortcut("## "), Some((2, "")));
+    }
+
+    #[test]
+    fn rejects_other_text() {
+        assert_eq!(HeadingBlock::parse_shortcut("####### Too deep"), None);
+        assert_eq!(HeadingBlock::parse_shortcut("#hashtag"), None);
+        assert_eq!(HeadingBlock::parse_shortcut("Title #"), None);
+    }
+
+    #[test]
+    fn clamps_stored_levels() {
+        assert_eq!(HeadingBlock::from_props(&json!({ "level": 9 })).level(), 6);
+        assert_eq!(HeadingBlock::from_props(&json!({ "level": 0 })).level(), 1);
+        assert_eq!(HeadingBlock::from_props(&Value::Null).level(), 1);
+    }
+}
@@ -0,0 +1,152 @@
+use std::path::PathBuf;
+
+use gpui::*;
+use gpui_component::input::{Input, InputState};
+use serde_json::{json, Value};
+
+use super::BlockContent;
+use crate::storage::AttachmentStore;
+
+/// An image from the attachment store, with the block text as caption
+pub struct ImageBlock {
+    /// Content-addressed file name in the image store
+    file: Option<String>,
+    /// Resolved path of `file`
+    path: Option<PathBuf>,
+    /// Display width as a percentage of the editor width
+    width: u32,
+}
+
+impl ImageBlock {
+    pub const MIN_WIDTH: u32 = 10;
+    pub const MAX_WIDTH: u32 = 100;
+    pub const WIDTH_STEP: u32 = 10;
+    /// Extensions of the image files that can be attached
+    pub const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "tiff"];
+
+    /// Restore an image block from stored properties
+    pub fn from_props(props: &Value, images: &AttachmentStore) -> Self {
+        let file = props
+            .get("file")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let width = props
+            .get("width")
+            .and_then(Value::as_u64)
+            .map_or(Self::MAX_WIDTH, |w| w as u32);
+
+        Self {
+            path: file.as_deref().map(|name| images.path(name)),
+            file,
+            width: width.clamp(Self::MIN_WIDTH, Self::MAX_WIDTH),
+        }
+    }
+
+    /// Get the file extension to store a clipboard image under
+    pub fn extension_for(format: ImageFormat) -> &'static str {
+        match format {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Gif => "gif",
+            ImageFormat::Svg => "svg",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Tiff => "tiff",
+        }
+    }
+
+    fn render_image(&self) -> Div {
+        let frame = div().w(relative(self.width as f32 / 100.));
+        match &self.path {
+            Some(path) => frame.child(
+                img(path.clone())
+                    .w_full()
+                    .object_fit(ObjectFit::Contain)
+                    .rounded_md(),
+            ),
+            None => frame
+                .flex()
+                .items_center()
+                .justify_center()
+                .h(px(120.))
+                .rounded_md()
+                .border_1()
+                .border_color(rgb(0x45475a))
+                .text_color(rgb(0x6c7086))
+                .child("No image"),
+        }
+    }
+}
+
+impl Default for ImageBlock {
+    fn default() -> Self {
+        Self {
+            file: None,
+            path: None,
+            width: Self::MAX_WIDTH,
+        }
+    }
+}
+
+impl BlockContent for ImageBlock {
+    fn type_name(&self) -> &'static str {
+        "Image"
+    }
+
+    fn placeholder(&self) -> &'static str {
+        "Add a caption..."
+    }
+
+    fn max_rows(&self) -> usize {
+        3
+    }
+
+    fn props(&self) -> Value {
+        json!({ "file": self.file, "width": self.width })
+    }
+
+    fn attachment(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    fn set_attachment(&mut self, file: &str, path: PathBuf) {
+        self.file = Some(file.to_string());
+        self.path = Some(path);
+    }
+
+    fn display_width(&self) -> Option<u32> {
+        Some(self.width)
+    }
+
+    fn set_display_width(&mut self, percent: u32) {
+        self.width = percent.clamp(Self::MIN_WIDTH, Self::MAX_WIDTH);
+    }
+
+    fn render_view(&self, text: &str) -> AnyElement {
+        div()
+            .flex()
+            .flex_col()
+            .items_center()
+            .gap_1()
+            .child(self.render_image())
+            .child(
+                div()
+                    .text_sm()
+                    .italic()
+                    .text_color(rgb(0x9399b2))
+                    .child(text.to_string()),
+            )
+            .into_any_element()
+    }
+
+    fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
+        div()
+            .flex()
+            .flex_col()
+            .items_center()
+            .gap_1()
+            .child(self.render_image())
+            .child(div().w_full().text_sm().child(Input::new(input)))
+            .into_any_element()
+    }
+}
@@ -0,0 +1,133 @@
+use gpui::{prelude::FluentBuilder, *};
+use gpui_component::input::{Input, InputState};
+use serde_json::{json, Value};
+
+use super::{BlockContent, Marks};
+
+/// The marker style of a list item
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ListStyle {
+    Bullet,
+    Numbered,
+    Todo,
+}
+
+impl ListStyle {
+    /// Get the kind string for storage
+    pub fn kind_string(self) -> &'static str {
+        match self {
+            ListStyle::Bullet => "bullet",
+            ListStyle::Numbered => "numbered",
+            ListStyle::Todo => "todo",
+        }
+    }
+}
+
+/// A single list item; consecutive items form a list
+pub struct ListBlock {
+    style: ListStyle,
+    checked: bool,
+    marks: Marks,
+}
+
+impl ListBlock {
+    pub fn new(style: ListStyle) -> Self {
+        Self {
+            style,
+            checked: false,
+            marks: Marks::default(),
+        }
+    }
+
+    /// Restore a list item from stored properties
+    pub fn from_props(style: ListStyle, props: &Value) -> Self {
+        let checked = props
+            .get("checked")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        Self {
+            style,
+            checked,
+            marks: Marks::default(),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.style == ListStyle::Todo && self.checked
+    }
+}
+
+impl BlockContent for ListBlock {
+    fn type_name(&self) -> &'static str {
+        match self.style {
+            ListStyle::Bullet => "Bullet",
+            ListStyle::Numbered => "Numbered",
+            ListStyle::Todo => "Todo",
+        }
+    }
+
+    fn placeholder(&self) -> &'static str {
+        match self.style {
+            ListStyle::Todo => "To-do",
+            _ => "List item",
+        }
+    }
+
+    fn max_rows(&self) -> usize {
+        1 // Enter starts the next item instead of a new line
+    }
+
+    fn splits_on_enter(&self) -> bool {
+        true
+    }
+
+    fn props(&self) -> Value {
+        let props = match self.style {
+            ListStyle::Todo => json!({ "checked": self.checked }),
+            _ => Value::Null,
+        };
+        self.marks.add_to_props(props)
+    }
+
+    fn on_text_changed(&mut self, text: &str) -> Option<String> {
+        self.marks.update_text(text);
+        None
+    }
+
+    fn marks(&self) -> Option<&Marks> {
+        Some(&self.marks)
+    }
+
+    fn marks_mut(&mut self) -> Option<&mut Marks> {
+        Some(&mut self.marks)
+    }
+
+    fn list_style(&self) -> Option<ListStyle> {
+        Some(self.style)
+    }
+
+    fn is_checked(&self) -> bool {
+        self.checked
+    }
+
+    fn set_checked(&mut self, checked: bool) {
+        if self.style == ListStyle::Todo {
+            self.checked = checked;
+        }
+    }
+
+    fn render_view(&self, text: &str) -> AnyElement {
+        div()
+            .when(self.is_done(), |this| {
+                this.line_through().text_color(rgb(0x6c7086))
+            })
+            .child(self.marks.render("marks".into(), text))
+            .into_any_element()
+    }
+
+    fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
+        div()
+            .child(Input::new(input))
+            .into_any_element()
+    }
+}
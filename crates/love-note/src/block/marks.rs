@@ -0,0 +1,432 @@
+use std::ops::Range;
+
+use gpui::*;
+use love_note_plugin_api::TextElement;
+use serde_json::{json, Value};
+
+/// Colors offered for colored text, cycled through by the shortcut
+pub const TEXT_COLORS: &[u32] = &[0xf38ba8, 0xfab387, 0xf9e2af, 0xa6e3a1, 0x89b4fa, 0xcba6f7];
+
+/// Inline formatting applied to a span of text
+#[derive(Clone, PartialEq, Debug)]
+pub enum MarkKind {
+    Bold,
+    Italic,
+    Code,
+    Strikethrough,
+    Link(String),
+    Color(u32),
+}
+
+impl MarkKind {
+    fn name(&self) -> &'static str {
+        match self {
+            MarkKind::Bold => "bold",
+            MarkKind::Italic => "italic",
+            MarkKind::Code => "code",
+            MarkKind::Strikethrough => "strikethrough",
+            MarkKind::Link(_) => "link",
+            MarkKind::Color(_) => "color",
+        }
+    }
+
+    /// Whether `other` is the same kind of formatting, ignoring its value
+    fn same_type(&self, other: &MarkKind) -> bool {
+        self.name() == other.name()
+    }
+}
+
+/// A formatted span, as byte offsets into the block text
+#[derive(Clone, PartialEq, Debug)]
+pub struct Mark {
+    pub range: Range<usize>,
+    pub kind: MarkKind,
+}
+
+impl Mark {
+    fn to_json(&self) -> Value {
+        let mut value = json!({
+            "type": self.kind.name(),
+            "start": self.range.start,
+            "end": self.range.end,
+        });
+        match &self.kind {
+            MarkKind::Link(url) => value["url"] = json!(url),
+            MarkKind::Color(color) => value["color"] = json!(format!("#{:06x}", color)),
+            _ => {}
+        }
+        value
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let start = value.get("start")?.as_u64()? as usize;
+        let end = value.get("end")?.as_u64()? as usize;
+        let kind = match value.get("type")?.as_str()? {
+            "bold" => MarkKind::Bold,
+            "italic" => MarkKind::Italic,
+            "code" => MarkKind::Code,
+            "strikethrough" => MarkKind::Strikethrough,
+            "link" => MarkKind::Link(value.get("url")?.as_str()?.to_string()),
+            "color" => {
+                let hex = value.get("color")?.as_str()?.trim_start_matches('#');
+                MarkKind::Color(u32::from_str_radix(hex, 16).ok()?)
+            }
+            _ => return None,
+        };
+        Some(Self {
+            range: start..end,
+            kind,
+        })
+    }
+}
+
+/// Inline formatting of a block's text. Marks are kept in step with the
+/// text as it is edited.
+#[derive(Clone, Default, Debug)]
+pub struct Marks {
+    marks: Vec<Mark>,
+    /// Text the marks currently refer to
+    text: String,
+}
+
+impl Marks {
+    /// Text the marks currently refer to
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Restore marks from stored properties. Marks that don't fit `text`
+    /// are dropped.
+    pub fn from_props(props: &Value, text: &str) -> Self {
+        let marks = props
+            .get("marks")
+            .and_then(Value::as_array)
+            .map(|marks| marks.iter().filter_map(Mark::from_json).collect())
+            .unwrap_or_default();
+        let mut marks = Self {
+            marks,
+            text: text.to_string(),
+        };
+        marks.normalize();
+        marks
+    }
+
+    /// Stored form, or `None` when the text is unformatted
+    pub fn to_props(&self) -> Option<Value> {
+        if self.marks.is_empty() {
+            return None;
+        }
+        Some(Value::Array(self.marks.iter().map(Mark::to_json).collect()))
+    }
+
+    /// `props` with the marks added under `marks`, for blocks that store
+    /// other properties as well
+    pub fn add_to_props(&self, props: Value) -> Value {
+        let Some(marks) = self.to_props() else {
+            return props;
+        };
+        let mut props = match props {
+            Value::Object(_) => props,
+            _ => json!({}),
+        };
+        props["marks"] = marks;
+        props
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Mark> {
+        self.marks.iter()
+    }
+
+    /// Shift the marks to follow an edit that turned the old text into
+    /// `text`. Typing at the end of a mark extends it; typing at its
+    /// start does not.
+    pub fn update_text(&mut self, text: &str) {
+        if self.text == text {
+            return;
+        }
+        let old = std::mem::replace(&mut self.text, text.to_string());
+
+        let prefix = common_prefix(&old, text);
+        let suffix = common_suffix(&old[prefix..], &text[prefix..]);
+        let old_end = old.len() - suffix;
+        let new_end = text.len() - suffix;
+
+        let shift = |pos: usize| pos + new_end - old_end;
+        for mark in &mut self.marks {
+            let Range { start, end } = mark.range;
+            let start = match start {
+                s if s < prefix => s,
+                s if s >= old_end => shift(s),
+                _ => new_end,
+            };
+            let end = match end {
+                e if e < prefix => e,
+                e if e >= old_end => shift(e),
+                _ => prefix,
+            };
+            mark.range = start..end;
+        }
+        self.normalize();
+    }
+
+    /// The formatting of `range` of the text, as marks on that part alone
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        let marks = self
+            .marks
+            .iter()
+            .filter(|m| m.range.start < range.end && range.start < m.range.end)
+            .map(|m| Mark {
+                range: m.range.start.max(range.start) - range.start
+                    ..m.range.end.min(range.end) - range.start,
+                kind: m.kind.clone(),
+            })
+            .collect();
+        let mut sliced = Self {
+            marks,
+            text: self.text.get(range).unwrap_or_default().to_string(),
+        };
+        sliced.normalize();
+        sliced
+    }
+
+    /// Add `other`'s text and formatting after this text
+    pub fn append(&mut self, other: &Marks) {
+        let offset = self.text.len();
+        self.text.push_str(&other.text);
+        self.marks.extend(other.marks.iter().map(|m| Mark {
+            range: m.range.start + offset..m.range.end + offset,
+            kind: m.kind.clone(),
+        }));
+        self.normalize();
+    }
+
+    /// Whether all of `range` carries formatting of the same type as `kind`
+    pub fn covers(&self, range: &Range<usize>, kind: &MarkKind) -> bool {
+        let mut pos = range.start;
+        let mut spans: Vec<&Range<usize>> = self
+            .marks
+            .iter()
+            .filter(|m| m.kind.same_type(kind))
+            .map(|m| &m.range)
+            .collect();
+        spans.sort_by_key(|r| r.start);
+        for span in spans {
+            if span.start > pos {
+                break;
+            }
+            pos = pos.max(span.end);
+        }
+        pos >= range.end
+    }
+
+    /// Remove formatting of the same type as `kind` from `range`
+    pub fn remove(&mut self, range: Range<usize>, kind: &MarkKind) {
+        let mut kept = Vec::new();
+        for mark in self.marks.drain(..) {
+            let overlaps = mark.range.start < range.end && range.start < mark.range.end;
+            if !overlaps || !mark.kind.same_type(kind) {
+                kept.push(mark);
+                continue;
+            }
+            if mark.range.start < range.start {
+                kept.push(Mark {
+                    range: mark.range.start..range.start,
+                    kind: mark.kind.clone(),
+                });
+            }
+            if range.end < mark.range.end {
+                kept.push(Mark {
+                    range: range.end..mark.range.end,
+                    kind: mark.kind,
+                });
+            }
+        }
+        self.marks = kept;
+    }
+
+    /// Apply `kind` to `range`, replacing any other value of that type
+    pub fn add(&mut self, range: Range<usize>, kind: MarkKind) {
+        self.remove(range.clone(), &kind);
+        self.marks.push(Mark { range, kind });
+        self.normalize();
+    }
+
+    /// Add `kind` to `range`, or remove it if the whole range already has
+    /// it. Returns false when the range is empty.
+    pub fn toggle(&mut self, range: Range<usize>, kind: MarkKind) -> bool {
+        let range = self.snap(range);
+        if range.is_empty() {
+            return false;
+        }
+        if self.covers(&range, &kind) {
+            self.remove(range, &kind);
+        } else {
+            self.add(range, kind);
+        }
+        true
+    }
+
+    /// The value of a mark of the same type as `kind` at `range.start`
+    pub fn kind_at(&self, range: &Range<usize>, kind: &MarkKind) -> Option<&MarkKind> {
+        self.marks
+            .iter()
+            .find(|m| m.kind.same_type(kind) && m.range.contains(&range.start))
+            .map(|m| &m.kind)
+    }
+
+    /// The text split into runs of uniform formatting
+    pub fn spans(&self) -> Vec<TextElement> {
+        let mut bounds = vec![0, self.text.len()];
+        for mark in &self.marks {
+            bounds.push(mark.range.start);
+            bounds.push(mark.range.end);
+        }
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        bounds
+            .windows(2)
+            .map(|pair| {
+                let range = pair[0]..pair[1];
+                let mut span = TextElement {
+                    content: self.text[range.clone()].to_string(),
+                    bold: false,
+                    italic: false,
+                    code: false,
+                    strikethrough: false,
+                    link: None,
+                    color: None,
+                };
+                let covering = self
+                    .marks
+                    .iter()
+                    .filter(|m| m.range.start <= range.start && range.end <= m.range.end);
+                for mark in covering {
+                    match &mark.kind {
+                        MarkKind::Bold => span.bold = true,
+                        MarkKind::Italic => span.italic = true,
+                        MarkKind::Code => span.code = true,
+                        MarkKind::Strikethrough => span.strikethrough = true,
+                        MarkKind::Link(url) => span.link = Some(url.clone()),
+                        MarkKind::Color(color) => span.color = Some(format!("#{:06x}", color)),
+                    }
+                }
+                span
+            })
+            .collect()
+    }
+
+    /// Render `text` with its formatting. Links open in the browser.
+    pub fn render(&self, id: ElementId, text: &str) -> AnyElement {
+        if self.marks.is_empty() || text != self.text {
+            return div().child(text.to_string()).into_any_element();
+        }
+
+        let mut highlights = Vec::new();
+        let mut links = Vec::new();
+        let mut pos = 0;
+        for span in self.spans() {
+            let range = pos..pos + span.content.len();
+            pos = range.end;
+
+            let mut style = HighlightStyle::default();
+            if span.bold {
+                style.font_weight = Some(FontWeight::BOLD);
+            }
+            if span.italic {
+                style.font_style = Some(FontStyle::Italic);
+            }
+            if span.code {
+                style.color = Some(rgb(0xfab387).into());
+                style.background_color = Some(rgb(0x313244).into());
+            }
+            if span.strikethrough {
+                style.strikethrough = Some(StrikethroughStyle {
+                    thickness: px(1.),
+                    color: None,
+                });
+            }
+            if let Some(color) = &span.color
+                && let Ok(color) = u32::from_str_radix(color.trim_start_matches('#'), 16)
+            {
+                style.color = Some(rgb(color).into());
+            }
+            if let Some(url) = span.link {
+                style.color = Some(rgb(0x89b4fa).into());
+                style.underline = Some(UnderlineStyle {
+                    thickness: px(1.),
+                    color: None,
+                    wavy: false,
+                });
+                links.push((range.clone(), url));
+            }
+            if style != HighlightStyle::default() {
+                highlights.push((range, style));
+            }
+        }
+
+        let styled = StyledText::new(text.to_string()).with_highlights(highlights);
+        let (ranges, urls): (Vec<_>, Vec<_>) = links.into_iter().unzip();
+        InteractiveText::new(id, styled)
+            .on_click(ranges, move |ix, _window, cx| cx.open_url(&urls[ix]))
+            .into_any_element()
+    }
+
+    /// Clamp a selection to the text and to character boundaries
+    pub fn snap(&self, range: Range<usize>) -> Range<usize> {
+        let snap = |mut pos: usize| {
+            pos = pos.min(self.text.len());
+            while !self.text.is_char_boundary(pos) {
+                pos -= 1;
+            }
+            pos
+        };
+        snap(range.start)..snap(range.end)
+    }
+
+    /// Drop empty and out-of-range marks and merge touching marks of the
+    /// same kind
+    fn normalize(&mut self) {
+        let len = self.text.len();
+        self.marks.retain(|m| {
+            m.range.start < m.range.end
+                && m.range.end <= len
+                && self.text.is_char_boundary(m.range.start)
+                && self.text.is_char_boundary(m.range.end)
+        });
+        self.marks.sort_by_key(|m| (m.kind.name(), m.range.start));
+
+        let mut merged: Vec<Mark> = Vec::new();
+        for mark in self.marks.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.kind == mark.kind && mark.range.start <= last.range.end => {
+                    last.range.end = last.range.end.max(mark.range.end);
+                }
+                _ => merged.push(mark),
+            }
+        }
+        self.marks = merged;
+    }
+}
+
+/// Length in bytes of the longest common prefix, on a character boundary
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map_or(a.len().min(b.len()), |((i, _), _)| i)
+}
+
+/// Length in bytes of the longest common suffix, on a character boundary
+fn common_suffix(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x.len_utf8())
+        .sum()
+}
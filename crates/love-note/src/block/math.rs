@@ -0,0 +1,176 @@
+use gpui::*;
+use gpui_component::input::{Input, InputState};
+
+use super::parse::latex::{self, MathNode};
+use super::{BlockContent, MONO_FONT};
+
+/// Base font size for display equations
+const MATH_SIZE: f32 = 20.;
+/// Scale applied to sub/superscripts and fraction parts
+const SCRIPT_SCALE: f32 = 0.7;
+/// Smallest size nested scripts shrink to
+const MIN_SIZE: f32 = 10.;
+
+/// A display equation written in LaTeX
+pub struct MathBlock;
+
+impl MathBlock {
+    /// Typeset LaTeX source, falling back to the raw source on parse errors
+    fn render_math(source: &str) -> AnyElement {
+        match latex::parse(source) {
+            Ok(nodes) => render_row(&nodes, MATH_SIZE)
+                .justify_center()
+                .into_any_element(),
+            Err(message) => div()
+                .flex()
+                .flex_col()
+                .items_center()
+                .font_family(MONO_FONT)
+                .text_sm()
+                .child(source.to_string())
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0xf38ba8))
+                        .child(message),
+                )
+                .into_any_element(),
+        }
+    }
+}
+
+impl BlockContent for MathBlock {
+    fn type_name(&self) -> &'static str {
+        "Math"
+    }
+
+    fn placeholder(&self) -> &'static str {
+        "E = mc^2"
+    }
+
+    fn max_rows(&self) -> usize {
+        10
+    }
+
+    fn is_numbered(&self, text: &str) -> bool {
+        !latex::is_unnumbered(text)
+    }
+
+    fn render_view(&self, text: &str) -> AnyElement {
+        div()
+            .py_2()
+            .child(Self::render_math(text))
+            .into_any_element()
+    }
+
+    fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
+        div()
+            .font_family(MONO_FONT)
+            .text_sm()
+            .child(Input::new(input))
+            .into_any_element()
+    }
+
+    fn render_preview(&self, text: &str) -> Option<AnyElement> {
+        if text.trim().is_empty() {
+            return None;
+        }
+        Some(
+            div()
+                .py_2()
+                .border_t_1()
+                .border_color(rgb(0x313244))
+                .child(Self::render_math(text))
+                .into_any_element(),
+        )
+    }
+}
+
+/// Lay out a list of nodes on one line
+fn render_row(nodes: &[MathNode], size: f32) -> Div {
+    div()
+        .flex()
+        .flex_row()
+        .items_center()
+        .text_size(px(size))
+        .children(nodes.iter().map(|node| render_node(node, size)))
+}
+
+fn script_size(size: f32) -> f32 {
+    (size * SCRIPT_SCALE).max(MIN_SIZE)
+}
+
+fn render_node(node: &MathNode, size: f32) -> AnyElement {
+    match node {
+        MathNode::Ident(s) => div().italic().child(s.clone()).into_any_element(),
+        MathNode::Number(s) | MathNode::Symbol(s) | MathNode::Text(s) => {
+            div().child(s.clone()).into_any_element()
+        }
+        MathNode::Operator(s) => div()
+            .px(px(size * 0.22))
+            .child(s.clone())
+            .into_any_element(),
+        MathNode::Bold(nodes) => render_row(nodes, size)
+            .font_weight(FontWeight::BOLD)
+            .into_any_element(),
+        MathNode::Group(nodes) => render_row(nodes, size).into_any_element(),
+        MathNode::Scripts { base, sub, sup } => {
+            let small = script_size(size);
+            // Stack the scripts so the superscript sits above the base's
+            // center line and the subscript below it
+            let spacer = || div().h(px(small * 0.6));
+            let scripts = div()
+                .flex()
+                .flex_col()
+                .items_start()
+                .child(match sup {
+                    Some(sup) => render_row(sup, small).into_any_element(),
+                    None => spacer().into_any_element(),
+                })
+                .child(match sub {
+                    Some(sub) => render_row(sub, small).into_any_element(),
+                    None => spacer().into_any_element(),
+                });
+
+            div()
+                .flex()
+                .flex_row()
+                .items_center()
+                .child(render_node(base, size))
+                .child(scripts)
+                .into_any_element()
+        }
+        MathNode::Frac(numerator, denominator) => {
+            let small = script_size(size);
+            div()
+                .flex()
+                .flex_col()
+                .items_center()
+                .px(px(size * 0.15))
+                .child(render_row(numerator, small))
+                .child(div().h(px(1.)).w_full().bg(rgb(0xcdd6f4)))
+                .child(render_row(denominator, small))
+                .into_any_element()
+        }
+        MathNode::Sqrt { index, radicand } => {
+            let small = script_size(script_size(size));
+            div()
+                .flex()
+                .flex_row()
+                .items_end()
+                .children(index.as_ref().map(|index| render_row(index, small)))
+                .child(div().child("√"))
+                .child(
+                    render_row(radicand, size)
+                        .border_t_1()
+                        .border_color(rgb(0xcdd6f4)),
+                )
+                .into_any_element()
+        }
+        MathNode::Space(em) => div().w(px(size * em)).into_any_element(),
+        MathNode::Unknown(s) => div()
+            .text_color(rgb(0xf38ba8))
+            .child(s.clone())
+            .into_any_element(),
+    }
+}
@@ -1,14 +1,48 @@
+mod callout;
+mod chart;
+mod chemistry;
+mod citation;
+mod code;
+mod divider;
 mod heading;
+mod image;
+mod list;
+mod marks;
+mod math;
+/// Parsers for the source of math and chemistry blocks
+pub(crate) mod parse;
+mod quote;
+mod table;
 mod text;
+mod toggle;
 
+pub use callout::{CalloutBlock, CalloutStyle};
+pub use chart::{ChartBlock, ChartKind, ChartSpec, Plot};
+pub use chemistry::ChemistryBlock;
+pub use citation::{Citation, CitationBlock};
+pub use code::{CodeBlock, LANGUAGES, MONO_FONT};
+pub use divider::DividerBlock;
 pub use heading::HeadingBlock;
+pub use image::ImageBlock;
+pub use list::{ListBlock, ListStyle};
+pub use marks::{Mark, MarkKind, Marks, TEXT_COLORS};
+pub use math::MathBlock;
+pub use quote::QuoteBlock;
+pub use table::{Column, ColumnType, TableBlock, TableData, DATE_FORMAT};
 pub use text::TextBlock;
+pub use toggle::ToggleBlock;
 
+use std::ops::Range;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
 use gpui::*;
-use gpui_component::input::InputState;
+use gpui_component::input::{InputState, Position};
+use love_note_plugin_api::BlockKindInfo;
+use serde_json::Value;
 use uuid::Uuid;
 
-use crate::storage::StoredBlock;
+use crate::storage::{Storage, StoredBlock};
 
 /// Trait that all block types must implement
 pub trait BlockContent: 'static {
@@ -23,6 +57,120 @@ pub trait BlockContent: 'static {
         20
     }
 
+    /// Adjusts the input state for this block type (e.g. tab handling)
+    fn configure_input(&self, state: InputState) -> InputState {
+        state
+    }
+
+    /// Returns kind-specific properties to persist alongside the text
+    fn props(&self) -> Value {
+        Value::Null
+    }
+
+    /// Called after the text changes; returns replacement text when the
+    /// block consumed part of the input (e.g. a Markdown shortcut)
+    fn on_text_changed(&mut self, _text: &str) -> Option<String> {
+        None
+    }
+
+    /// Returns the list style when the block is a list item
+    fn list_style(&self) -> Option<ListStyle> {
+        None
+    }
+
+    /// Returns whether a todo item is checked
+    fn is_checked(&self) -> bool {
+        false
+    }
+
+    /// Sets the checked state of a todo item
+    fn set_checked(&mut self, _checked: bool) {}
+
+    /// Returns the language id when the block holds source code
+    fn code_language(&self) -> Option<&str> {
+        None
+    }
+
+    /// Sets the language of a code block
+    fn set_code_language(&mut self, _language: &str) {}
+
+    /// Returns the attachment file name the block references
+    fn attachment(&self) -> Option<&str> {
+        None
+    }
+
+    /// Points the block at a stored attachment file
+    fn set_attachment(&mut self, _file: &str, _path: PathBuf) {}
+
+    /// Returns the display width in percent for resizable blocks
+    fn display_width(&self) -> Option<u32> {
+        None
+    }
+
+    /// Sets the display width in percent
+    fn set_display_width(&mut self, _percent: u32) {}
+
+    /// Returns the structured content of a table block
+    fn table(&self) -> Option<&TableData> {
+        None
+    }
+
+    /// Returns mutable structured content of a table block
+    fn table_mut(&mut self) -> Option<&mut TableData> {
+        None
+    }
+
+    /// Returns the style of a callout block
+    fn callout_style(&self) -> Option<CalloutStyle> {
+        None
+    }
+
+    /// Sets the style of a callout block
+    fn set_callout_style(&mut self, _style: CalloutStyle) {}
+
+    /// Returns the chart settings of a chart block
+    fn chart(&self) -> Option<&ChartSpec> {
+        None
+    }
+
+    /// Returns mutable chart settings of a chart block
+    fn chart_mut(&mut self) -> Option<&mut ChartSpec> {
+        None
+    }
+
+    /// Returns the inline formatting of blocks with rich text
+    fn marks(&self) -> Option<&Marks> {
+        None
+    }
+
+    /// Returns mutable inline formatting of blocks with rich text
+    fn marks_mut(&mut self) -> Option<&mut Marks> {
+        None
+    }
+
+    /// Returns whether Enter splits the block into two rather than
+    /// starting a new line
+    fn splits_on_enter(&self) -> bool {
+        false
+    }
+
+    /// Returns whether the block is a toggle that can collapse the blocks
+    /// nested in it
+    fn is_toggle(&self) -> bool {
+        false
+    }
+
+    /// Returns whether the block takes a number in the document's
+    /// equation sequence
+    fn is_numbered(&self, _text: &str) -> bool {
+        false
+    }
+
+    /// Renders a live preview shown below the editor while focused
+    fn render_preview(&self, _text: &str) -> Option<AnyElement> {
+        None
+    }
+
     /// Renders the block in view mode (when not focused)
     fn render_view(&self, text: &str) -> AnyElement;
 
@@ -35,6 +183,11 @@ pub struct Block {
     pub id: Uuid,
     pub kind: String,
     pub input: Entity<InputState>,
+    /// Toggle block this block is nested in
+    pub parent: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// Last change to the text or properties
+    pub updated_at: DateTime<Utc>,
     content: Box<dyn BlockContent>,
 }
 
@@ -45,49 +198,110 @@ impl Block {
         window: &mut Window,
         cx: &mut Context<T>,
     ) -> Self {
-        let placeholder = content.placeholder();
-        let max_rows = content.max_rows();
         let kind = content.type_name().to_lowercase();
-
-        let input = cx.new(|cx| {
-            InputState::new(window, cx)
-                .placeholder(placeholder)
-                .auto_grow(1, max_rows)
-        });
-
-        Self {
-            id: Uuid::new_v4(),
-            kind,
-            input,
-            content: Box::new(content),
-        }
+        Self::with_content(Uuid::new_v4(), kind, Box::new(content), "", window, cx)
     }
 
     /// Create a block from stored data
     pub fn from_stored<T>(
         stored: &StoredBlock,
+        storage: &Storage,
         window: &mut Window,
         cx: &mut Context<T>,
     ) -> Self {
-        let (content, placeholder, max_rows): (Box<dyn BlockContent>, &str, usize) =
-            match stored.kind.as_str() {
-                "heading" => (Box::new(HeadingBlock), HeadingBlock.placeholder(), HeadingBlock.max_rows()),
-                _ => (Box::new(TextBlock), TextBlock.placeholder(), TextBlock.max_rows()),
-            };
+        let mut content: Box<dyn BlockContent> = match stored.kind.as_str() {
+            "heading" => Box::new(HeadingBlock::from_props(&stored.props)),
+            "bullet" => Box::new(ListBlock::from_props(ListStyle::Bullet, &stored.props)),
+            "numbered" => Box::new(ListBlock::from_props(ListStyle::Numbered, &stored.props)),
+            "todo" => Box::new(ListBlock::from_props(ListStyle::Todo, &stored.props)),
+            "code" => Box::new(CodeBlock::from_props(&stored.props)),
+            "image" => Box::new(ImageBlock::from_props(&stored.props, storage.images())),
+            "math" => Box::new(MathBlock),
+            "table" => Box::new(TableBlock::from_props(&stored.props)),
+            "chart" => Box::new(ChartBlock::from_props(&stored.props)),
+            "chemistry" => Box::new(ChemistryBlock),
+            "citation" => Box::new(CitationBlock),
+            "callout" => Box::new(CalloutBlock::from_props(&stored.props)),
+            "quote" => Box::new(QuoteBlock::default()),
+            "divider" => Box::new(DividerBlock),
+            "toggle" => Box::new(ToggleBlock::default()),
+            _ => Box::new(TextBlock::from_props(&stored.props, &stored.content)),
+        };
+        if let Some(marks) = content.marks_mut() {
+            *marks = Marks::from_props(&stored.props, &stored.content);
+        }
 
-        let initial_content = stored.content.clone();
+        let mut block = Self::with_content(
+            stored.id,
+            stored.kind.clone(),
+            content,
+            &stored.content,
+            window,
+            cx,
+        );
+        block.parent = stored.parent;
+        block.created_at = stored.created_at;
+        block.updated_at = stored.updated_at;
+        block
+    }
+
+    /// Create a block of another kind that takes this block's place,
+    /// keeping its id, nesting, timestamps and, when both kinds hold rich
+    /// text, formatting, and holding `text`
+    pub fn converted<T>(
+        &self,
+        kind: BlockKind,
+        text: &str,
+        window: &mut Window,
+        cx: &mut Context<T>,
+    ) -> Self {
+        let mut block = kind.create_block(window, cx);
+        block.id = self.id;
+        block.parent = self.parent;
+        // Let the new kind pick up any shortcut left in the text, such as
+        // a heading's level
+        block.set_content(text, window, cx);
+        if let Some(marks) = self.marks() {
+            // Shift the marks past any shortcut the new kind took out
+            let mut marks = marks.clone();
+            marks.update_text(&block.get_content(cx));
+            if let Some(target) = block.content.marks_mut() {
+                *target = marks;
+            }
+        }
+        block.created_at = self.created_at;
+        block.updated_at = self.updated_at;
+        block
+    }
+
+    fn with_content<T>(
+        id: Uuid,
+        kind: String,
+        content: Box<dyn BlockContent>,
+        initial_content: &str,
+        window: &mut Window,
+        cx: &mut Context<T>,
+    ) -> Self {
+        let placeholder = content.placeholder();
+        let max_rows = content.max_rows();
+
+        let initial_content = initial_content.to_string();
         let input = cx.new(|cx| {
-            let mut state = InputState::new(window, cx)
+            let state = InputState::new(window, cx)
                 .placeholder(placeholder)
                 .auto_grow(1, max_rows);
+            let mut state = content.configure_input(state);
             state.set_value(&initial_content, window, cx);
             state
         });
 
         Self {
-            id: stored.id,
-            kind: stored.kind.clone(),
+            id,
+            kind,
             input,
+            parent: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
             content,
         }
     }
@@ -97,19 +311,197 @@ impl Block {
         self.content.type_name()
     }
 
+    /// Get the list style if this block is a list item
+    pub fn list_style(&self) -> Option<ListStyle> {
+        self.content.list_style()
+    }
+
+    /// Get whether this todo item is checked
+    pub fn is_checked(&self) -> bool {
+        self.content.is_checked()
+    }
+
+    /// Set the checked state of this todo item
+    pub fn set_checked(&mut self, checked: bool) {
+        self.touch();
+        self.content.set_checked(checked);
+    }
+
+    /// Get the language id if this block is a code block
+    pub fn code_language(&self) -> Option<&str> {
+        self.content.code_language()
+    }
+
+    /// Set the language of this code block
+    pub fn set_code_language(&mut self, language: &str) {
+        self.touch();
+        self.content.set_code_language(language);
+    }
+
+    /// Get the attachment file name this block references
+    pub fn attachment(&self) -> Option<&str> {
+        self.content.attachment()
+    }
+
+    /// Point this block at a stored attachment file
+    pub fn set_attachment(&mut self, file: &str, path: PathBuf) {
+        self.touch();
+        self.content.set_attachment(file, path);
+    }
+
+    /// Get the display width in percent for resizable blocks
+    pub fn display_width(&self) -> Option<u32> {
+        self.content.display_width()
+    }
+
+    /// Set the display width in percent
+    pub fn set_display_width(&mut self, percent: u32) {
+        self.touch();
+        self.content.set_display_width(percent);
+    }
+
+    /// Get the table content if this block is a table
+    pub fn table(&self) -> Option<&TableData> {
+        self.content.table()
+    }
+
+    /// Get mutable table content if this block is a table
+    pub fn table_mut(&mut self) -> Option<&mut TableData> {
+        self.touch();
+        self.content.table_mut()
+    }
+
+    /// Get the style if this block is a callout
+    pub fn callout_style(&self) -> Option<CalloutStyle> {
+        self.content.callout_style()
+    }
+
+    /// Set the style of this callout
+    pub fn set_callout_style(&mut self, style: CalloutStyle) {
+        self.touch();
+        self.content.set_callout_style(style);
+    }
+
+    /// Get the chart settings if this block is a chart
+    pub fn chart(&self) -> Option<&ChartSpec> {
+        self.content.chart()
+    }
+
+    /// Get mutable chart settings if this block is a chart
+    pub fn chart_mut(&mut self) -> Option<&mut ChartSpec> {
+        self.touch();
+        self.content.chart_mut()
+    }
+
+    /// Get the inline formatting if this block holds rich text
+    pub fn marks(&self) -> Option<&Marks> {
+        self.content.marks()
+    }
+
+    /// Get mutable inline formatting if this block holds rich text
+    pub fn marks_mut(&mut self) -> Option<&mut Marks> {
+        self.touch();
+        self.content.marks_mut()
+    }
+
+    /// Get whether Enter splits this block
+    pub fn splits_on_enter(&self) -> bool {
+        self.content.splits_on_enter()
+    }
+
+    /// Get whether this block is a toggle
+    pub fn is_toggle(&self) -> bool {
+        self.content.is_toggle()
+    }
+
+    /// Record that the block changed
+    fn touch(&mut self) {
+        self.updated_at = Utc::now();
+    }
+
+    /// Get whether this block is a numbered equation
+    pub fn is_numbered(&self, cx: &App) -> bool {
+        self.content.is_numbered(&self.get_content(cx))
+    }
+
+    /// Move keyboard focus into this block
+    pub fn focus(&self, window: &mut Window, cx: &App) {
+        self.input.read(cx).focus_handle(cx).focus(window);
+    }
+
+    /// Move keyboard focus into this block with the cursor at byte
+    /// `offset` of its text
+    pub fn focus_at(&self, offset: usize, window: &mut Window, cx: &mut App) {
+        let text = self.get_content(cx);
+        let before = &text[..offset.min(text.len())];
+        let line = before.matches('\n').count() as u32;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let character = before[line_start..].encode_utf16().count() as u32;
+
+        self.focus(window, cx);
+        self.input.update(cx, |state, cx| {
+            state.set_cursor_position(Position::new(line, character), window, cx);
+        });
+    }
+
+    /// Get the selected byte range of the text, if the block has focus
+    pub fn selection(&self, window: &mut Window, cx: &mut App) -> Option<Range<usize>> {
+        let text = self.get_content(cx);
+        let selection = self
+            .input
+            .update(cx, |state, cx| state.selected_text_range(false, window, cx))?;
+
+        // The platform input handler counts in UTF-16 code units
+        let byte_offset = |utf16: usize| {
+            let mut units = 0;
+            for (i, c) in text.char_indices() {
+                if units >= utf16 {
+                    return i;
+                }
+                units += c.len_utf16();
+            }
+            text.len()
+        };
+        Some(byte_offset(selection.range.start)..byte_offset(selection.range.end))
+    }
+
+    /// Replace the text, letting the content react as if it were typed
+    pub fn set_content(&mut self, text: &str, window: &mut Window, cx: &mut App) {
+        self.input.update(cx, |state, cx| {
+            state.set_value(text.to_string(), window, cx);
+        });
+        self.handle_text_changed(window, cx);
+    }
+
     /// Get the current text content
     pub fn get_content(&self, cx: &App) -> String {
         self.input.read(cx).text().to_string()
     }
 
+    /// Let the content react to a text change, rewriting the input if it
+    /// consumed part of the text. Returns true if the block changed.
+    pub fn handle_text_changed(&mut self, window: &mut Window, cx: &mut App) -> bool {
+        self.touch();
+        let text = self.get_content(cx);
+        let Some(replacement) = self.content.on_text_changed(&text) else {
+            return false;
+        };
+        self.input.update(cx, |state, cx| {
+            state.set_value(&replacement, window, cx);
+        });
+        true
+    }
+
     /// Convert to StoredBlock for persistence
     pub fn to_stored(&self, cx: &App) -> StoredBlock {
         StoredBlock {
             id: self.id,
             kind: self.kind.clone(),
             content: self.get_content(cx),
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
+            props: self.content.props(),
+            parent: self.parent,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
         }
     }
 
@@ -126,7 +518,9 @@ impl Block {
             .flex_col();
 
         if is_focused {
-            base.child(self.content.render_edit(&self.input)).into_any_element()
+            base.child(self.content.render_edit(&self.input))
+                .children(self.content.render_preview(&text_content))
+                .into_any_element()
         } else {
             let display_text = if text_content.is_empty() {
                 self.content.placeholder().to_string()
@@ -149,12 +543,43 @@ impl Block {
 pub enum BlockKind {
     Text,
     Heading,
+    Bullet,
+    Numbered,
+    Todo,
+    Code,
+    Image,
+    Math,
+    Table,
+    Chart,
+    Chemistry,
+    Citation,
+    Callout,
+    Quote,
+    Divider,
+    Toggle,
 }
 
 impl BlockKind {
     /// Get all available block kinds
     pub fn all() -> &'static [BlockKind] {
-        &[BlockKind::Heading, BlockKind::Text]
+        &[
+            BlockKind::Heading,
+            BlockKind::Text,
+            BlockKind::Bullet,
+            BlockKind::Numbered,
+            BlockKind::Todo,
+            BlockKind::Code,
+            BlockKind::Image,
+            BlockKind::Math,
+            BlockKind::Table,
+            BlockKind::Chart,
+            BlockKind::Chemistry,
+            BlockKind::Citation,
+            BlockKind::Callout,
+            BlockKind::Quote,
+            BlockKind::Divider,
+            BlockKind::Toggle,
+        ]
     }
 
     /// Get the display name for UI
@@ -162,6 +587,74 @@ impl BlockKind {
         match self {
             BlockKind::Text => "Text",
             BlockKind::Heading => "Heading",
+            BlockKind::Bullet => "Bullet List",
+            BlockKind::Numbered => "Numbered List",
+            BlockKind::Todo => "Todo",
+            BlockKind::Code => "Code",
+            BlockKind::Image => "Image",
+            BlockKind::Math => "Math",
+            BlockKind::Table => "Table",
+            BlockKind::Chart => "Chart",
+            BlockKind::Chemistry => "Chemistry",
+            BlockKind::Citation => "Citation",
+            BlockKind::Callout => "Callout",
+            BlockKind::Quote => "Quote",
+            BlockKind::Divider => "Divider",
+            BlockKind::Toggle => "Toggle",
+        }
+    }
+
+    /// Get a short description for menus
+    pub fn description(self) -> &'static str {
+        match self {
+            BlockKind::Text => "Plain paragraph",
+            BlockKind::Heading => "Section title",
+            BlockKind::Bullet => "Bulleted list item",
+            BlockKind::Numbered => "Numbered list item",
+            BlockKind::Todo => "Item with a checkbox",
+            BlockKind::Code => "Code with syntax highlighting",
+            BlockKind::Image => "Image from a file or the clipboard",
+            BlockKind::Math => "LaTeX equation",
+            BlockKind::Table => "Spreadsheet-style table",
+            BlockKind::Chart => "Plot of a table or inline data",
+            BlockKind::Chemistry => "Chemical formula or reaction",
+            BlockKind::Citation => "Reference written in BibTeX",
+            BlockKind::Callout => "Highlighted note",
+            BlockKind::Quote => "Quotation",
+            BlockKind::Divider => "Horizontal rule",
+            BlockKind::Toggle => "Collapsible section",
+        }
+    }
+
+    /// Get a glyph shown next to the name in menus
+    pub fn icon(self) -> &'static str {
+        match self {
+            BlockKind::Text => "¶",
+            BlockKind::Heading => "H",
+            BlockKind::Bullet => "•",
+            BlockKind::Numbered => "1.",
+            BlockKind::Todo => "☐",
+            BlockKind::Code => "</>",
+            BlockKind::Image => "🖼",
+            BlockKind::Math => "∑",
+            BlockKind::Table => "▦",
+            BlockKind::Chart => "📈",
+            BlockKind::Chemistry => "⚗",
+            BlockKind::Citation => "❞",
+            BlockKind::Callout => "ℹ",
+            BlockKind::Quote => "❝",
+            BlockKind::Divider => "—",
+            BlockKind::Toggle => "▸",
+        }
+    }
+
+    /// Describe this kind the way plugins describe theirs
+    pub fn info(self) -> BlockKindInfo {
+        BlockKindInfo {
+            kind: self.kind_string().to_string(),
+            name: self.display_name().to_string(),
+            icon: Some(self.icon().to_string()),
+            description: Some(self.description().to_string()),
         }
     }
 
@@ -170,14 +663,77 @@ impl BlockKind {
         match self {
             BlockKind::Text => "text",
             BlockKind::Heading => "heading",
+            BlockKind::Bullet => ListStyle::Bullet.kind_string(),
+            BlockKind::Numbered => ListStyle::Numbered.kind_string(),
+            BlockKind::Todo => ListStyle::Todo.kind_string(),
+            BlockKind::Code => "code",
+            BlockKind::Image => "image",
+            BlockKind::Math => "math",
+            BlockKind::Table => "table",
+            BlockKind::Chart => "chart",
+            BlockKind::Chemistry => "chemistry",
+            BlockKind::Citation => "citation",
+            BlockKind::Callout => "callout",
+            BlockKind::Quote => "quote",
+            BlockKind::Divider => "divider",
+            BlockKind::Toggle => "toggle",
+        }
+    }
+
+    /// Match a Markdown shortcut typed at the start of a text block.
+    ///
+    /// Returns the kind to convert to and the text that remains. Headings
+    /// keep their `#`s, which the heading block reads its level from.
+    pub fn from_shortcut(text: &str) -> Option<(BlockKind, &str)> {
+        const SHORTCUTS: &[(&str, BlockKind)] = &[
+            ("- ", BlockKind::Bullet),
+            ("* ", BlockKind::Bullet),
+            ("1. ", BlockKind::Numbered),
+            ("[] ", BlockKind::Todo),
+            ("[ ] ", BlockKind::Todo),
+            ("```", BlockKind::Code),
+            ("> ", BlockKind::Quote),
+        ];
+
+        if HeadingBlock::parse_shortcut(text).is_some() {
+            return Some((BlockKind::Heading, text));
+        }
+        if text == "---" {
+            return Some((BlockKind::Divider, ""));
+        }
+        SHORTCUTS.iter().find_map(|(prefix, kind)| {
+            text.strip_prefix(prefix).map(|rest| (*kind, rest))
+        })
+    }
+
+    /// Get the block kind for a list style
+    pub fn from_list_style(style: ListStyle) -> Self {
+        match style {
+            ListStyle::Bullet => BlockKind::Bullet,
+            ListStyle::Numbered => BlockKind::Numbered,
+            ListStyle::Todo => BlockKind::Todo,
         }
     }
 
     /// Create a block of this kind
     pub fn create_block<T>(self, window: &mut Window, cx: &mut Context<T>) -> Block {
         match self {
-            BlockKind::Text => Block::new(TextBlock, window, cx),
-            BlockKind::Heading => Block::new(HeadingBlock, window, cx),
+            BlockKind::Text => Block::new(TextBlock::default(), window, cx),
+            BlockKind::Heading => Block::new(HeadingBlock::default(), window, cx),
+            BlockKind::Bullet => Block::new(ListBlock::new(ListStyle::Bullet), window, cx),
+            BlockKind::Numbered => Block::new(ListBlock::new(ListStyle::Numbered), window, cx),
+            BlockKind::Todo => Block::new(ListBlock::new(ListStyle::Todo), window, cx),
+            BlockKind::Code => Block::new(CodeBlock::default(), window, cx),
+            BlockKind::Image => Block::new(ImageBlock::default(), window, cx),
+            BlockKind::Math => Block::new(MathBlock, window, cx),
+            BlockKind::Table => Block::new(TableBlock::default(), window, cx),
+            BlockKind::Chart => Block::new(ChartBlock::default(), window, cx),
+            BlockKind::Chemistry => Block::new(ChemistryBlock, window, cx),
+            BlockKind::Citation => Block::new(CitationBlock, window, cx),
+            BlockKind::Callout => Block::new(CalloutBlock::default(), window, cx),
+            BlockKind::Quote => Block::new(QuoteBlock::default(), window, cx),
+            BlockKind::Divider => Block::new(DividerBlock, window, cx),
+            BlockKind::Toggle => Block::new(ToggleBlock::default(), window, cx),
         }
     }
 }
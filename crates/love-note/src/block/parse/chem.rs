@@ -0,0 +1,469 @@
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Mass of an electron in g/mol, for half-reactions
+const ELECTRON_MASS: f64 = 0.000_548_58;
+
+/// Standard atomic weights in g/mol (IUPAC, abridged). Elements without
+/// stable isotopes use the mass number of their longest-lived isotope.
+#[rustfmt::skip]
+const ELEMENTS: &[(&str, f64)] = &[
+    ("H", 1.008), ("He", 4.0026), ("Li", 6.94), ("Be", 9.0122), ("B", 10.81),
+    ("C", 12.011), ("N", 14.007), ("O", 15.999), ("F", 18.998), ("Ne", 20.180),
+    ("Na", 22.990), ("Mg", 24.305), ("Al", 26.982), ("Si", 28.085), ("P", 30.974),
+    ("S", 32.06), ("Cl", 35.45), ("Ar", 39.95), ("K", 39.098), ("Ca", 40.078),
+    ("Sc", 44.956), ("Ti", 47.867), ("V", 50.942), ("Cr", 51.996), ("Mn", 54.938),
+    ("Fe", 55.845), ("Co", 58.933), ("Ni", 58.693), ("Cu", 63.546), ("Zn", 65.38),
+    ("Ga", 69.723), ("Ge", 72.630), ("As", 74.922), ("Se", 78.971), ("Br", 79.904),
+    ("Kr", 83.798), ("Rb", 85.468), ("Sr", 87.62), ("Y", 88.906), ("Zr", 91.224),
+    ("Nb", 92.906), ("Mo", 95.95), ("Tc", 98.), ("Ru", 101.07), ("Rh", 102.91),
+    ("Pd", 106.42), ("Ag", 107.87), ("Cd", 112.41), ("In", 114.82), ("Sn", 118.71),
+    ("Sb", 121.76), ("Te", 127.60), ("I", 126.90), ("Xe", 131.29), ("Cs", 132.91),
+    ("Ba", 137.33), ("La", 138.91), ("Ce", 140.12), ("Pr", 140.91), ("Nd", 144.24),
+    ("Pm", 145.), ("Sm", 150.36), ("Eu", 151.96), ("Gd", 157.25), ("Tb", 158.93),
+    ("Dy", 162.50), ("Ho", 164.93), ("Er", 167.26), ("Tm", 168.93), ("Yb", 173.05),
+    ("Lu", 174.97), ("Hf", 178.49), ("Ta", 180.95), ("W", 183.84), ("Re", 186.21),
+    ("Os", 190.23), ("Ir", 192.22), ("Pt", 195.08), ("Au", 196.97), ("Hg", 200.59),
+    ("Tl", 204.38), ("Pb", 207.2), ("Bi", 208.98), ("Po", 209.), ("At", 210.),
+    ("Rn", 222.), ("Fr", 223.), ("Ra", 226.), ("Ac", 227.), ("Th", 232.04),
+    ("Pa", 231.04), ("U", 238.03), ("Np", 237.), ("Pu", 244.), ("Am", 243.),
+    ("Cm", 247.), ("Bk", 247.), ("Cf", 251.), ("Es", 252.), ("Fm", 257.),
+    ("Md", 258.), ("No", 259.), ("Lr", 266.), ("Rf", 267.), ("Db", 268.),
+    ("Sg", 269.), ("Bh", 270.), ("Hs", 269.), ("Mt", 278.), ("Ds", 281.),
+    ("Rg", 282.), ("Cn", 285.), ("Nh", 286.), ("Fl", 289.), ("Mc", 290.),
+    ("Lv", 293.), ("Ts", 294.), ("Og", 294.),
+];
+
+/// Arrows accepted between the two sides of a reaction, longest first
+const ARROWS: &[(&str, Arrow)] = &[
+    ("<=>", Arrow::Equilibrium),
+    ("<->", Arrow::Equilibrium),
+    ("⇌", Arrow::Equilibrium),
+    ("->", Arrow::Forward),
+    ("→", Arrow::Forward),
+    ("=", Arrow::Forward),
+];
+
+/// Look up the atomic weight of an element symbol
+pub fn atomic_weight(symbol: &str) -> Option<f64> {
+    ELEMENTS
+        .iter()
+        .find(|(s, _)| *s == symbol)
+        .map(|(_, weight)| *weight)
+}
+
+/// A piece of a formula, kept in written order for typesetting
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    /// An element with its subscript count
+    Element { symbol: String, count: u32 },
+    /// A bracketed group such as `(OH)2`
+    Group {
+        open: char,
+        close: char,
+        parts: Vec<Part>,
+        count: u32,
+    },
+    /// Water of crystallization and similar adducts after a dot
+    Adduct { coefficient: u32, parts: Vec<Part> },
+    /// An electron in a half-reaction
+    Electron,
+}
+
+/// A formula with its stoichiometric coefficient, charge and state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Species {
+    pub coefficient: f64,
+    pub parts: Vec<Part>,
+    pub charge: i32,
+    /// Physical state such as `aq` or `s`
+    pub state: Option<String>,
+}
+
+impl Species {
+    /// Atom counts of one formula unit
+    pub fn atoms(&self) -> BTreeMap<String, f64> {
+        let mut atoms = BTreeMap::new();
+        count_atoms(&self.parts, 1., &mut atoms);
+        atoms
+    }
+
+    /// Molar mass of one formula unit in g/mol
+    pub fn molar_mass(&self) -> f64 {
+        mass_of(&self.parts)
+    }
+}
+
+fn count_atoms(parts: &[Part], multiplier: f64, atoms: &mut BTreeMap<String, f64>) {
+    for part in parts {
+        match part {
+            Part::Element { symbol, count } => {
+                *atoms.entry(symbol.clone()).or_default() += multiplier * f64::from(*count);
+            }
+            Part::Group { parts, count, .. } => {
+                count_atoms(parts, multiplier * f64::from(*count), atoms);
+            }
+            Part::Adduct { coefficient, parts } => {
+                count_atoms(parts, multiplier * f64::from(*coefficient), atoms);
+            }
+            Part::Electron => {}
+        }
+    }
+}
+
+fn mass_of(parts: &[Part]) -> f64 {
+    parts
+        .iter()
+        .map(|part| match part {
+            // Symbols are validated while parsing
+            Part::Element { symbol, count } => {
+                atomic_weight(symbol).unwrap_or_default() * f64::from(*count)
+            }
+            Part::Group { parts, count, .. } => mass_of(parts) * f64::from(*count),
+            Part::Adduct { coefficient, parts } => mass_of(parts) * f64::from(*coefficient),
+            Part::Electron => ELECTRON_MASS,
+        })
+        .sum()
+}
+
+/// The arrow between reactants and products
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arrow {
+    Forward,
+    Equilibrium,
+}
+
+impl Arrow {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Arrow::Forward => "→",
+            Arrow::Equilibrium => "⇌",
+        }
+    }
+}
+
+/// A parsed block: a single formula, a sum of species, or a reaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reaction {
+    pub reactants: Vec<Species>,
+    /// `None` when there is no right-hand side
+    pub arrow: Option<Arrow>,
+    pub products: Vec<Species>,
+}
+
+impl Reaction {
+    /// Describe each element (and the charge) whose amount differs between
+    /// the sides, as `name: left → right`. Empty when balanced.
+    pub fn imbalances(&self) -> Vec<String> {
+        let totals = |side: &[Species]| {
+            let mut atoms = BTreeMap::<String, f64>::new();
+            let mut charge = 0.;
+            for species in side {
+                for (symbol, count) in species.atoms() {
+                    *atoms.entry(symbol).or_default() += species.coefficient * count;
+                }
+                charge += species.coefficient * f64::from(species.charge);
+            }
+            (atoms, charge)
+        };
+        let (left, left_charge) = totals(&self.reactants);
+        let (right, right_charge) = totals(&self.products);
+
+        let mut symbols: Vec<&String> = left.keys().chain(right.keys()).collect();
+        symbols.sort();
+        symbols.dedup();
+
+        let differs = |a: f64, b: f64| (a - b).abs() > 1e-9;
+        let mut imbalances: Vec<String> = symbols
+            .into_iter()
+            .filter_map(|symbol| {
+                let l = left.get(symbol).copied().unwrap_or_default();
+                let r = right.get(symbol).copied().unwrap_or_default();
+                differs(l, r)
+                    .then(|| format!("{}: {} → {}", symbol, format_number(l), format_number(r)))
+            })
+            .collect();
+        if differs(left_charge, right_charge) {
+            imbalances.push(format!(
+                "charge: {} → {}",
+                format_number(left_charge),
+                format_number(right_charge)
+            ));
+        }
+        imbalances
+    }
+}
+
+/// Format a count without trailing zeros
+pub fn format_number(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Charge as written in a superscript: `+`, `2−`
+pub fn charge_text(charge: i32) -> String {
+    let sign = if charge > 0 { "+" } else { "−" };
+    match charge.abs() {
+        1 => sign.to_string(),
+        n => format!("{}{}", n, sign),
+    }
+}
+
+/// Parse a formula or reaction equation
+pub fn parse(text: &str) -> Result<Reaction, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Empty formula".to_string());
+    }
+
+    let split = ARROWS
+        .iter()
+        .find_map(|(token, arrow)| text.split_once(token).map(|(l, r)| (l, *arrow, r)));
+    match split {
+        Some((left, arrow, right)) => Ok(Reaction {
+            reactants: parse_side(left)?,
+            arrow: Some(arrow),
+            products: parse_side(right)?,
+        }),
+        None => Ok(Reaction {
+            reactants: parse_side(text)?,
+            arrow: None,
+            products: Vec::new(),
+        }),
+    }
+}
+
+/// Parse species joined by ` + ` (a `+` without spaces is a charge)
+fn parse_side(text: &str) -> Result<Vec<Species>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Missing species".to_string());
+    }
+    text.split(" + ").map(parse_species).collect()
+}
+
+fn parse_species(text: &str) -> Result<Species, String> {
+    let mut text = text.trim();
+
+    let digits = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let coefficient = match &text[..digits] {
+        "" => 1.,
+        number => number
+            .parse()
+            .map_err(|_| format!("Invalid coefficient '{}'", number))?,
+    };
+    text = text[digits..].trim_start();
+
+    let mut state = None;
+    for candidate in ["(aq)", "(s)", "(l)", "(g)"] {
+        if let Some(rest) = text.strip_suffix(candidate) {
+            state = Some(candidate[1..candidate.len() - 1].to_string());
+            text = rest;
+            break;
+        }
+    }
+
+    let (formula, charge) = split_charge(text)?;
+    if formula.is_empty() {
+        return Err("Missing formula".to_string());
+    }
+    let parts = if formula == "e" {
+        vec![Part::Electron]
+    } else {
+        let mut parser = Parser {
+            chars: formula.chars().peekable(),
+        };
+        parser.parse_formula()?
+    };
+
+    Ok(Species {
+        coefficient,
+        parts,
+        charge,
+        state,
+    })
+}
+
+/// Split a trailing charge off a formula: `SO4^2-`, `Fe^{3+}`, `Na+`, `O--`
+fn split_charge(text: &str) -> Result<(&str, i32), String> {
+    if let Some((formula, charge)) = text.split_once('^') {
+        let charge = charge.trim_start_matches('{').trim_end_matches('}');
+        let sign = match (charge.starts_with(['+', '-']), charge.ends_with(['+', '-'])) {
+            (true, _) => &charge[..1],
+            (_, true) => &charge[charge.len() - 1..],
+            _ => return Err(format!("Charge '{}' needs a sign", charge)),
+        };
+        let magnitude = charge.trim_matches(['+', '-']);
+        let magnitude: i32 = match magnitude {
+            "" => 1,
+            digits => digits
+                .parse()
+                .map_err(|_| format!("Invalid charge '{}'", charge))?,
+        };
+        let charge = if sign == "-" { -magnitude } else { magnitude };
+        return Ok((formula, charge));
+    }
+
+    let formula = text.trim_end_matches(['+', '-']);
+    let charge = text[formula.len()..]
+        .chars()
+        .map(|c| if c == '+' { 1 } else { -1 })
+        .sum();
+    Ok((formula, charge))
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    /// Parse a whole formula, including adducts after a dot
+    fn parse_formula(&mut self) -> Result<Vec<Part>, String> {
+        let mut parts = self.parse_parts(None)?;
+        while self.chars.next_if(|c| matches!(c, '·' | '.' | '*')).is_some() {
+            let coefficient = self.parse_count()?;
+            let adduct = self.parse_parts(None)?;
+            if adduct.is_empty() {
+                return Err("Missing formula after '·'".to_string());
+            }
+            parts.push(Part::Adduct {
+                coefficient,
+                parts: adduct,
+            });
+        }
+        match self.chars.next() {
+            None => Ok(parts),
+            Some(c) => Err(format!("Unexpected '{}'", c)),
+        }
+    }
+
+    /// Parse elements and groups until `close` (or a dot or the end)
+    fn parse_parts(&mut self, close: Option<char>) -> Result<Vec<Part>, String> {
+        let mut parts = Vec::new();
+        loop {
+            match self.chars.peek().copied() {
+                Some(c) if Some(c) == close => {
+                    self.chars.next();
+                    return Ok(parts);
+                }
+                None | Some('·' | '.' | '*') => {
+                    return match close {
+                        Some(close) => Err(format!("Missing '{}'", close)),
+                        None => Ok(parts),
+                    };
+                }
+                Some(c) if c.is_ascii_uppercase() => {
+                    self.chars.next();
+                    let mut symbol = c.to_string();
+                    while let Some(c) = self.chars.next_if(char::is_ascii_lowercase) {
+                        symbol.push(c);
+                    }
+                    if atomic_weight(&symbol).is_none() {
+                        return Err(format!("Unknown element '{}'", symbol));
+                    }
+                    let count = self.parse_count()?;
+                    parts.push(Part::Element { symbol, count });
+                }
+                Some(open @ ('(' | '[')) => {
+                    self.chars.next();
+                    let close = if open == '(' { ')' } else { ']' };
+                    let inner = self.parse_parts(Some(close))?;
+                    if inner.is_empty() {
+                        return Err(format!("Empty '{}{}'", open, close));
+                    }
+                    let count = self.parse_count()?;
+                    parts.push(Part::Group {
+                        open,
+                        close,
+                        parts: inner,
+                        count,
+                    });
+                }
+                Some(c) => return Err(format!("Unexpected '{}'", c)),
+            }
+        }
+    }
+
+    /// A count after an element, group or dot, 1 if none is written
+    fn parse_count(&mut self) -> Result<u32, String> {
+        let mut digits = String::new();
+        while let Some(c) = self.chars.next_if(char::is_ascii_digit) {
+            digits.push(c);
+        }
+        if digits.is_empty() {
+            return Ok(1);
+        }
+        match digits.parse() {
+            Ok(0) => Err(format!("Count '{}' must be at least 1", digits)),
+            Ok(count) => Ok(count),
+            Err(_) => Err(format!("Count '{}' is too large", digits)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn species(text: &str) -> Species {
+        let mut reaction = parse(text).unwrap();
+        assert_eq!(reaction.reactants.len(), 1);
+        reaction.reactants.remove(0)
+    }
+
+    #[test]
+    fn counts_atoms_in_groups_and_adducts() {
+        let atoms = species("CuSO4·5H2O").atoms();
+        assert_eq!(atoms["Cu"], 1.);
+        assert_eq!(atoms["H"], 10.);
+        assert_eq!(atoms["O"], 9.);
+
+        let atoms = species("Ca(OH)2").atoms();
+        assert_eq!(atoms["O"], 2.);
+        assert_eq!(atoms["H"], 2.);
+    }
+
+    #[test]
+    fn computes_molar_mass() {
+        assert!((species("H2O").molar_mass() - 18.015).abs() < 0.001);
+        assert!((species("NaCl").molar_mass() - 58.44).abs() < 0.01);
+    }
+
+    #[test]
+    fn parses_charges_and_states() {
+        assert_eq!(species("SO4^2-").charge, -2);
+        assert_eq!(species("Fe^{3+}").charge, 3);
+        assert_eq!(species("Na+").charge, 1);
+        assert_eq!(species("O--").charge, -2);
+        assert_eq!(species("NaCl(aq)").state.as_deref(), Some("aq"));
+    }
+
+    #[test]
+    fn checks_balance() {
+        let balanced = parse("2H2 + O2 -> 2H2O").unwrap();
+        assert_eq!(balanced.arrow, Some(Arrow::Forward));
+        assert!(balanced.imbalances().is_empty());
+
+        let unbalanced = parse("H2 + O2 -> H2O").unwrap();
+        assert_eq!(unbalanced.imbalances().len(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_formulas() {
+        assert!(parse("").is_err());
+        assert!(parse("Xx2").is_err());
+        assert!(parse("Ca(OH").is_err());
+        assert!(parse("H2 + ").is_err());
+    }
+
+    #[test]
+    fn rejects_zero_and_overflowing_counts() {
+        assert!(parse("H0").is_err());
+        assert!(parse("(OH)0").is_err());
+        assert!(parse("H99999999999").is_err());
+        assert_eq!(species("H2").atoms()["H"], 2.);
+    }
+}
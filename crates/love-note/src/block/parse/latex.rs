@@ -0,0 +1,428 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A node in a parsed formula
+#[derive(Debug, Clone, PartialEq)]
+pub enum MathNode {
+    /// A variable, rendered in italics
+    Ident(String),
+    /// A number
+    Number(String),
+    /// An operator or relation symbol, rendered with spacing
+    Operator(String),
+    /// A symbol rendered upright (Greek capitals, delimiters, ...)
+    Symbol(String),
+    /// Upright text from `\text{}`, `\mathrm{}` and function names
+    Text(String),
+    /// Bold content from `\mathbf{}`
+    Bold(Vec<MathNode>),
+    /// A `{...}` group
+    Group(Vec<MathNode>),
+    /// A base with optional subscript and superscript
+    Scripts {
+        base: Box<MathNode>,
+        sub: Option<Vec<MathNode>>,
+        sup: Option<Vec<MathNode>>,
+    },
+    /// `\frac{numerator}{denominator}`
+    Frac(Vec<MathNode>, Vec<MathNode>),
+    /// `\sqrt[index]{radicand}`
+    Sqrt {
+        index: Option<Vec<MathNode>>,
+        radicand: Vec<MathNode>,
+    },
+    /// Horizontal space in em
+    Space(f32),
+    /// An unsupported command, shown verbatim
+    Unknown(String),
+}
+
+/// Parse LaTeX math source into a list of nodes
+pub fn parse(source: &str) -> Result<Vec<MathNode>, String> {
+    let mut parser = Parser {
+        chars: source.chars().peekable(),
+    };
+    let nodes = parser.parse_seq(None)?;
+    match parser.chars.next() {
+        None => Ok(nodes),
+        Some(c) => Err(format!("Unexpected '{}'", c)),
+    }
+}
+
+/// Returns true if the source opts out of equation numbering
+pub fn is_unnumbered(source: &str) -> bool {
+    source.contains("\\notag") || source.contains("\\nonumber")
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    /// Parse nodes until `end` (or the end of input when `None`)
+    fn parse_seq(&mut self, end: Option<char>) -> Result<Vec<MathNode>, String> {
+        let mut nodes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match (self.chars.peek().copied(), end) {
+                (None, None) => return Ok(nodes),
+                (None, Some(end)) => return Err(format!("Missing '{}'", end)),
+                (Some(c), Some(end)) if c == end => {
+                    self.chars.next();
+                    return Ok(nodes);
+                }
+                // Let the caller report the stray brace
+                (Some('}'), None) => return Ok(nodes),
+                (Some('}'), Some(_)) => return Err("Unexpected '}'".to_string()),
+                (Some('^') | Some('_'), _) => {
+                    // A script with no base attaches to an empty group
+                    let node = self.parse_scripts(MathNode::Group(Vec::new()))?;
+                    nodes.push(node);
+                }
+                (Some(_), _) => {
+                    if let Some(atom) = self.parse_atom()? {
+                        let node = self.parse_scripts(atom)?;
+                        nodes.push(node);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Attach any `^`/`_` scripts following `base`
+    fn parse_scripts(&mut self, base: MathNode) -> Result<MathNode, String> {
+        let mut sub = None;
+        let mut sup = None;
+        loop {
+            self.skip_whitespace();
+            match self.chars.peek() {
+                Some('_') if sub.is_none() => {
+                    self.chars.next();
+                    sub = Some(self.parse_arg()?);
+                }
+                Some('^') if sup.is_none() => {
+                    self.chars.next();
+                    sup = Some(self.parse_arg()?);
+                }
+                _ => break,
+            }
+        }
+
+        if sub.is_none() && sup.is_none() {
+            Ok(base)
+        } else {
+            Ok(MathNode::Scripts {
+                base: Box::new(base),
+                sub,
+                sup,
+            })
+        }
+    }
+
+    /// Parse a command argument: a braced group or a single atom
+    fn parse_arg(&mut self) -> Result<Vec<MathNode>, String> {
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'{').is_some() {
+            return self.parse_seq(Some('}'));
+        }
+        match self.parse_atom()? {
+            Some(atom) => Ok(vec![atom]),
+            None => Err("Missing argument".to_string()),
+        }
+    }
+
+    /// Read a braced argument verbatim (for `\text{}`)
+    fn parse_raw_arg(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'{').is_none() {
+            return Err("Expected '{'".to_string());
+        }
+        let mut depth = 0;
+        let mut text = String::new();
+        for c in self.chars.by_ref() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => return Ok(text),
+                '}' => depth -= 1,
+                _ => {}
+            }
+            text.push(c);
+        }
+        Err("Missing '}'".to_string())
+    }
+
+    /// Parse a single atom, or `None` for tokens that produce no output
+    fn parse_atom(&mut self) -> Result<Option<MathNode>, String> {
+        let Some(c) = self.chars.next() else {
+            return Ok(None);
+        };
+
+        let node = match c {
+            '{' => MathNode::Group(self.parse_seq(Some('}'))?),
+            '}' => return Err("Unexpected '}'".to_string()),
+            '\\' => return self.parse_command(),
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(d) = self.chars.next_if(|d| d.is_ascii_digit() || *d == '.') {
+                    number.push(d);
+                }
+                MathNode::Number(number)
+            }
+            c if c.is_alphabetic() => MathNode::Ident(c.to_string()),
+            '+' | '=' | '<' | '>' | ':' => MathNode::Operator(c.to_string()),
+            '-' => MathNode::Operator("−".to_string()),
+            '*' => MathNode::Operator("∗".to_string()),
+            '\'' => MathNode::Symbol("′".to_string()),
+            '&' | '~' => MathNode::Space(0.28),
+            c => MathNode::Symbol(c.to_string()),
+        };
+        Ok(Some(node))
+    }
+
+    fn parse_command(&mut self) -> Result<Option<MathNode>, String> {
+        let mut name = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_ascii_alphabetic()) {
+            name.push(c);
+        }
+
+        if name.is_empty() {
+            // Control symbol such as `\,` or `\{`
+            let Some(c) = self.chars.next() else {
+                return Ok(Some(MathNode::Unknown("\\".to_string())));
+            };
+            let node = match c {
+                ',' => MathNode::Space(0.17),
+                ':' | '>' => MathNode::Space(0.22),
+                ';' => MathNode::Space(0.28),
+                ' ' => MathNode::Space(0.33),
+                '!' => return Ok(None),
+                '\\' => MathNode::Space(1.0),
+                c => MathNode::Symbol(c.to_string()),
+            };
+            return Ok(Some(node));
+        }
+
+        let node = match name.as_str() {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.parse_arg()?;
+                let denominator = self.parse_arg()?;
+                MathNode::Frac(numerator, denominator)
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                let index = if self.chars.next_if_eq(&'[').is_some() {
+                    Some(self.parse_seq(Some(']'))?)
+                } else {
+                    None
+                };
+                MathNode::Sqrt {
+                    index,
+                    radicand: self.parse_arg()?,
+                }
+            }
+            "text" | "textrm" | "mathrm" | "operatorname" | "mbox" => {
+                MathNode::Text(self.parse_raw_arg()?)
+            }
+            "mathbf" | "boldsymbol" | "bm" => MathNode::Bold(self.parse_arg()?),
+            "mathit" | "mathnormal" => MathNode::Group(self.parse_arg()?),
+            "left" | "right" | "big" | "Big" | "bigl" | "bigr" | "Bigl" | "Bigr" => {
+                // Delimiters are drawn at text size
+                return match self.parse_atom()? {
+                    Some(MathNode::Symbol(s)) if s == "." => Ok(None),
+                    other => Ok(other),
+                };
+            }
+            "quad" => MathNode::Space(1.0),
+            "qquad" => MathNode::Space(2.0),
+            "notag" | "nonumber" | "displaystyle" | "textstyle" => return Ok(None),
+            "sin" | "cos" | "tan" | "cot" | "sec" | "csc" | "sinh" | "cosh" | "tanh" | "arcsin"
+            | "arccos" | "arctan" | "log" | "ln" | "lg" | "exp" | "lim" | "max" | "min"
+            | "sup" | "inf" | "det" | "dim" | "ker" | "gcd" | "arg" | "deg" => {
+                MathNode::Text(name)
+            }
+            _ => match symbol(&name) {
+                Some((s, SymbolClass::Operator)) => MathNode::Operator(s.to_string()),
+                Some((s, SymbolClass::Ident)) => MathNode::Ident(s.to_string()),
+                Some((s, SymbolClass::Upright)) => MathNode::Symbol(s.to_string()),
+                None => MathNode::Unknown(format!("\\{}", name)),
+            },
+        };
+        Ok(Some(node))
+    }
+}
+
+enum SymbolClass {
+    Operator,
+    Ident,
+    Upright,
+}
+
+/// Look up the Unicode rendering of a symbol command
+fn symbol(name: &str) -> Option<(&'static str, SymbolClass)> {
+    use SymbolClass::*;
+    let entry = match name {
+        "alpha" => ("α", Ident),
+        "beta" => ("β", Ident),
+        "gamma" => ("γ", Ident),
+        "delta" => ("δ", Ident),
+        "epsilon" => ("ϵ", Ident),
+        "varepsilon" => ("ε", Ident),
+        "zeta" => ("ζ", Ident),
+        "eta" => ("η", Ident),
+        "theta" => ("θ", Ident),
+        "vartheta" => ("ϑ", Ident),
+        "iota" => ("ι", Ident),
+        "kappa" => ("κ", Ident),
+        "lambda" => ("λ", Ident),
+        "mu" => ("μ", Ident),
+        "nu" => ("ν", Ident),
+        "xi" => ("ξ", Ident),
+        "pi" => ("π", Ident),
+        "rho" => ("ρ", Ident),
+        "sigma" => ("σ", Ident),
+        "tau" => ("τ", Ident),
+        "upsilon" => ("υ", Ident),
+        "phi" => ("ϕ", Ident),
+        "varphi" => ("φ", Ident),
+        "chi" => ("χ", Ident),
+        "psi" => ("ψ", Ident),
+        "omega" => ("ω", Ident),
+        "Gamma" => ("Γ", Upright),
+        "Delta" => ("Δ", Upright),
+        "Theta" => ("Θ", Upright),
+        "Lambda" => ("Λ", Upright),
+        "Xi" => ("Ξ", Upright),
+        "Pi" => ("Π", Upright),
+        "Sigma" => ("Σ", Upright),
+        "Phi" => ("Φ", Upright),
+        "Psi" => ("Ψ", Upright),
+        "Omega" => ("Ω", Upright),
+        "hbar" => ("ℏ", Ident),
+        "ell" => ("ℓ", Ident),
+        "infty" => ("∞", Upright),
+        "partial" => ("∂", Upright),
+        "nabla" => ("∇", Upright),
+        "degree" | "circ" => ("°", Upright),
+        "prime" => ("′", Upright),
+        "sum" => ("∑", Upright),
+        "prod" => ("∏", Upright),
+        "int" => ("∫", Upright),
+        "iint" => ("∬", Upright),
+        "oint" => ("∮", Upright),
+        "ldots" | "dots" => ("…", Upright),
+        "cdots" => ("⋯", Upright),
+        "vdots" => ("⋮", Upright),
+        "langle" => ("⟨", Upright),
+        "rangle" => ("⟩", Upright),
+        "lbrace" => ("{", Upright),
+        "rbrace" => ("}", Upright),
+        "vert" | "mid" => ("|", Operator),
+        "cdot" => ("·", Operator),
+        "times" => ("×", Operator),
+        "div" => ("÷", Operator),
+        "pm" => ("±", Operator),
+        "mp" => ("∓", Operator),
+        "leq" | "le" => ("≤", Operator),
+        "geq" | "ge" => ("≥", Operator),
+        "neq" | "ne" => ("≠", Operator),
+        "approx" => ("≈", Operator),
+        "sim" => ("∼", Operator),
+        "simeq" => ("≃", Operator),
+        "equiv" => ("≡", Operator),
+        "propto" => ("∝", Operator),
+        "ll" => ("≪", Operator),
+        "gg" => ("≫", Operator),
+        "in" => ("∈", Operator),
+        "notin" => ("∉", Operator),
+        "subset" => ("⊂", Operator),
+        "subseteq" => ("⊆", Operator),
+        "cup" => ("∪", Operator),
+        "cap" => ("∩", Operator),
+        "forall" => ("∀", Upright),
+        "exists" => ("∃", Upright),
+        "to" | "rightarrow" => ("→", Operator),
+        "leftarrow" | "gets" => ("←", Operator),
+        "leftrightarrow" => ("↔", Operator),
+        "Rightarrow" | "implies" => ("⇒", Operator),
+        "Leftarrow" => ("⇐", Operator),
+        "Leftrightarrow" | "iff" => ("⇔", Operator),
+        "rightleftharpoons" => ("⇌", Operator),
+        "mapsto" => ("↦", Operator),
+        _ => return None,
+    };
+    Some(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ident(name: &str) -> MathNode {
+        MathNode::Ident(name.to_string())
+    }
+
+    fn number(value: &str) -> MathNode {
+        MathNode::Number(value.to_string())
+    }
+
+    #[test]
+    fn parses_scripts() {
+        assert_eq!(
+            parse("x_1^{2}").unwrap(),
+            vec![MathNode::Scripts {
+                base: Box::new(ident("x")),
+                sub: Some(vec![number("1")]),
+                sup: Some(vec![number("2")]),
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_fractions_and_roots() {
+        assert_eq!(
+            parse(r"\frac{a}{b}").unwrap(),
+            vec![MathNode::Frac(vec![ident("a")], vec![ident("b")])]
+        );
+        assert_eq!(
+            parse(r"\sqrt[3]{x}").unwrap(),
+            vec![MathNode::Sqrt {
+                index: Some(vec![number("3")]),
+                radicand: vec![ident("x")],
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_symbols_and_text() {
+        let nodes = parse(r"\alpha \leq \text{max}").unwrap();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[2], MathNode::Text("max".to_string()));
+        assert!(matches!(&nodes[1], MathNode::Operator(op) if op == "≤"));
+    }
+
+    #[test]
+    fn keeps_unknown_commands() {
+        assert_eq!(
+            parse(r"\foo").unwrap(),
+            vec![MathNode::Unknown(r"\foo".to_string())]
+        );
+    }
+
+    #[test]
+    fn rejects_unbalanced_braces() {
+        assert!(parse("{x").is_err());
+        assert!(parse("x}").is_err());
+        assert!(parse(r"\frac{a}").is_err());
+    }
+
+    #[test]
+    fn detects_unnumbered_equations() {
+        assert!(is_unnumbered(r"E = mc^2 \notag"));
+        assert!(is_unnumbered(r"E = mc^2 \nonumber"));
+        assert!(!is_unnumbered("E = mc^2"));
+    }
+}
@@ -0,0 +1,12 @@
+/// A parser for chemical formulas and reaction equations used by
+/// chemistry blocks, with a bundled periodic table for molar masses.
+///
+/// Formulas are written plainly (`CuSO4·5H2O`, `Ca(OH)2`), charges with a
+/// caret or trailing signs (`SO4^2-`, `Na+`), states in parentheses
+/// (`NaCl(aq)`), and species are joined with ` + ` and an arrow.
+pub(crate) mod chem;
+/// A small parser for the LaTeX math subset used by math blocks.
+///
+/// The parser produces a layout tree that the math block renders with
+/// native GPUI elements, so equations are typeset fully offline.
+pub(crate) mod latex;
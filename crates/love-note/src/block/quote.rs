@@ -0,0 +1,66 @@
+use gpui::*;
+use gpui_component::input::{Input, InputState};
+use serde_json::Value;
+
+use super::{BlockContent, Marks};
+
+/// A quotation, set off with a rule on the left
+#[derive(Default)]
+pub struct QuoteBlock {
+    marks: Marks,
+}
+
+impl QuoteBlock {
+    fn framed(element: Div) -> Div {
+        element
+            .pl_3()
+            .border_l_4()
+            .border_color(rgb(0x6c7086))
+    }
+}
+
+impl BlockContent for QuoteBlock {
+    fn type_name(&self) -> &'static str {
+        "Quote"
+    }
+
+    fn placeholder(&self) -> &'static str {
+        "Quote..."
+    }
+
+    fn splits_on_enter(&self) -> bool {
+        true
+    }
+
+    fn props(&self) -> Value {
+        self.marks.add_to_props(Value::Null)
+    }
+
+    fn on_text_changed(&mut self, text: &str) -> Option<String> {
+        self.marks.update_text(text);
+        None
+    }
+
+    fn marks(&self) -> Option<&Marks> {
+        Some(&self.marks)
+    }
+
+    fn marks_mut(&mut self) -> Option<&mut Marks> {
+        Some(&mut self.marks)
+    }
+
+    fn render_view(&self, text: &str) -> AnyElement {
+        Self::framed(div())
+            .italic()
+            .text_color(rgb(0xa6adc8))
+            .child(self.marks.render("marks".into(), text))
+            .into_any_element()
+    }
+
+    fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
+        Self::framed(div())
+            .italic()
+            .child(Input::new(input))
+            .into_any_element()
+    }
+}
@@ -0,0 +1,379 @@
+use chrono::NaiveDate;
+use gpui::*;
+use gpui_component::input::{Input, InputState};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::BlockContent;
+
+/// Date format accepted in date columns
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The kind of values a column holds
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    #[default]
+    Text,
+    Number,
+    Date,
+}
+
+impl ColumnType {
+    /// Get the display name for UI
+    pub fn display_name(self) -> &'static str {
+        match self {
+            ColumnType::Text => "Text",
+            ColumnType::Number => "Number",
+            ColumnType::Date => "Date",
+        }
+    }
+
+    /// Get the next type, for cycling through types in the header
+    pub fn next(self) -> Self {
+        match self {
+            ColumnType::Text => ColumnType::Number,
+            ColumnType::Number => ColumnType::Date,
+            ColumnType::Date => ColumnType::Text,
+        }
+    }
+
+    /// Check whether a cell value is valid for this type (empty is valid)
+    pub fn accepts(self, value: &str) -> bool {
+        let value = value.trim();
+        value.is_empty()
+            || match self {
+                ColumnType::Text => true,
+                ColumnType::Number => value.parse::<f64>().is_ok(),
+                ColumnType::Date => NaiveDate::parse_from_str(value, DATE_FORMAT).is_ok(),
+            }
+    }
+
+    /// Pick the narrowest type that accepts every value
+    fn infer<'a>(values: impl Iterator<Item = &'a str> + Clone) -> Self {
+        let non_empty = values.filter(|v| !v.trim().is_empty());
+        if non_empty.clone().next().is_none() {
+            return ColumnType::Text;
+        }
+        [ColumnType::Number, ColumnType::Date]
+            .into_iter()
+            .find(|kind| non_empty.clone().all(|v| kind.accepts(v)))
+            .unwrap_or(ColumnType::Text)
+    }
+}
+
+/// A table column
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Column {
+    pub name: String,
+    #[serde(default)]
+    pub kind: ColumnType,
+}
+
+impl Column {
+    pub fn new(name: impl Into<String>, kind: ColumnType) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+        }
+    }
+}
+
+/// Structured table content: typed columns and rows of cell strings
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TableData {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl TableData {
+    /// Create an empty table with the given size
+    pub fn with_size(rows: usize, columns: usize) -> Self {
+        Self {
+            columns: (0..columns)
+                .map(|i| Column::new(Self::default_column_name(i), ColumnType::Text))
+                .collect(),
+            rows: vec![vec![String::new(); columns]; rows],
+        }
+    }
+
+    fn default_column_name(index: usize) -> String {
+        format!("Column {}", index + 1)
+    }
+
+    /// Restore a table from stored properties
+    pub fn from_props(props: &Value) -> Self {
+        let mut data: TableData = serde_json::from_value(props.clone()).unwrap_or_default();
+        data.normalize();
+        data
+    }
+
+    /// Pad or trim rows so every row has one cell per column
+    fn normalize(&mut self) {
+        let width = self.columns.len();
+        for row in &mut self.rows {
+            row.resize(width, String::new());
+        }
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn cell(&self, row: usize, column: usize) -> &str {
+        self.rows
+            .get(row)
+            .and_then(|r| r.get(column))
+            .map_or("", String::as_str)
+    }
+
+    pub fn set_cell(&mut self, row: usize, column: usize, value: impl Into<String>) {
+        if let Some(cell) = self.rows.get_mut(row).and_then(|r| r.get_mut(column)) {
+            *cell = value.into();
+        }
+    }
+
+    /// Insert an empty row at `index` (clamped to the end)
+    pub fn insert_row(&mut self, index: usize) {
+        let index = index.min(self.rows.len());
+        self.rows
+            .insert(index, vec![String::new(); self.columns.len()]);
+    }
+
+    pub fn remove_row(&mut self, index: usize) {
+        if index < self.rows.len() {
+            self.rows.remove(index);
+        }
+    }
+
+    /// Insert an empty text column at `index` (clamped to the end)
+    pub fn insert_column(&mut self, index: usize) {
+        let index = index.min(self.columns.len());
+        let name = Self::default_column_name(self.columns.len());
+        self.columns.insert(index, Column::new(name, ColumnType::Text));
+        for row in &mut self.rows {
+            row.insert(index, String::new());
+        }
+    }
+
+    pub fn remove_column(&mut self, index: usize) {
+        if index < self.columns.len() {
+            self.columns.remove(index);
+            for row in &mut self.rows {
+                row.remove(index);
+            }
+        }
+    }
+
+    /// Numeric values of a column (`None` for empty or invalid cells)
+    pub fn numbers(&self, column: usize) -> Vec<Option<f64>> {
+        self.rows
+            .iter()
+            .map(|row| row.get(column).and_then(|v| v.trim().parse().ok()))
+            .collect()
+    }
+
+    /// Paste a block of rows into the table starting at the given cell,
+    /// growing the table as needed
+    pub fn paste(&mut self, row: usize, column: usize, values: Vec<Vec<String>>) {
+        for (r, cells) in values.into_iter().enumerate() {
+            for (c, value) in cells.into_iter().enumerate() {
+                while self.columns.len() <= column + c {
+                    self.insert_column(self.columns.len());
+                }
+                while self.rows.len() <= row + r {
+                    self.insert_row(self.rows.len());
+                }
+                self.set_cell(row + r, column + c, value);
+            }
+        }
+    }
+
+    /// Split CSV or tab-separated text into rows of cells without any
+    /// header detection. Returns `None` for text with no delimiter.
+    pub fn parse_cells(text: &str) -> Option<Vec<Vec<String>>> {
+        parse_delimited(text)
+    }
+
+    /// Build a table from CSV or tab-separated text.
+    ///
+    /// Returns `None` unless the text has at least two rows of the same
+    /// number of fields, at least two each, so prose that happens to hold
+    /// a comma isn't taken for a table. The first row becomes the header
+    /// when it looks like one, and column types are inferred from the
+    /// values.
+    pub fn from_delimited(text: &str) -> Option<Self> {
+        let mut rows = parse_delimited(text)?;
+        let width = rows.first().map_or(0, Vec::len);
+        if rows.len() < 2 || width < 2 || rows.iter().any(|row| row.len() != width) {
+            return None;
+        }
+
+        let has_header = looks_like_header(&rows);
+        let header = if has_header {
+            rows.remove(0)
+        } else {
+            (0..width).map(Self::default_column_name).collect()
+        };
+        Some(Self::with_header(header, rows))
+    }
+
+    /// Build a table from column names and rows of cells, inferring the
+    /// column types from the values
+    pub fn with_header(header: Vec<String>, rows: Vec<Vec<String>>) -> Self {
+        let columns = header
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let values = rows.iter().map(|row| row.get(i).map_or("", String::as_str));
+                Column::new(name, ColumnType::infer(values))
+            })
+            .collect();
+
+        let mut data = Self { columns, rows };
+        data.normalize();
+        data
+    }
+
+    /// Serialize the table as CSV with a header row
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        let header = self.columns.iter().map(|c| c.name.as_str());
+        write_csv_row(&mut out, header);
+        for row in &self.rows {
+            write_csv_row(&mut out, row.iter().map(String::as_str));
+        }
+        out
+    }
+}
+
+fn write_csv_row<'a>(out: &mut String, cells: impl Iterator<Item = &'a str>) {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if cell.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&cell.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(cell);
+        }
+    }
+    out.push('\n');
+}
+
+/// Split delimited text into rows, honouring double-quoted fields.
+/// The delimiter is detected from the first line (tab, comma or semicolon).
+fn parse_delimited(text: &str) -> Option<Vec<Vec<String>>> {
+    let first_line = text.lines().next()?;
+    let delimiter = ['\t', ',', ';']
+        .into_iter()
+        .find(|d| first_line.contains(*d))?;
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.next_if_eq(&'"').is_some() => field.push('"'),
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            c if c == delimiter && !in_quotes => row.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows.retain(|row| row.iter().any(|cell| !cell.trim().is_empty()));
+    Some(rows)
+}
+
+/// The first row is a header when every cell is non-empty, non-numeric text
+fn looks_like_header(rows: &[Vec<String>]) -> bool {
+    rows.first().is_some_and(|first| {
+        first.iter().all(|cell| {
+            let cell = cell.trim();
+            !cell.is_empty() && cell.parse::<f64>().is_err()
+        })
+    })
+}
+
+/// A table of typed columns; the block text is the table caption
+pub struct TableBlock {
+    data: TableData,
+}
+
+impl TableBlock {
+    pub fn new(data: TableData) -> Self {
+        Self { data }
+    }
+
+    /// Restore a table block from stored properties
+    pub fn from_props(props: &Value) -> Self {
+        Self::new(TableData::from_props(props))
+    }
+}
+
+impl Default for TableBlock {
+    fn default() -> Self {
+        Self::new(TableData::with_size(2, 2))
+    }
+}
+
+impl BlockContent for TableBlock {
+    fn type_name(&self) -> &'static str {
+        "Table"
+    }
+
+    fn placeholder(&self) -> &'static str {
+        "Table caption..."
+    }
+
+    fn max_rows(&self) -> usize {
+        1
+    }
+
+    fn props(&self) -> Value {
+        serde_json::to_value(&self.data).unwrap_or_default()
+    }
+
+    fn table(&self) -> Option<&TableData> {
+        Some(&self.data)
+    }
+
+    fn table_mut(&mut self) -> Option<&mut TableData> {
+        Some(&mut self.data)
+    }
+
+    fn render_view(&self, text: &str) -> AnyElement {
+        div()
+            .text_sm()
+            .italic()
+            .text_color(rgb(0x9399b2))
+            .child(text.to_string())
+            .into_any_element()
+    }
+
+    fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
+        div()
+            .text_sm()
+            .child(Input::new(input))
+            .into_any_element()
+    }
+}
@@ -1,10 +1,23 @@
 use gpui::*;
 use gpui_component::input::{Input, InputState};
+use serde_json::{json, Value};
 
-use super::BlockContent;
+use super::{BlockContent, Marks};
 
-/// A simple text block for general content
-pub struct TextBlock;
+/// A simple text block for general content, with inline formatting
+#[derive(Default)]
+pub struct TextBlock {
+    marks: Marks,
+}
+
+impl TextBlock {
+    /// Restore a text block and its formatting from stored properties
+    pub fn from_props(props: &Value, text: &str) -> Self {
+        Self {
+            marks: Marks::from_props(props, text),
+        }
+    }
+}
 
 impl BlockContent for TextBlock {
     fn type_name(&self) -> &'static str {
@@ -19,9 +32,33 @@ impl BlockContent for TextBlock {
         20
     }
 
+    fn splits_on_enter(&self) -> bool {
+        true
+    }
+
+    fn props(&self) -> Value {
+        match self.marks.to_props() {
+            Some(marks) => json!({ "marks": marks }),
+            None => Value::Null,
+        }
+    }
+
+    fn on_text_changed(&mut self, text: &str) -> Option<String> {
+        self.marks.update_text(text);
+        None
+    }
+
+    fn marks(&self) -> Option<&Marks> {
+        Some(&self.marks)
+    }
+
+    fn marks_mut(&mut self) -> Option<&mut Marks> {
+        Some(&mut self.marks)
+    }
+
     fn render_view(&self, text: &str) -> AnyElement {
         div()
-            .child(text.to_string())
+            .child(self.marks.render("marks".into(), text))
             .into_any_element()
     }
 
@@ -30,4 +67,18 @@ impl BlockContent for TextBlock {
             .child(Input::new(input))
             .into_any_element()
     }
+
+    fn render_preview(&self, text: &str) -> Option<AnyElement> {
+        if self.marks.is_empty() {
+            return None;
+        }
+        Some(
+            div()
+                .pt_1()
+                .text_sm()
+                .text_color(rgb(0xa6adc8))
+                .child(self.marks.render("marks-preview".into(), text))
+                .into_any_element(),
+        )
+    }
 }
@@ -0,0 +1,66 @@
+use gpui::*;
+use gpui_component::input::{Input, InputState};
+use serde_json::Value;
+
+use super::{BlockContent, Marks};
+
+/// A summary line whose child blocks can be collapsed out of view. The
+/// children are stored as the blocks following the toggle, each pointing
+/// back at it as their parent.
+#[derive(Default)]
+pub struct ToggleBlock {
+    marks: Marks,
+}
+
+impl BlockContent for ToggleBlock {
+    fn type_name(&self) -> &'static str {
+        "Toggle"
+    }
+
+    fn placeholder(&self) -> &'static str {
+        "Toggle summary..."
+    }
+
+    fn max_rows(&self) -> usize {
+        1
+    }
+
+    fn splits_on_enter(&self) -> bool {
+        true
+    }
+
+    fn is_toggle(&self) -> bool {
+        true
+    }
+
+    fn props(&self) -> Value {
+        self.marks.add_to_props(Value::Null)
+    }
+
+    fn on_text_changed(&mut self, text: &str) -> Option<String> {
+        self.marks.update_text(text);
+        None
+    }
+
+    fn marks(&self) -> Option<&Marks> {
+        Some(&self.marks)
+    }
+
+    fn marks_mut(&mut self) -> Option<&mut Marks> {
+        Some(&mut self.marks)
+    }
+
+    fn render_view(&self, text: &str) -> AnyElement {
+        div()
+            .font_weight(FontWeight::MEDIUM)
+            .child(self.marks.render("marks".into(), text))
+            .into_any_element()
+    }
+
+    fn render_edit(&self, input: &Entity<InputState>) -> AnyElement {
+        div()
+            .font_weight(FontWeight::MEDIUM)
+            .child(Input::new(input))
+            .into_any_element()
+    }
+}
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use gpui::*;
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    Sizable,
+};
+
+use super::export::export_directory;
+use super::LoveNote;
+use crate::storage::{RestoreMode, Storage};
+
+/// How often to check whether an automatic backup is due
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+impl LoveNote {
+    /// Make automatic backups for as long as the returned task is kept
+    pub(super) fn schedule_backups(storage: Arc<Storage>, cx: &mut Context<Self>) -> Task<()> {
+        cx.spawn(async move |this, cx| {
+            loop {
+                let storage = storage.clone();
+                let made = cx
+                    .background_executor()
+                    .spawn(async move { storage.backup_if_due() })
+                    .await;
+                let shown = this.update(cx, |this, cx| {
+                    let error = made.err().map(|e| SharedString::from(e.to_string()));
+                    if this.backup_error != error {
+                        this.backup_error = error;
+                        cx.notify();
+                    }
+                });
+                if shown.is_err() {
+                    break;
+                }
+                cx.background_executor().timer(BACKUP_CHECK_INTERVAL).await;
+            }
+        })
+    }
+
+    /// Ask for a destination and write a backup of everything stored there
+    fn back_up(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.save_document(cx);
+        let storage = self.storage.clone();
+        let name = format!("Love Note backup {}.zip", chrono::Local::now().format("%Y-%m-%d"));
+        let path = cx.prompt_for_new_path(&export_directory(), Some(&name));
+
+        cx.spawn_in(window, async move |this, cx| {
+            let Ok(Ok(Some(path))) = path.await else {
+                return;
+            };
+            let written = cx
+                .background_executor()
+                .spawn(async move { storage.write_backup(&path) })
+                .await;
+            if let Err(e) = written {
+                this.update_in(cx, |this, window, cx| {
+                    this.show_error("Failed to back up", &e.to_string(), window, cx);
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    /// Ask for a backup and whether to merge it with what's stored or
+    /// replace it, restore it and reopen the most recent document
+    fn restore(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Restore backup".into()),
+        });
+
+        cx.spawn_in(window, async move |this, cx| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(path) = paths.into_iter().next() else {
+                return;
+            };
+            let Ok(answer) = this.update_in(cx, |_, window, cx| {
+                window.prompt(
+                    PromptLevel::Warning,
+                    "Restore backup",
+                    Some(
+                        "Merge keeps documents edited since the backup. \
+                         Replace discards everything not in the backup.",
+                    ),
+                    &["Merge", "Replace", "Cancel"],
+                    cx,
+                )
+            }) else {
+                return;
+            };
+            let mode = match answer.await {
+                Ok(0) => RestoreMode::Merge,
+                Ok(1) => RestoreMode::Replace,
+                _ => return,
+            };
+
+            let Ok(storage) = this.update(cx, |this, cx| {
+                // Store open edits so merging can weigh them
+                this.save_document(cx);
+                this.storage.clone()
+            }) else {
+                return;
+            };
+            let restored = cx
+                .background_executor()
+                .spawn(async move {
+                    storage.restore_backup(&path, mode)?;
+                    storage.get_or_create_default()
+                })
+                .await;
+            let document = match restored {
+                Ok(restored) => restored,
+                Err(e) => {
+                    this.update_in(cx, |this, window, cx| {
+                        this.show_error("Failed to restore backup", &e.to_string(), window, cx);
+                    })
+                    .ok();
+                    return;
+                }
+            };
+            this.update_in(cx, |this, window, cx| {
+                this.collapsed = this.storage.collapsed_blocks().unwrap_or_else(|e| {
+                    eprintln!("Failed to load toggle state: {}", e);
+                    Default::default()
+                });
+                // The open document may have been replaced, so it isn't
+                // saved again before switching
+                this.show_document(document, window, cx);
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    pub(super) fn render_backup_buttons(&self, cx: &mut Context<Self>) -> AnyElement {
+        let failed = self.backup_error.clone().map(|error| {
+            div()
+                .id("backup-failed")
+                .px_1()
+                .text_xs()
+                .text_color(rgb(0xf38ba8))
+                .cursor_pointer()
+                .child("Backup failed")
+                .on_click(cx.listener(move |this, _, window, cx| {
+                    this.show_error("Automatic backup failed", &error, window, cx);
+                }))
+        });
+
+        div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .children(failed)
+            .child(
+                Button::new("back-up")
+                    .label("Back up…")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(|this, _, window, cx| {
+                        this.back_up(window, cx);
+                    })),
+            )
+            .child(
+                Button::new("restore")
+                    .label("Restore…")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(|this, _, window, cx| {
+                        this.restore(window, cx);
+                    })),
+            )
+            .into_any_element()
+    }
+}
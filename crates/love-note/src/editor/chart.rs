@@ -0,0 +1,314 @@
+use std::path::PathBuf;
+
+use gpui::*;
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    Sizable,
+};
+use uuid::Uuid;
+
+use super::LoveNote;
+use crate::block::{Block, ChartBlock, ChartKind, ChartSpec, Plot, TableData};
+
+/// File formats a chart can be exported to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(super) enum ChartExport {
+    Svg,
+    Png,
+}
+
+impl ChartExport {
+    fn extension(self) -> &'static str {
+        match self {
+            ChartExport::Svg => "svg",
+            ChartExport::Png => "png",
+        }
+    }
+}
+
+impl LoveNote {
+    /// Ids of the document's table blocks, in document order
+    fn table_ids(&self) -> Vec<Uuid> {
+        self.blocks
+            .iter()
+            .filter(|b| b.table().is_some())
+            .map(|b| b.id)
+            .collect()
+    }
+
+    /// Resolve the data a chart plots: a table block, or inline CSV
+    fn chart_data(&self, block: &Block, spec: &ChartSpec, cx: &App) -> Result<TableData, String> {
+        match spec.source {
+            Some(table_id) => self
+                .blocks
+                .iter()
+                .find(|b| b.id == table_id)
+                .and_then(Block::table)
+                .cloned()
+                .ok_or_else(|| "The plotted table no longer exists".to_string()),
+            None => ChartBlock::parse_inline(&block.get_content(cx))
+                .ok_or_else(|| "Enter data as CSV with a header row".to_string()),
+        }
+    }
+
+    /// Apply `f` to a chart block's settings, then save and re-render
+    fn update_chart(
+        &mut self,
+        block_id: Uuid,
+        cx: &mut Context<Self>,
+        f: impl FnOnce(&mut ChartSpec, usize),
+    ) {
+        let Some(block) = self.blocks.iter().find(|b| b.id == block_id) else {
+            return;
+        };
+        let Some(spec) = block.chart() else {
+            return;
+        };
+        let columns = self
+            .chart_data(block, spec, cx)
+            .map_or(0, |data| data.column_count());
+
+        if let Some(spec) = self
+            .blocks
+            .iter_mut()
+            .find(|b| b.id == block_id)
+            .and_then(Block::chart_mut)
+        {
+            f(spec, columns);
+            self.save_document(cx);
+            cx.notify();
+        }
+    }
+
+    fn cycle_chart_kind(&mut self, block_id: Uuid, cx: &mut Context<Self>) {
+        self.update_chart(block_id, cx, |spec, _| spec.kind = spec.kind.next());
+    }
+
+    /// Step the data source through inline data and each table block
+    fn cycle_chart_source(&mut self, block_id: Uuid, cx: &mut Context<Self>) {
+        let tables = self.table_ids();
+        self.update_chart(block_id, cx, |spec, _| {
+            let next = match spec.source {
+                None => 0,
+                Some(id) => tables.iter().position(|t| *t == id).map_or(0, |i| i + 1),
+            };
+            spec.source = tables.get(next).copied();
+        });
+    }
+
+    fn cycle_chart_x(&mut self, block_id: Uuid, cx: &mut Context<Self>) {
+        self.update_chart(block_id, cx, |spec, columns| {
+            spec.x = (spec.x + 1) % columns.max(1);
+        });
+    }
+
+    fn cycle_chart_y(&mut self, block_id: Uuid, cx: &mut Context<Self>) {
+        self.update_chart(block_id, cx, |spec, columns| {
+            spec.y = (spec.y + 1) % columns.max(1);
+        });
+    }
+
+    fn change_chart_bins(&mut self, block_id: Uuid, delta: isize, cx: &mut Context<Self>) {
+        self.update_chart(block_id, cx, |spec, _| {
+            spec.bins = spec
+                .bins
+                .saturating_add_signed(delta)
+                .clamp(ChartSpec::MIN_BINS, ChartSpec::MAX_BINS);
+        });
+    }
+
+    /// Ask for a destination and write the chart there
+    fn export_chart(
+        &mut self,
+        index: usize,
+        format: ChartExport,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(block) = self.blocks.get(index) else {
+            return;
+        };
+        let Some(spec) = block.chart() else {
+            return;
+        };
+        let plot = match self
+            .chart_data(block, spec, cx)
+            .and_then(|data| Plot::new(spec, &data))
+        {
+            Ok(plot) => plot,
+            Err(e) => {
+                self.show_error("Failed to export chart", &e.to_string(), window, cx);
+                return;
+            }
+        };
+        let bytes = match format {
+            ChartExport::Svg => Ok(plot.to_svg().into_bytes()),
+            ChartExport::Png => plot.to_png(),
+        };
+        let bytes = match bytes {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                self.show_error("Failed to export chart", &e.to_string(), window, cx);
+                return;
+            }
+        };
+
+        let directory = directories::UserDirs::new()
+            .map(|dirs| dirs.home_dir().to_path_buf())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let name = format!("chart.{}", format.extension());
+        let path = cx.prompt_for_new_path(&directory, Some(&name));
+
+        cx.spawn_in(window, async move |this, cx| {
+            let Ok(Ok(Some(path))) = path.await else {
+                return;
+            };
+            if let Err(e) = std::fs::write(&path, bytes) {
+                this.update_in(cx, |this, window, cx| {
+                    this.show_error("Failed to export chart", &e.to_string(), window, cx);
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    pub(super) fn render_chart_block(
+        &self,
+        index: usize,
+        block: &Block,
+        spec: &ChartSpec,
+        window: &Window,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let block_id = block.id;
+        let data = self.chart_data(block, spec, cx);
+        let column_name = |column: usize| {
+            data.as_ref()
+                .ok()
+                .and_then(|data| data.columns.get(column.min(data.column_count().saturating_sub(1))))
+                .map_or_else(String::new, |c| c.name.clone())
+        };
+        let source = match spec.source {
+            None => "Inline data".to_string(),
+            Some(id) => match self.table_ids().iter().position(|t| *t == id) {
+                Some(i) => format!("Table {}", i + 1),
+                None => "Missing table".to_string(),
+            },
+        };
+        let histogram = spec.kind == ChartKind::Histogram;
+
+        let mut toolbar = div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .child(
+                Button::new(("chart-kind", index))
+                    .label(spec.kind.display_name())
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.cycle_chart_kind(block_id, cx);
+                    })),
+            )
+            .child(
+                Button::new(("chart-source", index))
+                    .label(source)
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.cycle_chart_source(block_id, cx);
+                    })),
+            )
+            .child(
+                Button::new(("chart-x", index))
+                    .label(format!("X: {}", column_name(spec.x)))
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.cycle_chart_x(block_id, cx);
+                    })),
+            );
+
+        toolbar = if histogram {
+            toolbar
+                .child(
+                    Button::new(("chart-fewer-bins", index))
+                        .label("−")
+                        .xsmall()
+                        .ghost()
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.change_chart_bins(block_id, -1, cx);
+                        })),
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x9399b2))
+                        .child(format!("{} bins", spec.bins)),
+                )
+                .child(
+                    Button::new(("chart-more-bins", index))
+                        .label("+")
+                        .xsmall()
+                        .ghost()
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.change_chart_bins(block_id, 1, cx);
+                        })),
+                )
+        } else {
+            toolbar.child(
+                Button::new(("chart-y", index))
+                    .label(format!("Y: {}", column_name(spec.y)))
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.cycle_chart_y(block_id, cx);
+                    })),
+            )
+        };
+
+        let toolbar = toolbar
+            .child(div().flex_1())
+            .child(
+                Button::new(("chart-svg", index))
+                    .label("Export SVG")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, window, cx| {
+                        this.export_chart(index, ChartExport::Svg, window, cx);
+                    })),
+            )
+            .child(
+                Button::new(("chart-png", index))
+                    .label("Export PNG")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, window, cx| {
+                        this.export_chart(index, ChartExport::Png, window, cx);
+                    })),
+            );
+
+        let chart = match data.and_then(|data| Plot::new(spec, &data)) {
+            Ok(plot) => plot.render(),
+            Err(message) => div()
+                .py_2()
+                .text_sm()
+                .text_color(rgb(0xf38ba8))
+                .child(message)
+                .into_any_element(),
+        };
+
+        // Inline data is edited as the block text below the chart
+        let inline = spec.source.is_none();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(toolbar)
+            .child(chart)
+            .children(inline.then(|| block.render(window, cx)))
+            .into_any_element()
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use gpui::*;
+use love_note_plugin_api::ExportFormat;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::LoveNote;
+use crate::block::Block;
+use crate::markdown;
+use crate::storage::StoredBlock;
+
+/// Blocks attached to the clipboard text
+#[derive(Serialize, Deserialize)]
+struct CopiedBlocks {
+    blocks: Vec<StoredBlock>,
+}
+
+/// Copies of `blocks` under new ids. Blocks nested in a copied block are
+/// nested in its copy; the others keep their parent.
+pub(super) fn with_new_ids(blocks: &[StoredBlock]) -> Vec<StoredBlock> {
+    let mut new_ids = HashMap::new();
+    blocks
+        .iter()
+        .map(|block| {
+            let mut copy = block.clone();
+            copy.id = Uuid::new_v4();
+            new_ids.insert(block.id, copy.id);
+            copy.parent = block
+                .parent
+                .map(|parent| new_ids.get(&parent).copied().unwrap_or(parent));
+            copy.created_at = chrono::Utc::now();
+            copy.updated_at = copy.created_at;
+            copy
+        })
+        .collect()
+}
+
+/// Attachments of the blocks on the clipboard, which cleanup keeps so cut
+/// images can still be pasted
+pub(super) fn clipboard_attachments(cx: &App) -> HashSet<String> {
+    let Some(item) = cx.read_from_clipboard() else {
+        return HashSet::new();
+    };
+    item.entries()
+        .iter()
+        .filter_map(|entry| match entry {
+            ClipboardEntry::String(string) => string.metadata_json::<CopiedBlocks>(),
+            _ => None,
+        })
+        .flat_map(|copied| copied.blocks)
+        .filter_map(|block| block.attachment().map(str::to_string))
+        .collect()
+}
+
+impl LoveNote {
+    /// Put the blocks in `range` on the clipboard
+    pub(super) fn copy_blocks(&self, range: Range<usize>, cx: &mut Context<Self>) {
+        let blocks: Vec<StoredBlock> = self.blocks[range]
+            .iter()
+            .map(|block| block.to_stored(cx))
+            .collect();
+        let images = self.storage.images();
+        let text = markdown::to_markdown(
+            &blocks,
+            |file| images.path(file).display().to_string(),
+            |block| self.plugin_exports.export(block, ExportFormat::Markdown),
+        );
+        cx.write_to_clipboard(ClipboardItem::new_string_with_json_metadata(
+            text,
+            CopiedBlocks { blocks },
+        ));
+    }
+
+    /// Blocks to paste: blocks copied from a note, or Markdown that is
+    /// more than a single paragraph. Plain text is left to the input.
+    fn clipboard_blocks(&self, cx: &App) -> Option<Vec<StoredBlock>> {
+        let item = cx.read_from_clipboard()?;
+        let copied = item.entries().iter().find_map(|entry| match entry {
+            ClipboardEntry::String(string) => string.metadata_json::<CopiedBlocks>(),
+            _ => None,
+        });
+        if let Some(copied) = copied {
+            return Some(copied.blocks);
+        }
+
+        let mut blocks = markdown::from_markdown(&item.text()?);
+        if let [block] = blocks.as_slice()
+            && block.kind == "text"
+        {
+            return None;
+        }
+        if let Err(e) = markdown::paste_images(&mut blocks, self.storage.images()) {
+            eprintln!("Failed to paste images: {}", e);
+            return None;
+        }
+        Some(blocks)
+    }
+
+    /// Paste blocks after the selected blocks or the focused block. An
+    /// empty focused block is replaced. Returns true if there was
+    /// something to paste.
+    pub(super) fn paste_blocks(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        let (index, parent, replace) = match self.selected_range() {
+            Some(range) => (range.end, self.blocks[range.start].parent, None),
+            None => {
+                let Some(index) = self.focused_block_index(window, cx) else {
+                    return false;
+                };
+                let block = &self.blocks[index];
+                // Code, math and the like take the clipboard text as is
+                if !block.splits_on_enter() {
+                    return false;
+                }
+                let end = self.subtree_end(index);
+                let empty = end == index + 1 && block.get_content(cx).trim().is_empty();
+                (end, block.parent, empty.then_some(index))
+            }
+        };
+        let Some(blocks) = self.clipboard_blocks(cx).filter(|blocks| !blocks.is_empty()) else {
+            return false;
+        };
+
+        let mut blocks = with_new_ids(&blocks);
+        let pasted: Vec<Uuid> = blocks.iter().map(|block| block.id).collect();
+        for block in &mut blocks {
+            if !block.parent.is_some_and(|p| pasted.contains(&p)) {
+                block.parent = parent;
+            }
+        }
+
+        let mut at = index;
+        // Saved along with the pasted blocks
+        if let Some(empty) = replace
+            && self.take_block(empty).is_some()
+        {
+            at -= 1;
+        }
+        let blocks = blocks
+            .iter()
+            .map(|stored| Block::from_stored(stored, &self.storage, window, cx))
+            .collect();
+        self.insert_blocks(at, blocks, window, cx);
+
+        let (first, last) = (pasted[0], pasted[pasted.len() - 1]);
+        if self.block_selection.is_some() {
+            self.select_blocks(first, last, window, cx);
+        } else if let Some(block) = self.blocks.iter().find(|b| b.id == last) {
+            let end = block.get_content(cx).len();
+            block.focus_at(end, window, cx);
+        }
+        true
+    }
+}
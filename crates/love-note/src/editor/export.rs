@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use gpui::*;
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    Sizable,
+};
+use love_note_plugin_api::ExportHook;
+
+use super::LoveNote;
+use crate::export::{self, PluginExports};
+use crate::storage::{AttachmentStore, Document};
+
+/// Folder export dialogs start in
+pub(super) fn export_directory() -> PathBuf {
+    directories::UserDirs::new()
+        .map(|dirs| dirs.home_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+impl LoveNote {
+    /// Let a plugin decide how blocks of `kind` are exported
+    pub fn register_export_hook(&mut self, kind: impl Into<String>, hook: Arc<dyn ExportHook>) {
+        self.plugin_exports.register(kind, hook);
+    }
+
+    /// The text of the first heading, or "Untitled"
+    pub(super) fn document_title(&self, cx: &App) -> String {
+        self.blocks
+            .iter()
+            .find(|block| block.kind == "heading")
+            .map(|block| block.get_content(cx).trim().to_string())
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| "Untitled".to_string())
+    }
+
+    fn toggle_export_menu(&mut self, cx: &mut Context<Self>) {
+        self.export_menu = !self.export_menu;
+        cx.notify();
+    }
+
+    /// Ask for a destination and write the document there with `write`,
+    /// which returns the path of the written file
+    fn export_document(
+        &mut self,
+        extension: &str,
+        write: impl FnOnce(&Document, &Path, &AttachmentStore, &PluginExports) -> Result<PathBuf>
+            + Send
+            + 'static,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.export_menu = false;
+        cx.notify();
+
+        let document = self.document(cx);
+        let name = export::file_name(&self.document_title(cx), extension);
+        self.export_as(document, &name, write, window, cx);
+    }
+
+    /// Ask for a destination, suggesting `name`, and write `document`
+    /// there with `write`
+    pub(super) fn export_as(
+        &self,
+        document: Document,
+        name: &str,
+        write: impl FnOnce(&Document, &Path, &AttachmentStore, &PluginExports) -> Result<PathBuf>
+            + Send
+            + 'static,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let storage = self.storage.clone();
+        let plugins = self.plugin_exports.clone();
+        let path = cx.prompt_for_new_path(&export_directory(), Some(name));
+
+        cx.spawn_in(window, async move |this, cx| {
+            let Ok(Ok(Some(path))) = path.await else {
+                return;
+            };
+            // Laying out pages and rendering charts can take a while
+            let written = cx
+                .background_executor()
+                .spawn(async move { write(&document, &path, storage.images(), &plugins) })
+                .await;
+            if let Err(e) = written {
+                this.update_in(cx, |this, window, cx| {
+                    this.show_error("Failed to export document", &e.to_string(), window, cx);
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    fn export_pdf(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let options = self.pdf_options;
+        self.export_document(
+            "pdf",
+            move |document, path, images, _| export::write_pdf(document, path, images, &options),
+            window,
+            cx,
+        );
+    }
+
+    pub(super) fn render_export_button(&self, cx: &mut Context<Self>) -> AnyElement {
+        let options = self.pdf_options;
+        let menu = self.export_menu.then(|| {
+            div()
+                .absolute()
+                .top_full()
+                .right_0()
+                .occlude()
+                .flex()
+                .flex_col()
+                .items_end()
+                .px_2()
+                .py_1()
+                .bg(rgb(0x313244))
+                .rounded_md()
+                .shadow_md()
+                .child(
+                    Button::new("export-markdown")
+                        .label("Markdown")
+                        .xsmall()
+                        .ghost()
+                        .on_click(cx.listener(|this, _, window, cx| {
+                            this.export_document("md", export::write_markdown, window, cx);
+                        })),
+                )
+                .child(
+                    Button::new("export-html")
+                        .label("HTML")
+                        .xsmall()
+                        .ghost()
+                        .on_click(cx.listener(|this, _, window, cx| {
+                            this.export_document("html", export::write_html, window, cx);
+                        })),
+                )
+                .child(
+                    Button::new("export-latex")
+                        .label("LaTeX")
+                        .xsmall()
+                        .ghost()
+                        .on_click(cx.listener(|this, _, window, cx| {
+                            this.export_document("tex", export::write_latex, window, cx);
+                        })),
+                )
+                .child(
+                    Button::new("export-notebook")
+                        .label("Notebook")
+                        .xsmall()
+                        .ghost()
+                        .on_click(cx.listener(|this, _, window, cx| {
+                            this.export_document("ipynb", export::write_notebook, window, cx);
+                        })),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_1()
+                        .child(
+                            Button::new("export-pdf-page-size")
+                                .label(options.page_size.display_name())
+                                .xsmall()
+                                .ghost()
+                                .tooltip("Page size")
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.pdf_options.page_size = this.pdf_options.page_size.next();
+                                    cx.notify();
+                                })),
+                        )
+                        .child(
+                            Button::new("export-pdf-margins")
+                                .label(options.margins.display_name())
+                                .xsmall()
+                                .ghost()
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.pdf_options.margins = this.pdf_options.margins.next();
+                                    cx.notify();
+                                })),
+                        )
+                        .child(
+                            Button::new("export-pdf")
+                                .label("PDF")
+                                .xsmall()
+                                .ghost()
+                                .on_click(cx.listener(|this, _, window, cx| {
+                                    this.export_pdf(window, cx);
+                                })),
+                        ),
+                )
+        });
+
+        div()
+            .relative()
+            .child(
+                Button::new("export")
+                    .label("Export ▾")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.toggle_export_menu(cx);
+                    })),
+            )
+            .children(menu)
+            .into_any_element()
+    }
+}
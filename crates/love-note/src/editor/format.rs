@@ -0,0 +1,108 @@
+use std::ops::Range;
+
+use gpui::*;
+
+use super::LoveNote;
+use crate::block::{MarkKind, Marks, TEXT_COLORS};
+
+impl LoveNote {
+    /// Byte range selected in the focused block, with its index
+    fn text_selection(&self, window: &mut Window, cx: &mut App) -> Option<(usize, Range<usize>)> {
+        let index = self.focused_block_index(window, cx)?;
+        let block = &self.blocks[index];
+        block.marks()?;
+
+        let range = block.selection(window, cx)?;
+        (!range.is_empty()).then_some((index, range))
+    }
+
+    /// Change the formatting of the focused block's selection
+    fn update_marks(
+        &mut self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+        f: impl FnOnce(&mut Marks, Range<usize>),
+    ) {
+        let Some((index, range)) = self.text_selection(window, cx) else {
+            return;
+        };
+        if let Some(marks) = self.blocks[index].marks_mut() {
+            let range = marks.snap(range);
+            f(marks, range);
+            self.save_document(cx);
+            cx.notify();
+        }
+    }
+
+    fn toggle_mark(&mut self, kind: MarkKind, window: &mut Window, cx: &mut Context<Self>) {
+        self.update_marks(window, cx, |marks, range| {
+            marks.toggle(range, kind);
+        });
+    }
+
+    /// Link the selection to the URL on the clipboard, or unlink it if it
+    /// is already a link
+    fn toggle_link(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let url = cx
+            .read_from_clipboard()
+            .and_then(|item| item.text())
+            .map(|text| text.trim().to_string())
+            .filter(|text| ["http://", "https://", "mailto:"].iter().any(|p| text.starts_with(p)));
+
+        let mut missing = false;
+        self.update_marks(window, cx, |marks, range| {
+            let link = MarkKind::Link(String::new());
+            if marks.kind_at(&range, &link).is_some() {
+                marks.remove(range, &link);
+            } else if let Some(url) = url {
+                marks.add(range, MarkKind::Link(url));
+            } else {
+                missing = true;
+            }
+        });
+        if missing {
+            self.show_error("Failed to add link", "Clipboard does not contain a link", window, cx);
+        }
+    }
+
+    /// Step the selection's color through the palette and back to none
+    fn cycle_text_color(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.update_marks(window, cx, |marks, range| {
+            let color = MarkKind::Color(0);
+            let next = match marks.kind_at(&range, &color) {
+                Some(MarkKind::Color(current)) => TEXT_COLORS
+                    .iter()
+                    .position(|c| c == current)
+                    .and_then(|i| TEXT_COLORS.get(i + 1)),
+                _ => TEXT_COLORS.first(),
+            };
+            match next {
+                Some(next) => marks.add(range, MarkKind::Color(*next)),
+                None => marks.remove(range, &color),
+            }
+        });
+    }
+
+    /// Handle a formatting shortcut. Returns true if the keystroke was one.
+    pub(super) fn format_shortcut(
+        &mut self,
+        keystroke: &Keystroke,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let modifiers = &keystroke.modifiers;
+        if !modifiers.control || modifiers.alt {
+            return false;
+        }
+        match (keystroke.key.as_str(), modifiers.shift) {
+            ("b", false) => self.toggle_mark(MarkKind::Bold, window, cx),
+            ("i", false) => self.toggle_mark(MarkKind::Italic, window, cx),
+            ("e", false) => self.toggle_mark(MarkKind::Code, window, cx),
+            ("x", true) => self.toggle_mark(MarkKind::Strikethrough, window, cx),
+            ("k", false) => self.toggle_link(window, cx),
+            ("h", true) => self.cycle_text_color(window, cx),
+            _ => return false,
+        }
+        true
+    }
+}
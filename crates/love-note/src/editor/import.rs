@@ -0,0 +1,78 @@
+use gpui::*;
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    Sizable,
+};
+
+use super::LoveNote;
+use crate::import;
+
+impl LoveNote {
+    /// Ask for a Markdown file or folder or a Jupyter notebook, store
+    /// the documents in it and open the first one
+    fn import_documents(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: true,
+            multiple: false,
+            prompt: Some("Import Markdown or notebook".into()),
+        });
+
+        cx.spawn_in(window, async move |this, cx| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(path) = paths.into_iter().next() else {
+                return;
+            };
+            let Ok(storage) = this.update(cx, |this, _| this.storage.clone()) else {
+                return;
+            };
+            let name = path.display().to_string();
+            // Reading a folder of notes and their images can take a while
+            let imported = cx
+                .background_executor()
+                .spawn(async move {
+                    // Imported images aren't referenced until the documents are saved
+                    let _pause = storage.images().pause_cleanup();
+                    let notebook = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ipynb"));
+                    let imported = if notebook {
+                        import::import_notebook(&path, storage.images())
+                    } else {
+                        import::import_markdown(&path, storage.images())
+                    }?;
+                    for document in &imported.documents {
+                        storage.save_document(document)?;
+                    }
+                    Ok::<_, anyhow::Error>(imported)
+                })
+                .await;
+            this.update_in(cx, |this, window, cx| {
+                let imported = match imported {
+                    Ok(imported) => imported,
+                    Err(e) => {
+                        this.show_error(&format!("Failed to import {}", name), &e.to_string(), window, cx);
+                        return;
+                    }
+                };
+                this.show_warnings("Some files couldn't be imported", &imported.skipped, window, cx);
+                if let Some(document) = imported.documents.into_iter().next() {
+                    this.open_document(document, window, cx);
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    pub(super) fn render_import_button(&self, cx: &mut Context<Self>) -> AnyElement {
+        Button::new("import")
+            .label("Import…")
+            .xsmall()
+            .ghost()
+            .on_click(cx.listener(|this, _, window, cx| {
+                this.import_documents(window, cx);
+            }))
+            .into_any_element()
+    }
+}
@@ -0,0 +1,1203 @@
+/// Backing up and restoring the whole vault from the title bar, and
+/// automatic backups in the background.
+mod backup;
+/// Chart blocks: choosing what to plot and exporting the result.
+mod chart;
+/// Copying blocks to the clipboard and pasting them back.
+///
+/// Copied blocks go on the clipboard as Markdown, for other applications,
+/// with the blocks themselves attached so pasting into a note restores
+/// them exactly. Markdown pasted from elsewhere is parsed into blocks.
+mod clipboard;
+/// Exporting the document to other formats from the title bar.
+mod export;
+/// Importing documents from the title bar.
+mod import;
+/// Inline formatting of rich text blocks from keyboard shortcuts.
+mod format;
+/// Keyboard editing across blocks: Enter splits a block, Backspace at its
+/// start joins it to the previous one, and the arrow keys move between
+/// blocks at the first and last line.
+mod navigate;
+/// Moving blocks: dragging a block's handle onto an insert line, or
+/// Alt+Up/Down on the focused block.
+mod reorder;
+/// Searching every document from the title bar and jumping to a result.
+mod search;
+/// Selecting a run of blocks, by shift-clicking or by dragging from one
+/// block into another, and acting on them together.
+mod selection;
+/// The slash command menu: typing `/` in an empty text block lists the
+/// block kinds, filtered by what follows the slash.
+mod slash;
+/// Spreadsheet-style editing of table blocks.
+///
+/// A single shared input is moved between cells, so the table itself only
+/// ever renders plain text plus the one cell being edited.
+mod table;
+/// Toggle blocks: nesting blocks under a summary line and collapsing them.
+mod toggle;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use gpui::{prelude::FluentBuilder, *};
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    checkbox::Checkbox,
+    input::{Backspace, Enter, Escape, InputEvent, InputState, MoveDown, MoveUp, Paste},
+    Sizable,
+};
+use uuid::Uuid;
+
+use crate::block::{Block, BlockKind, CodeBlock, ImageBlock, ListStyle, LANGUAGES};
+use crate::export::{PdfOptions, PluginExports};
+use crate::storage::{Document, SearchHit, Storage};
+use love_note_plugin_api::BlockKindInfo;
+use clipboard::clipboard_attachments;
+use reorder::DraggedBlock;
+use selection::BlockSelection;
+use slash::SlashMenu;
+use table::CellRef;
+use toggle::InsertSlot;
+
+/// Position of a block in the document's numbered sequences
+#[derive(Clone, Copy)]
+struct Numbering {
+    /// Item number within a run of numbered list items
+    list: usize,
+    /// Equation number, if the block is a numbered equation
+    equation: Option<usize>,
+}
+
+/// Indentation per level of toggle nesting
+const NEST_INDENT: f32 = 24.;
+
+/// Most warnings listed in one prompt
+const MAX_WARNINGS: usize = 10;
+
+/// The main Love Note editor component
+pub struct LoveNote {
+    blocks: Vec<Block>,
+    hovered_insert_line: Option<InsertSlot>,
+    /// The open document's title and metadata; its blocks are `blocks`
+    document: Document,
+    storage: Arc<Storage>,
+    /// Track if any block was previously focused (for auto-save on blur)
+    had_focus: bool,
+    /// Input event subscriptions, keyed by block id
+    block_subscriptions: HashMap<Uuid, Subscription>,
+    /// Code block whose language selector is open
+    language_menu: Option<Uuid>,
+    /// Block whose "turn into" menu is open
+    turn_into_menu: Option<Uuid>,
+    /// Slash command menu, while a command is being typed
+    slash_menu: Option<SlashMenu>,
+    /// Block kinds provided by plugins
+    plugin_kinds: Vec<BlockKindInfo>,
+    /// Export hooks of plugin block kinds
+    plugin_exports: PluginExports,
+    /// Whether the export format menu is open
+    export_menu: bool,
+    /// Page setup chosen for PDF export
+    pdf_options: PdfOptions,
+    /// Blocks selected as a group
+    block_selection: Option<BlockSelection>,
+    /// Block the mouse was pressed in, while the button is held
+    pressed_block: Option<Uuid>,
+    /// Focus for the editor itself, held while blocks are selected
+    focus_handle: FocusHandle,
+    /// Table cell being edited, if any
+    active_cell: Option<CellRef>,
+    /// Shared input used to edit the active table cell
+    cell_input: Entity<InputState>,
+    _cell_subscription: Subscription,
+    /// Toggle blocks whose children are hidden
+    collapsed: HashSet<Uuid>,
+    /// Query typed in the title bar search
+    search_input: Entity<InputState>,
+    _search_subscription: Subscription,
+    /// Blocks matching the search query
+    search_hits: Vec<SearchHit>,
+    /// The search for the latest query, dropped when the query changes
+    search_task: Option<Task<()>>,
+    /// Why the last automatic backup failed, cleared once one succeeds
+    backup_error: Option<SharedString>,
+    /// Automatic backups, running while the editor is open
+    _backup_task: Task<()>,
+}
+
+impl LoveNote {
+    pub fn new(storage: Arc<Storage>, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        // Load or create default document
+        let mut document = storage
+            .get_or_create_default()
+            .expect("Failed to load or create document");
+        let blocks = Self::load_blocks(&mut document, &storage, window, cx);
+
+        // Drop image files left behind by deleted blocks
+        if let Err(e) = storage.collect_garbage_except(clipboard_attachments(cx)) {
+            eprintln!("Failed to clean up attachments: {}", e);
+        }
+
+        let block_subscriptions = blocks
+            .iter()
+            .map(|block| (block.id, Self::subscribe_block(block, window, cx)))
+            .collect();
+
+        let (cell_input, cell_subscription) = Self::new_cell_input(window, cx);
+        let (search_input, search_subscription) = Self::new_search_input(window, cx);
+
+        let collapsed = storage.collapsed_blocks().unwrap_or_else(|e| {
+            eprintln!("Failed to load toggle state: {}", e);
+            HashSet::new()
+        });
+
+        let backup_task = Self::schedule_backups(storage.clone(), cx);
+
+        Self {
+            blocks,
+            hovered_insert_line: None,
+            document,
+            storage,
+            had_focus: false,
+            block_subscriptions,
+            language_menu: None,
+            turn_into_menu: None,
+            slash_menu: None,
+            plugin_kinds: Vec::new(),
+            plugin_exports: PluginExports::default(),
+            export_menu: false,
+            pdf_options: PdfOptions::default(),
+            block_selection: None,
+            pressed_block: None,
+            focus_handle: cx.focus_handle(),
+            active_cell: None,
+            cell_input,
+            _cell_subscription: cell_subscription,
+            collapsed,
+            search_input,
+            _search_subscription: search_subscription,
+            search_hits: Vec::new(),
+            search_task: None,
+            backup_error: None,
+            _backup_task: backup_task,
+        }
+    }
+
+    /// Take the blocks out of `document` as UI blocks
+    fn load_blocks(
+        document: &mut Document,
+        storage: &Storage,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Vec<Block> {
+        if document.blocks.is_empty() {
+            // Create a default text block if document is empty
+            return vec![BlockKind::Text.create_block(window, cx)];
+        }
+        std::mem::take(&mut document.blocks)
+            .iter()
+            .map(|stored| Block::from_stored(stored, storage, window, cx))
+            .collect()
+    }
+
+    /// Replace the open document with `document`
+    pub fn open_document(&mut self, document: Document, window: &mut Window, cx: &mut Context<Self>) {
+        self.save_document(cx);
+        self.show_document(document, window, cx);
+    }
+
+    /// Show `document` in place of the open one without saving it first
+    fn show_document(&mut self, mut document: Document, window: &mut Window, cx: &mut Context<Self>) {
+        self.blocks = Self::load_blocks(&mut document, &self.storage, window, cx);
+        self.block_subscriptions = self
+            .blocks
+            .iter()
+            .map(|block| (block.id, Self::subscribe_block(block, window, cx)))
+            .collect();
+        self.document = document;
+        self.block_selection = None;
+        self.active_cell = None;
+        self.slash_menu = None;
+        self.turn_into_menu = None;
+        self.language_menu = None;
+        self.hovered_insert_line = None;
+        // Saving marks it as the most recently edited document, so it
+        // opens again next time
+        self.save_document(cx);
+        cx.notify();
+    }
+
+    /// Subscribe to a block's input events
+    fn subscribe_block(block: &Block, window: &mut Window, cx: &mut Context<Self>) -> Subscription {
+        let block_id = block.id;
+        cx.subscribe_in(&block.input, window, move |this, _, event: &InputEvent, window, cx| {
+            this.on_block_input_event(block_id, event, window, cx);
+        })
+    }
+
+    fn on_block_input_event(
+        &mut self,
+        block_id: Uuid,
+        event: &InputEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !matches!(event, InputEvent::Change) {
+            return;
+        }
+        let Some(index) = self.blocks.iter().position(|b| b.id == block_id) else {
+            return;
+        };
+
+        if self.apply_shortcut(index, window, cx) {
+            return;
+        }
+        self.update_slash_menu(index, cx);
+        let changed = self.blocks[index].handle_text_changed(window, cx);
+        if changed {
+            cx.notify();
+        }
+    }
+
+    /// Convert a text block that starts with a Markdown shortcut (`# `,
+    /// `- `, `[] `, ...) into the matching kind. Only a shortcut just
+    /// typed converts, so editing text that already starts with one
+    /// leaves it alone. Returns true if it did.
+    fn apply_shortcut(&mut self, index: usize, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        let block = &self.blocks[index];
+        if block.kind != BlockKind::Text.kind_string() {
+            return false;
+        }
+        // The marks still refer to the text before this change
+        if block
+            .marks()
+            .is_some_and(|marks| BlockKind::from_shortcut(marks.text()).is_some())
+        {
+            return false;
+        }
+        let text = block.get_content(cx);
+        let Some((kind, rest)) = BlockKind::from_shortcut(&text) else {
+            return false;
+        };
+
+        // "```python" names the language rather than starting the code
+        if kind == BlockKind::Code
+            && let Some(language) = CodeBlock::language_id(rest.trim())
+        {
+            let mut converted = self.blocks[index].converted(kind, "", window, cx);
+            converted.set_code_language(language);
+            self.replace_block(index, converted, window, cx);
+            self.blocks[index].focus(window, cx);
+            return true;
+        }
+        self.convert_text_block(index, kind, rest, window, cx);
+        true
+    }
+
+    /// Replace the text block at `index` with a new block of `kind`
+    /// holding `text`, and keep typing in it
+    fn convert_text_block(
+        &mut self,
+        index: usize,
+        kind: BlockKind,
+        text: &str,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let converted = self.blocks[index].converted(kind, text, window, cx);
+        let parent = converted.parent;
+        self.replace_block(index, converted, window, cx);
+
+        // A divider has no text, so carry on typing in a new block below
+        if kind == BlockKind::Divider {
+            self.insert_block_at(index + 1, parent, BlockKind::Text, window, cx);
+            self.blocks[index + 1].focus(window, cx);
+        } else {
+            self.blocks[index].focus(window, cx);
+        }
+    }
+
+    /// Change the kind of the block at `index`, keeping its text
+    fn turn_into(
+        &mut self,
+        index: usize,
+        kind: BlockKind,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if index < self.blocks.len() {
+            self.convert_block(index, kind, window, cx);
+            self.turn_into_menu = None;
+            self.blocks[index].focus(window, cx);
+        }
+    }
+
+    /// Replace the block at `index` with one of `kind` holding its text
+    fn convert_block(
+        &mut self,
+        index: usize,
+        kind: BlockKind,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let block = &self.blocks[index];
+        let text = block.get_content(cx);
+        let converted = block.converted(kind, &text, window, cx);
+        let old = self.replace_block(index, converted, window, cx);
+        if old.is_toggle() && !self.blocks[index].is_toggle() {
+            self.release_children(&old);
+            self.save_document(cx);
+        }
+    }
+
+    /// Apply the kind chosen in the "turn into" menu to the selected
+    /// blocks, or else to the block the menu was opened for
+    fn choose_turn_into(&mut self, kind: BlockKind, window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(range) = self.selected_range() {
+            self.turn_selection_into(range, kind, window, cx);
+        } else if let Some(index) = self
+            .turn_into_menu
+            .and_then(|id| self.blocks.iter().position(|b| b.id == id))
+        {
+            self.turn_into(index, kind, window, cx);
+        }
+    }
+
+    fn toggle_turn_into_menu(&mut self, block_id: Uuid, cx: &mut Context<Self>) {
+        self.turn_into_menu = match self.turn_into_menu {
+            Some(open) if open == block_id => None,
+            _ => Some(block_id),
+        };
+        cx.notify();
+    }
+
+    fn toggle_checked(&mut self, index: usize, checked: bool, cx: &mut Context<Self>) {
+        if let Some(block) = self.blocks.get_mut(index) {
+            block.set_checked(checked);
+            self.save_document(cx);
+            cx.notify();
+        }
+    }
+
+    /// Check if any block currently has focus
+    fn any_block_focused(&self, window: &Window, cx: &App) -> bool {
+        self.blocks.iter().any(|block| {
+            let input_state = block.input.read(cx);
+            input_state.focus_handle(cx).is_focused(window)
+        })
+    }
+
+    /// The document as it currently stands in the editor
+    fn document(&self, cx: &App) -> Document {
+        let mut document = self.document.clone();
+        document.blocks = self.blocks.iter().map(|b| b.to_stored(cx)).collect();
+        document.updated_at = chrono::Utc::now();
+        document
+    }
+
+    /// Save the current document to storage
+    fn save_document(&self, cx: &App) {
+        let document = self.document(cx);
+        if let Err(e) = self.storage.save_document(&document) {
+            eprintln!("Failed to save document: {}", e);
+        }
+    }
+
+    /// Tell the user about problems that didn't stop an action, such as
+    /// files an import had to skip
+    fn show_warnings(&self, message: &str, warnings: &[String], window: &mut Window, cx: &mut App) {
+        if warnings.is_empty() {
+            return;
+        }
+        let mut detail = warnings[..warnings.len().min(MAX_WARNINGS)].join("\n");
+        if warnings.len() > MAX_WARNINGS {
+            detail.push_str(&format!("\n…and {} more", warnings.len() - MAX_WARNINGS));
+        }
+        // Nothing to do once it's dismissed
+        drop(window.prompt(PromptLevel::Warning, message, Some(&detail), &["OK"], cx));
+    }
+
+    /// Tell the user an action failed and why
+    fn show_error(&self, message: &str, error: &str, window: &mut Window, cx: &mut App) {
+        drop(window.prompt(PromptLevel::Critical, message, Some(error), &["OK"], cx));
+    }
+
+    fn insert_block_at(
+        &mut self,
+        index: usize,
+        parent: Option<Uuid>,
+        kind: BlockKind,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let mut new_block = kind.create_block(window, cx);
+        new_block.parent = parent;
+        self.insert_block(index, new_block, window, cx);
+    }
+
+    fn insert_block(
+        &mut self,
+        index: usize,
+        block: Block,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.insert_blocks(index, vec![block], window, cx);
+    }
+
+    /// Insert `blocks` in order from `index`, saving once
+    fn insert_blocks(
+        &mut self,
+        index: usize,
+        blocks: Vec<Block>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        for (offset, block) in blocks.into_iter().enumerate() {
+            let subscription = Self::subscribe_block(&block, window, cx);
+            self.block_subscriptions.insert(block.id, subscription);
+            self.blocks.insert(index + offset, block);
+        }
+        self.hovered_insert_line = None;
+        self.save_document(cx);
+        cx.notify();
+    }
+
+    /// Put `block` in place of the block at `index`, returning the old one
+    fn replace_block(
+        &mut self,
+        index: usize,
+        block: Block,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Block {
+        let subscription = Self::subscribe_block(&block, window, cx);
+        self.block_subscriptions.insert(block.id, subscription);
+        let old = std::mem::replace(&mut self.blocks[index], block);
+        self.save_document(cx);
+        cx.notify();
+        old
+    }
+
+    fn remove_block(&mut self, index: usize, cx: &mut Context<Self>) {
+        if let Some(block) = self.take_block(index) {
+            self.save_document(cx);
+            if block.attachment().is_some() {
+                self.collect_garbage(cx);
+            }
+            cx.notify();
+        }
+    }
+
+    /// Remove the block at `index` without saving
+    fn take_block(&mut self, index: usize) -> Option<Block> {
+        if index >= self.blocks.len() {
+            return None;
+        }
+        let block = self.blocks.remove(index);
+        self.block_subscriptions.remove(&block.id);
+        self.release_children(&block);
+        Some(block)
+    }
+
+    /// Remove image files no block refers to any more, keeping those of
+    /// blocks on the clipboard
+    fn collect_garbage(&self, cx: &App) {
+        if let Err(e) = self.storage.collect_garbage_except(clipboard_attachments(cx)) {
+            eprintln!("Failed to clean up attachments: {}", e);
+        }
+    }
+
+    fn toggle_language_menu(&mut self, block_id: Uuid, cx: &mut Context<Self>) {
+        self.language_menu = match self.language_menu {
+            Some(open) if open == block_id => None,
+            _ => Some(block_id),
+        };
+        cx.notify();
+    }
+
+    fn set_code_language(&mut self, index: usize, language: &str, cx: &mut Context<Self>) {
+        if let Some(block) = self.blocks.get_mut(index) {
+            block.set_code_language(language);
+            self.language_menu = None;
+            self.save_document(cx);
+            cx.notify();
+        }
+    }
+
+    /// Copy a block's raw text to the system clipboard
+    fn copy_block(&self, index: usize, cx: &mut Context<Self>) {
+        if let Some(block) = self.blocks.get(index) {
+            cx.write_to_clipboard(ClipboardItem::new_string(block.get_content(cx)));
+        }
+    }
+
+    /// Ask the user for an image file and import it into the block
+    fn choose_image(&mut self, block_id: Uuid, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Insert Image".into()),
+        });
+
+        cx.spawn_in(window, async move |this, cx| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(path) = paths.into_iter().next() else {
+                return;
+            };
+            this.update_in(cx, |this, window, cx| {
+                let file = this.storage.images().import_file(&path);
+                this.attach_image(block_id, file, window, cx);
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    /// Import an image from the clipboard into the block
+    fn paste_image(&mut self, block_id: Uuid, window: &mut Window, cx: &mut Context<Self>) {
+        let item = cx.read_from_clipboard();
+        let image = item.iter().flat_map(|item| item.entries()).find_map(|entry| match entry {
+            ClipboardEntry::Image(image) => Some(image),
+            _ => None,
+        });
+        let Some(image) = image else {
+            self.show_error("Failed to paste image", "Clipboard does not contain an image", window, cx);
+            return;
+        };
+
+        let file = self
+            .storage
+            .images()
+            .import_bytes(&image.bytes, ImageBlock::extension_for(image.format));
+        self.attach_image(block_id, file, window, cx);
+    }
+
+    fn attach_image(
+        &mut self,
+        block_id: Uuid,
+        file: anyhow::Result<String>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let file = match file {
+            Ok(file) => file,
+            Err(e) => {
+                self.show_error("Failed to import image", &e.to_string(), window, cx);
+                return;
+            }
+        };
+        let path = self.storage.images().path(&file);
+        if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+            block.set_attachment(&file, path);
+            self.save_document(cx);
+            cx.notify();
+        }
+    }
+
+    fn cycle_callout_style(&mut self, index: usize, cx: &mut Context<Self>) {
+        if let Some(block) = self.blocks.get_mut(index)
+            && let Some(style) = block.callout_style()
+        {
+            block.set_callout_style(style.next());
+            self.save_document(cx);
+            cx.notify();
+        }
+    }
+
+    fn resize_block(&mut self, index: usize, delta: i32, cx: &mut Context<Self>) {
+        if let Some(block) = self.blocks.get_mut(index)
+            && let Some(width) = block.display_width()
+        {
+            block.set_display_width(width.saturating_add_signed(delta));
+            self.save_document(cx);
+            cx.notify();
+        }
+    }
+
+    fn render_block_row(
+        &self,
+        index: usize,
+        block: &Block,
+        numbering: Numbering,
+        window: &Window,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        if let Some(equation) = numbering.equation {
+            return div()
+                .flex()
+                .items_center()
+                .child(div().flex_1().child(block.render(window, cx)))
+                .child(
+                    div()
+                        .text_color(rgb(0x9399b2))
+                        .child(format!("({})", equation)),
+                )
+                .into_any_element();
+        }
+        if let Some(spec) = block.chart() {
+            return self.render_chart_block(index, block, spec, window, cx);
+        }
+        if let Some(table) = block.table() {
+            return self.render_table_block(index, block, table, window, cx);
+        }
+        if let Some(language) = block.code_language() {
+            return self.render_code_block(index, block, language, window, cx);
+        }
+        if let Some(width) = block.display_width() {
+            return self.render_image_block(index, block, width, window, cx);
+        }
+        if let Some(style) = block.callout_style() {
+            return div()
+                .flex()
+                .items_start()
+                .gap_2()
+                .p_2()
+                .rounded_md()
+                .border_l_4()
+                .border_color(rgb(style.color()))
+                .bg(style.background())
+                .child(
+                    Button::new(("callout-style", index))
+                        .icon(style.icon())
+                        .xsmall()
+                        .ghost()
+                        .tooltip(style.display_name())
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.cycle_callout_style(index, cx);
+                        })),
+                )
+                .child(div().flex_1().child(block.render(window, cx)))
+                .into_any_element();
+        }
+        if block.is_toggle() {
+            return self.render_toggle_block(index, block, window, cx);
+        }
+        let Some(style) = block.list_style() else {
+            return block.render(window, cx);
+        };
+
+        let marker = match style {
+            ListStyle::Bullet => div().child("•").into_any_element(),
+            ListStyle::Numbered => div().child(format!("{}.", numbering.list)).into_any_element(),
+            ListStyle::Todo => Checkbox::new(("todo", index))
+                .checked(block.is_checked())
+                .on_click(cx.listener(move |this, checked: &bool, _window, cx| {
+                    this.toggle_checked(index, *checked, cx);
+                }))
+                .into_any_element(),
+        };
+
+        div()
+            .flex()
+            .items_start()
+            .gap_2()
+            .child(
+                div()
+                    .flex()
+                    .justify_end()
+                    .min_w(px(24.))
+                    .text_color(rgb(0x9399b2))
+                    .child(marker),
+            )
+            .child(div().flex_1().child(block.render(window, cx)))
+            .into_any_element()
+    }
+
+    fn render_code_block(
+        &self,
+        index: usize,
+        block: &Block,
+        language: &str,
+        window: &Window,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let block_id = block.id;
+        let menu_open = self.language_menu == Some(block_id);
+
+        let header = div()
+            .flex()
+            .items_center()
+            .justify_between()
+            .child(
+                Button::new(("code-language", index))
+                    .label(format!("{} ▾", CodeBlock::language_name(language)))
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.toggle_language_menu(block_id, cx);
+                    })),
+            )
+            .child(
+                Button::new(("code-copy", index))
+                    .label("Copy")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.copy_block(index, cx);
+                    })),
+            );
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(header)
+            .when(menu_open, |this| {
+                this.child(self.render_language_menu(index, language, cx))
+            })
+            .child(block.render(window, cx))
+            .into_any_element()
+    }
+
+    fn render_image_block(
+        &self,
+        index: usize,
+        block: &Block,
+        width: u32,
+        window: &Window,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let block_id = block.id;
+        let step = ImageBlock::WIDTH_STEP as i32;
+
+        let header = div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .child(
+                Button::new(("image-choose", index))
+                    .label("Choose File…")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, window, cx| {
+                        this.choose_image(block_id, window, cx);
+                    })),
+            )
+            .child(
+                Button::new(("image-paste", index))
+                    .label("Paste")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, window, cx| {
+                        this.paste_image(block_id, window, cx);
+                    })),
+            )
+            .child(
+                Button::new(("image-shrink", index))
+                    .label("−")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.resize_block(index, -step, cx);
+                    })),
+            )
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0x9399b2))
+                    .child(format!("{}%", width)),
+            )
+            .child(
+                Button::new(("image-grow", index))
+                    .label("+")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.resize_block(index, step, cx);
+                    })),
+            );
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(header)
+            .child(block.render(window, cx))
+            .into_any_element()
+    }
+
+    fn render_language_menu(
+        &self,
+        index: usize,
+        current: &str,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let mut menu = div().flex().flex_wrap().gap_1();
+
+        for (id, name, _) in LANGUAGES {
+            let button = Button::new((*id, index)).label(*name).xsmall();
+            let button = if *id == current {
+                button.primary()
+            } else {
+                button.ghost()
+            };
+            menu = menu.child(button.on_click(cx.listener(move |this, _, _window, cx| {
+                this.set_code_language(index, id, cx);
+            })));
+        }
+
+        menu
+    }
+
+    /// Get the index of the currently focused block
+    fn focused_block_index(&self, window: &Window, cx: &App) -> Option<usize> {
+        self.blocks.iter().position(|block| {
+            let input_state = block.input.read(cx);
+            input_state.focus_handle(cx).is_focused(window)
+        })
+    }
+
+    fn render_toolbar(&self, focused_index: Option<usize>, cx: &mut Context<Self>) -> impl IntoElement {
+        let focused = focused_index.map(|index| (index, &self.blocks[index]));
+        let menu = focused.filter(|(_, block)| self.turn_into_menu == Some(block.id));
+
+        div()
+            .absolute()
+            .top_2()
+            .right_2()
+            // Keep clicks on the toolbar from reaching the blocks below
+            .occlude()
+            .flex()
+            .flex_col()
+            .items_end()
+            .gap_1()
+            .px_2()
+            .py_1()
+            .bg(rgb(0x313244))
+            .rounded_md()
+            .shadow_md()
+            .when_some(focused, |this, (index, block)| {
+                let block_id = block.id;
+                this.child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_1()
+                        .child(
+                            Button::new(("turn-into", index))
+                                .label(format!("{} ▾", block.type_name()))
+                                .xsmall()
+                                .ghost()
+                                .tooltip("Turn into")
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    this.toggle_turn_into_menu(block_id, cx);
+                                })),
+                        )
+                        .child(
+                            Button::new(("delete", index))
+                                .label("🗑")
+                                .xsmall()
+                                .ghost()
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    this.remove_block(index, cx);
+                                })),
+                        ),
+                )
+            })
+            .when_some(menu, |this, (_, block)| {
+                this.child(self.render_turn_into_menu(Some(&block.kind), cx))
+            })
+    }
+
+    /// Kinds to turn blocks into, highlighting the `current` kind
+    fn render_turn_into_menu(&self, current: Option<&str>, cx: &mut Context<Self>) -> AnyElement {
+        let mut menu = div().flex().flex_col().items_end();
+
+        for kind in BlockKind::all() {
+            let kind = *kind;
+            let button = Button::new(("turn-into-kind", kind as usize))
+                .label(kind.display_name())
+                .xsmall();
+            let button = if current == Some(kind.kind_string()) {
+                button.primary()
+            } else {
+                button.ghost()
+            };
+            menu = menu.child(button.on_click(cx.listener(move |this, _, window, cx| {
+                this.choose_turn_into(kind, window, cx);
+            })));
+        }
+
+        menu.into_any_element()
+    }
+
+    fn set_hovered_insert_line(&mut self, slot: Option<InsertSlot>, cx: &mut Context<Self>) {
+        if self.hovered_insert_line != slot {
+            self.hovered_insert_line = slot;
+            cx.notify();
+        }
+    }
+
+    fn render_insert_line(&self, slot: InsertSlot, depth: usize, cx: &mut Context<Self>) -> AnyElement {
+        let is_hovered = self.hovered_insert_line == Some(slot);
+
+        div()
+            .id(ElementId::Name(format!("insert-line-{}-{}", slot.index, depth).into()))
+            .flex()
+            .items_center()
+            .justify_center()
+            .min_h(px(24.))
+            .w_full()
+            .pl(px(NEST_INDENT * depth as f32))
+            .cursor_pointer()
+            // While a block is dragged, the insert lines are drop targets
+            .drag_over::<DraggedBlock>(|style, _, _, _| style.bg(rgb(0x313244)))
+            .on_drop(cx.listener(move |this, dragged: &DraggedBlock, _window, cx| {
+                this.move_block(dragged.block_id, slot, cx);
+            }))
+            .on_hover(cx.listener(move |this, hovered: &bool, _, cx| {
+                if *hovered && !cx.has_active_drag() {
+                    this.set_hovered_insert_line(Some(slot), cx);
+                } else {
+                    this.set_hovered_insert_line(None, cx);
+                }
+            }))
+            .child(if is_hovered {
+                self.render_insert_buttons(slot, cx).into_any_element()
+            } else {
+                self.render_insert_line_inactive().into_any_element()
+            })
+            .into_any_element()
+    }
+
+    fn render_insert_buttons(&self, slot: InsertSlot, cx: &mut Context<Self>) -> impl IntoElement {
+        let mut buttons = div().flex().flex_wrap().items_center().justify_center().gap_2();
+
+        for kind in BlockKind::all() {
+            let kind = *kind;
+            buttons = buttons.child(
+                Button::new((kind.display_name(), slot.index))
+                    .label(format!("+ {}", kind.display_name()))
+                    .xsmall()
+                    .primary()
+                    .on_click(cx.listener(move |this, _, window, cx| {
+                        this.insert_block_at(slot.index, slot.parent, kind, window, cx);
+                    })),
+            );
+        }
+
+        buttons
+    }
+
+    fn render_insert_line_inactive(&self) -> impl IntoElement {
+        div()
+            .h(px(1.))
+            .w_full()
+            .mx_4()
+            .bg(rgb(0x45475a))
+    }
+}
+
+impl Render for LoveNote {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        // Auto-save when focus leaves any block
+        let has_focus = self.any_block_focused(window, cx);
+        if self.had_focus && !has_focus {
+            // Focus just left a block, save the document
+            self.save_document(cx);
+        }
+        self.had_focus = has_focus;
+
+        // Get focused block for toolbar
+        let focused_index = self.focused_block_index(window, cx);
+
+        let mut children: Vec<AnyElement> = Vec::new();
+
+        // Insert line at the very top (index 0)
+        let top = InsertSlot {
+            index: 0,
+            parent: None,
+        };
+        children.push(self.render_insert_line(top, 0, cx));
+
+        // Blocks inside collapsed toggles are skipped, but still count
+        // towards the document's numbering
+        let ancestors = self.ancestors();
+        let visible = self.visible_indices();
+
+        // Render blocks with insert lines between them, numbering
+        // consecutive numbered list items and equations
+        let mut list_number = 0;
+        let mut equation_number = 0;
+        let mut next_visible = visible.iter().copied().peekable();
+        for (i, block) in self.blocks.iter().enumerate() {
+            list_number = match block.list_style() {
+                Some(ListStyle::Numbered) => list_number + 1,
+                _ => 0,
+            };
+            let equation = block.is_numbered(cx).then(|| {
+                equation_number += 1;
+                equation_number
+            });
+            if next_visible.next_if_eq(&i).is_none() {
+                continue;
+            }
+            let numbering = Numbering {
+                list: list_number,
+                equation,
+            };
+            let path = &ancestors[i];
+            let depth = path.len();
+            let block_id = block.id;
+            let handle = self.render_drag_handle(i, block, cx);
+            let row = self.render_block_row(i, block, numbering, window, cx);
+            let slash_menu = self.render_slash_menu(i, cx);
+            children.push(
+                div()
+                    .flex()
+                    .items_start()
+                    .gap_1()
+                    .pl(px(NEST_INDENT * depth as f32))
+                    .rounded_md()
+                    .when(self.is_selected(i), |this| this.bg(rgb(0x313244)))
+                    .capture_any_mouse_down(cx.listener(move |this, event: &MouseDownEvent, window, cx| {
+                        this.press_block(block_id, event, window, cx);
+                    }))
+                    .on_mouse_move(cx.listener(move |this, event: &MouseMoveEvent, window, cx| {
+                        this.drag_over_block(block_id, event, window, cx);
+                    }))
+                    .child(handle)
+                    .child(
+                        div()
+                            .flex_1()
+                            .min_w_0()
+                            .child(row)
+                            .children(slash_menu),
+                    )
+                    .into_any_element(),
+            );
+
+            // Insert lines after each block: inside an expanded toggle,
+            // then after each toggle this block closes
+            let next = next_visible.peek().copied();
+            let next_depth = next.map_or(0, |n| ancestors[n].len());
+            let index = next.unwrap_or(self.blocks.len());
+            let inner = block.is_toggle() && !self.is_collapsed(block.id);
+            let innermost = if inner { depth + 1 } else { depth };
+            for level in (next_depth..=innermost).rev() {
+                let parent = if level > depth {
+                    Some(block.id)
+                } else {
+                    level.checked_sub(1).map(|k| path[k])
+                };
+                children.push(self.render_insert_line(InsertSlot { index, parent }, level, cx));
+            }
+        }
+
+        let toolbar = match self.selected_range() {
+            Some(range) => self.render_selection_toolbar(range, cx),
+            None => self.render_toolbar(focused_index, cx).into_any_element(),
+        };
+
+        div()
+            .track_focus(&self.focus_handle)
+            .flex()
+            .flex_col()
+            .size_full()
+            .bg(rgb(0x1e1e2e))
+            .capture_any_mouse_up(cx.listener(|this, _: &MouseUpEvent, _window, _cx| {
+                this.release_block();
+            }))
+            .text_color(rgb(0xcdd6f4))
+            .capture_action(cx.listener(|this, _: &Paste, window, cx| {
+                if this.paste_blocks(window, cx) || this.paste_table(window, cx) {
+                    cx.stop_propagation();
+                }
+            }))
+            // While the slash menu is open, the arrow keys, Enter and
+            // Escape drive the menu instead of the input. Otherwise they
+            // move, split and join blocks at block boundaries.
+            .capture_action(cx.listener(|this, _: &MoveUp, window, cx| {
+                if this.move_slash_selection(-1, cx) || this.focus_adjacent_block(-1, window, cx) {
+                    cx.stop_propagation();
+                }
+            }))
+            .capture_action(cx.listener(|this, _: &MoveDown, window, cx| {
+                if this.move_slash_selection(1, cx) || this.focus_adjacent_block(1, window, cx) {
+                    cx.stop_propagation();
+                }
+            }))
+            .capture_action(cx.listener(|this, _: &Enter, window, cx| {
+                if this.confirm_slash_menu(window, cx) || this.split_block(window, cx) {
+                    cx.stop_propagation();
+                }
+            }))
+            .capture_action(cx.listener(|this, _: &Backspace, window, cx| {
+                if this.join_with_previous(window, cx) {
+                    cx.stop_propagation();
+                }
+            }))
+            .capture_action(cx.listener(|this, _: &Escape, _window, cx| {
+                if this.close_slash_menu(cx) {
+                    cx.stop_propagation();
+                }
+            }))
+            .on_key_down(cx.listener(|this, event: &KeyDownEvent, window, cx| {
+                // Handle Ctrl+S for save
+                if event.keystroke.modifiers.control && event.keystroke.key == "s" {
+                    this.save_document(cx);
+                    println!("Document saved!");
+                }
+                // Ctrl+B, Ctrl+I, ... format the selected text,
+                // Alt+Up/Down move the focused block, and Escape, Delete
+                // and the clipboard shortcuts act on selected blocks
+                if this.format_shortcut(&event.keystroke, window, cx)
+                    || this.move_shortcut(&event.keystroke, window, cx)
+                    || this.selection_shortcut(&event.keystroke, window, cx)
+                {
+                    cx.stop_propagation();
+                }
+            }))
+            // Title bar
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .px_4()
+                    .py_2()
+                    .bg(rgb(0x181825))
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_4()
+                            .child(
+                                div()
+                                    .text_sm()
+                                    .text_color(rgb(0x9399b2))
+                                    .child("Love Note"),
+                            )
+                            .child(self.render_search(cx)),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_1()
+                            .child(self.render_import_button(cx))
+                            .child(self.render_export_button(cx))
+                            .child(self.render_backup_buttons(cx)),
+                    ),
+            )
+            // Content area with blocks and toolbar
+            .child(
+                div()
+                    .relative()
+                    .flex()
+                    .flex_1()
+                    .flex_col()
+                    .p_4()
+                    .gap_1()
+                    .children(children)
+                    // Toolbar in top-right
+                    .child(toolbar),
+            )
+    }
+}
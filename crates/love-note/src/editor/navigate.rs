@@ -0,0 +1,162 @@
+use gpui::*;
+
+use super::LoveNote;
+use crate::block::BlockKind;
+
+impl LoveNote {
+    /// Split the focused block at the cursor, moving the text after it
+    /// into a new block below. Returns false when Enter should be left to
+    /// the input, e.g. in code blocks or with Shift held.
+    pub(super) fn split_block(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        if window.modifiers().shift {
+            return false;
+        }
+        let Some(index) = self.focused_block_index(window, cx) else {
+            return false;
+        };
+        let block = &self.blocks[index];
+        if !block.splits_on_enter() {
+            return false;
+        }
+        let Some(selection) = block.selection(window, cx) else {
+            return false;
+        };
+        let text = block.get_content(cx);
+
+        // Enter on an empty list item leaves the list
+        if block.list_style().is_some() && text.is_empty() {
+            self.turn_into(index, BlockKind::Text, window, cx);
+            return true;
+        }
+
+        let kind = block
+            .list_style()
+            .map_or(BlockKind::Text, BlockKind::from_list_style);
+        // Splitting an expanded toggle starts its list of children
+        let (at, parent) = if block.is_toggle() && !self.is_collapsed(block.id) {
+            (index + 1, Some(block.id))
+        } else {
+            (self.subtree_end(index), block.parent)
+        };
+
+        // Formatting after the cursor moves with the text
+        let tail_marks = block.marks().map(|marks| {
+            let mut marks = marks.clone();
+            marks.update_text(&text);
+            marks.slice(selection.end..text.len())
+        });
+
+        self.blocks[index].set_content(&text[..selection.start], window, cx);
+        self.insert_block_at(at, parent, kind, window, cx);
+        self.blocks[at].set_content(&text[selection.end..], window, cx);
+        if let Some(tail_marks) = tail_marks
+            && let Some(marks) = self.blocks[at].marks_mut()
+        {
+            *marks = tail_marks;
+        }
+        self.blocks[at].focus_at(0, window, cx);
+        self.save_document(cx);
+        true
+    }
+
+    /// Handle Backspace at the start of the focused block: formatted
+    /// blocks become plain text, and text joins the previous block.
+    /// Returns false when the input should handle the key.
+    pub(super) fn join_with_previous(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        let Some(index) = self.focused_block_index(window, cx) else {
+            return false;
+        };
+        let block = &self.blocks[index];
+        if !block.splits_on_enter() || block.selection(window, cx) != Some(0..0) {
+            return false;
+        }
+        if block.kind != BlockKind::Text.kind_string() {
+            self.turn_into(index, BlockKind::Text, window, cx);
+            return true;
+        }
+
+        let visible = self.visible_indices();
+        let Some(previous) = visible.iter().rev().copied().find(|i| *i < index) else {
+            return false;
+        };
+        let text = block.get_content(cx);
+        let target = &self.blocks[previous];
+
+        if target.splits_on_enter() {
+            let target_text = target.get_content(cx);
+            let joined_at = target_text.len();
+            let joined = target_text.clone() + &text;
+            // Both blocks keep their formatting in the joined text
+            let joined_marks = target.marks().zip(block.marks()).map(|(first, second)| {
+                let mut first = first.clone();
+                first.update_text(&target_text);
+                let mut second = second.clone();
+                second.update_text(&text);
+                first.append(&second);
+                first
+            });
+            self.blocks[previous].set_content(&joined, window, cx);
+            if let Some(joined_marks) = joined_marks
+                && let Some(marks) = self.blocks[previous].marks_mut()
+            {
+                *marks = joined_marks;
+            }
+            self.remove_block(index, cx);
+            self.blocks[previous].focus_at(joined_at, window, cx);
+        } else if text.is_empty() {
+            // Step back onto a block without flowing text, e.g. an image
+            self.remove_block(index, cx);
+            self.blocks[previous].focus(window, cx);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Move focus to the previous (`delta` -1) or next (1) visible block
+    /// when the cursor is on the first or last line. Returns false when
+    /// the input should move the cursor instead.
+    pub(super) fn focus_adjacent_block(
+        &mut self,
+        delta: isize,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let Some(index) = self.focused_block_index(window, cx) else {
+            return false;
+        };
+        let block = &self.blocks[index];
+        let Some(selection) = block.selection(window, cx) else {
+            return false;
+        };
+        let text = block.get_content(cx);
+        let at_edge = if delta < 0 {
+            !text[..selection.start].contains('\n')
+        } else {
+            !text[selection.end..].contains('\n')
+        };
+        if !selection.is_empty() || !at_edge {
+            return false;
+        }
+
+        let visible = self.visible_indices();
+        let Some(position) = visible.iter().position(|i| *i == index) else {
+            return false;
+        };
+        let Some(&target) = position
+            .checked_add_signed(delta)
+            .and_then(|p| visible.get(p))
+        else {
+            return false;
+        };
+
+        let offset = if delta < 0 {
+            self.blocks[target].get_content(cx).len()
+        } else {
+            0
+        };
+        self.blocks[target].focus_at(offset, window, cx);
+        cx.notify();
+        true
+    }
+}
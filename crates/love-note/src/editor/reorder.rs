@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use gpui::*;
+use uuid::Uuid;
+
+use super::toggle::InsertSlot;
+use super::LoveNote;
+use crate::block::Block;
+
+/// A block being dragged by its handle. Also renders the drag preview.
+#[derive(Clone)]
+pub(super) struct DraggedBlock {
+    pub block_id: Uuid,
+    label: SharedString,
+}
+
+impl Render for DraggedBlock {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .max_w(px(320.))
+            .px_2()
+            .py_1()
+            .bg(rgb(0x313244))
+            .rounded_md()
+            .shadow_md()
+            .text_sm()
+            .text_color(rgb(0xcdd6f4))
+            .child(self.label.clone())
+    }
+}
+
+impl LoveNote {
+    /// Move a block, together with the blocks nested in it, to `slot`
+    pub(super) fn move_block(&mut self, block_id: Uuid, slot: InsertSlot, cx: &mut Context<Self>) {
+        if let Some(index) = self.blocks.iter().position(|b| b.id == block_id) {
+            self.move_blocks(index..self.subtree_end(index), slot, cx);
+        }
+    }
+
+    /// Move a run of blocks to `slot`. Blocks whose parent is outside the
+    /// run are nested in the slot's parent.
+    pub(super) fn move_blocks(&mut self, range: Range<usize>, slot: InsertSlot, cx: &mut Context<Self>) {
+        let mut document = self.document(cx);
+        if document.move_blocks(range, slot.index, slot.parent).is_none() {
+            return;
+        }
+
+        // Follow the order and nesting the document now has
+        let order: HashMap<Uuid, usize> = document
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| (block.id, i))
+            .collect();
+        self.blocks.sort_by_key(|block| order[&block.id]);
+        for (block, stored) in self.blocks.iter_mut().zip(&document.blocks) {
+            block.parent = stored.parent;
+        }
+        self.hovered_insert_line = None;
+        if let Err(e) = self.storage.save_document(&document) {
+            eprintln!("Failed to save document: {}", e);
+        }
+        cx.notify();
+    }
+
+    /// Where a run of blocks goes when moved past the previous (`delta`
+    /// -1) or next (1) visible block, entering and leaving toggles on the
+    /// way. `None` at the start or end of the document.
+    pub(super) fn adjacent_slot(&self, range: Range<usize>, delta: isize) -> Option<InsertSlot> {
+        let visible = self.visible_indices();
+        if delta < 0 {
+            // In front of the previous block, at its level
+            let previous = visible.iter().rev().copied().find(|i| *i < range.start)?;
+            Some(InsertSlot {
+                index: previous,
+                parent: self.blocks[previous].parent,
+            })
+        } else {
+            // Behind the next block, or first inside it if it is an
+            // expanded toggle
+            let next = visible.iter().copied().find(|i| *i >= range.end)?;
+            let block = &self.blocks[next];
+            if block.is_toggle() && !self.is_collapsed(block.id) {
+                Some(InsertSlot {
+                    index: next + 1,
+                    parent: Some(block.id),
+                })
+            } else {
+                Some(InsertSlot {
+                    index: self.subtree_end(next),
+                    parent: block.parent,
+                })
+            }
+        }
+    }
+
+    /// Move the focused block past the previous (`delta` -1) or next (1)
+    /// visible block. Returns false when there is nowhere to move.
+    fn move_focused_block(&mut self, delta: isize, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        let Some(index) = self.focused_block_index(window, cx) else {
+            return false;
+        };
+        let range = index..self.subtree_end(index);
+        let Some(slot) = self.adjacent_slot(range.clone(), delta) else {
+            return false;
+        };
+        self.move_blocks(range, slot, cx);
+        true
+    }
+
+    /// Handle Alt+Up/Down. Returns true if the keystroke was one.
+    pub(super) fn move_shortcut(
+        &mut self,
+        keystroke: &Keystroke,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let modifiers = &keystroke.modifiers;
+        if !modifiers.alt || modifiers.control || modifiers.shift {
+            return false;
+        }
+        match keystroke.key.as_str() {
+            "up" => self.move_focused_block(-1, window, cx),
+            "down" => self.move_focused_block(1, window, cx),
+            _ => false,
+        }
+    }
+
+    /// The grip a block is dragged by
+    pub(super) fn render_drag_handle(&self, index: usize, block: &Block, cx: &App) -> AnyElement {
+        let text = block.get_content(cx);
+        let label = match text.lines().next().map(str::trim) {
+            Some(line) if !line.is_empty() => line.to_string(),
+            _ => block.type_name().to_string(),
+        };
+        let dragged = DraggedBlock {
+            block_id: block.id,
+            label: label.into(),
+        };
+
+        div()
+            .id(("drag-handle", index))
+            .flex_none()
+            .w(px(16.))
+            .pt_1()
+            .text_xs()
+            .text_color(rgb(0x45475a))
+            .hover(|this| this.text_color(rgb(0x9399b2)))
+            .cursor_grab()
+            .child("⋮⋮")
+            .on_drag(dragged, |dragged, _, _, cx| cx.new(|_| dragged.clone()))
+            .into_any_element()
+    }
+}
@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use gpui::*;
+use gpui_component::input::{Input, InputEvent, InputState};
+use uuid::Uuid;
+
+use super::LoveNote;
+use crate::storage::SearchHit;
+
+/// Most results listed at once
+const MAX_RESULTS: usize = 20;
+
+/// How long typing has to pause before searching
+const SEARCH_DELAY: Duration = Duration::from_millis(150);
+
+/// Characters of a matching line shown around the match
+const SNIPPET_CHARS: usize = 80;
+
+impl LoveNote {
+    pub(super) fn new_search_input(
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> (Entity<InputState>, Subscription) {
+        let input = cx.new(|cx| InputState::new(window, cx).placeholder("Search…"));
+        let subscription = cx.subscribe_in(&input, window, |this, _, event: &InputEvent, window, cx| {
+            match event {
+                InputEvent::Change => this.update_search(cx),
+                InputEvent::PressEnter { .. } => {
+                    if let Some(hit) = this.search_hits.first().cloned() {
+                        this.open_search_hit(&hit, window, cx);
+                    }
+                }
+                _ => {}
+            }
+        });
+        (input, subscription)
+    }
+
+    /// Search for the query once typing pauses, reading documents in the
+    /// background
+    fn update_search(&mut self, cx: &mut Context<Self>) {
+        let query = self.search_input.read(cx).value().trim().to_string();
+        if query.is_empty() {
+            self.search_task = None;
+            self.search_hits.clear();
+            cx.notify();
+            return;
+        }
+
+        let storage = self.storage.clone();
+        self.search_task = Some(cx.spawn(async move |this, cx| {
+            cx.background_executor().timer(SEARCH_DELAY).await;
+            let hits = cx
+                .background_executor()
+                .spawn(async move { storage.search(&query) })
+                .await;
+            this.update(cx, |this, cx| {
+                this.search_hits = match hits {
+                    Ok(mut hits) => {
+                        // Results in the open document first
+                        hits.sort_by_key(|hit| hit.document_id != this.document.id);
+                        hits.truncate(MAX_RESULTS);
+                        hits
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to search: {}", e);
+                        Vec::new()
+                    }
+                };
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+
+    /// Open the document holding a result, expand the toggles around the
+    /// block and focus it
+    fn open_search_hit(&mut self, hit: &SearchHit, window: &mut Window, cx: &mut Context<Self>) {
+        if hit.document_id != self.document.id {
+            match self.storage.load_document(hit.document_id) {
+                Ok(Some(document)) => self.open_document(document, window, cx),
+                Ok(None) => return,
+                Err(e) => {
+                    eprintln!("Failed to load document: {}", e);
+                    return;
+                }
+            }
+        }
+
+        self.search_hits.clear();
+        self.search_input
+            .update(cx, |input, cx| input.set_value("", window, cx));
+
+        let Some(index) = self.blocks.iter().position(|block| block.id == hit.block_id) else {
+            cx.notify();
+            return;
+        };
+        let mut parent = self.blocks[index].parent;
+        while let Some(id) = parent {
+            self.expand(id);
+            parent = self
+                .blocks
+                .iter()
+                .find(|block| block.id == id)
+                .and_then(|block| block.parent);
+        }
+        self.blocks[index].focus(window, cx);
+        cx.notify();
+    }
+
+    /// Show a collapsed toggle's children
+    fn expand(&mut self, block_id: Uuid) {
+        if self.collapsed.remove(&block_id)
+            && let Err(e) = self.storage.set_collapsed(block_id, false)
+        {
+            eprintln!("Failed to save toggle state: {}", e);
+        }
+    }
+
+    pub(super) fn render_search(&self, cx: &mut Context<Self>) -> AnyElement {
+        let query = self.search_input.read(cx).value().trim().to_lowercase();
+        let results = (!query.is_empty()).then(|| {
+            let rows: Vec<AnyElement> = self
+                .search_hits
+                .iter()
+                .enumerate()
+                .map(|(i, hit)| {
+                    let open = hit.clone();
+                    div()
+                        .id(("search-hit", i))
+                        .flex()
+                        .flex_col()
+                        .px_2()
+                        .py_1()
+                        .rounded_sm()
+                        .cursor_pointer()
+                        .hover(|style| style.bg(rgb(0x45475a)))
+                        .on_click(cx.listener(move |this, _, window, cx| {
+                            this.open_search_hit(&open, window, cx);
+                        }))
+                        .child(
+                            div()
+                                .text_xs()
+                                .text_color(rgb(0x9399b2))
+                                .child(hit.document_title.clone()),
+                        )
+                        .child(div().text_sm().child(snippet(&hit.text, &query)))
+                        .into_any_element()
+                })
+                .collect();
+            let empty = rows.is_empty().then(|| {
+                div()
+                    .px_2()
+                    .py_1()
+                    .text_sm()
+                    .text_color(rgb(0x6c7086))
+                    .child("No results")
+            });
+
+            div()
+                .absolute()
+                .top_full()
+                .left_0()
+                .w(px(360.))
+                .occlude()
+                .flex()
+                .flex_col()
+                .p_1()
+                .bg(rgb(0x313244))
+                .rounded_md()
+                .shadow_md()
+                .children(rows)
+                .children(empty)
+        });
+
+        div()
+            .relative()
+            .w(px(240.))
+            .text_sm()
+            .child(Input::new(&self.search_input))
+            .children(results)
+            .into_any_element()
+    }
+}
+
+/// The line of `text` holding `query`, shortened around the match
+fn snippet(text: &str, query: &str) -> String {
+    let line = text
+        .lines()
+        .find(|line| line.to_lowercase().contains(query))
+        .unwrap_or(text)
+        .trim();
+    let chars: Vec<char> = line.chars().collect();
+    if chars.len() <= SNIPPET_CHARS {
+        return line.to_string();
+    }
+
+    let lower = line.to_lowercase();
+    let at = lower.find(query).map_or(0, |i| lower[..i].chars().count());
+    let start = at.saturating_sub(SNIPPET_CHARS / 4).min(chars.len() - SNIPPET_CHARS);
+    let end = start + SNIPPET_CHARS;
+    let mut snippet: String = chars[start..end].iter().collect();
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
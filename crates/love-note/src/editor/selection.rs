@@ -0,0 +1,339 @@
+use std::ops::Range;
+
+use gpui::*;
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    Sizable,
+};
+use uuid::Uuid;
+
+use super::clipboard::with_new_ids;
+use super::LoveNote;
+use crate::block::{Block, BlockKind};
+use crate::export;
+use crate::storage::Document;
+
+/// A run of selected blocks, from the block the selection started at to
+/// the one it was extended to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(super) struct BlockSelection {
+    anchor: Uuid,
+    head: Uuid,
+}
+
+impl LoveNote {
+    /// Indices of the selected blocks, including blocks nested in them
+    pub(super) fn selected_range(&self) -> Option<Range<usize>> {
+        let selection = self.block_selection?;
+        let position = |id: Uuid| self.blocks.iter().position(|b| b.id == id);
+        let (anchor, head) = (position(selection.anchor)?, position(selection.head)?);
+        Some(anchor.min(head)..self.subtree_end(anchor.max(head)))
+    }
+
+    pub(super) fn is_selected(&self, index: usize) -> bool {
+        self.selected_range().is_some_and(|range| range.contains(&index))
+    }
+
+    /// Select the blocks from `anchor` to `head` and take keyboard focus
+    /// away from the inputs, so Delete and Escape act on the selection
+    pub(super) fn select_blocks(&mut self, anchor: Uuid, head: Uuid, window: &mut Window, cx: &mut Context<Self>) {
+        let selection = BlockSelection { anchor, head };
+        if self.block_selection != Some(selection) {
+            self.block_selection = Some(selection);
+            self.focus_handle.focus(window);
+            cx.notify();
+        }
+    }
+
+    pub(super) fn clear_selection(&mut self, cx: &mut Context<Self>) -> bool {
+        if self.block_selection.take().is_none() {
+            return false;
+        }
+        self.turn_into_menu = None;
+        cx.notify();
+        true
+    }
+
+    /// Mouse down on a block row. Shift extends the selection to the
+    /// block; otherwise a drag from here into another block starts one.
+    pub(super) fn press_block(
+        &mut self,
+        block_id: Uuid,
+        event: &MouseDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if event.modifiers.shift {
+            let anchor = self
+                .block_selection
+                .map(|selection| selection.anchor)
+                .or_else(|| {
+                    self.focused_block_index(window, cx)
+                        .map(|index| self.blocks[index].id)
+                });
+            if let Some(anchor) = anchor {
+                self.select_blocks(anchor, block_id, window, cx);
+                cx.stop_propagation();
+                return;
+            }
+        }
+        self.clear_selection(cx);
+        self.pressed_block = Some(block_id);
+    }
+
+    /// Mouse moved over a block row while a button may be held
+    pub(super) fn drag_over_block(
+        &mut self,
+        block_id: Uuid,
+        event: &MouseMoveEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if event.pressed_button != Some(MouseButton::Left) {
+            return;
+        }
+        match self.pressed_block {
+            Some(anchor) if anchor != block_id || self.block_selection.is_some() => {
+                self.select_blocks(anchor, block_id, window, cx);
+            }
+            _ => {}
+        }
+    }
+
+    pub(super) fn release_block(&mut self) {
+        self.pressed_block = None;
+    }
+
+    fn delete_selection(&mut self, cx: &mut Context<Self>) {
+        let removed = self.take_selection(cx);
+        if removed.iter().any(|block| block.attachment().is_some()) {
+            self.collect_garbage(cx);
+        }
+    }
+
+    /// Put the selected blocks on the clipboard and remove them. Their
+    /// images are kept for pasting.
+    fn cut_selection(&mut self, cx: &mut Context<Self>) {
+        self.copy_selection(cx);
+        self.take_selection(cx);
+    }
+
+    /// Remove the selected blocks, saving once, and return them
+    fn take_selection(&mut self, cx: &mut Context<Self>) -> Vec<Block> {
+        let Some(range) = self.selected_range() else {
+            return Vec::new();
+        };
+        self.clear_selection(cx);
+        let removed = range.rev().filter_map(|index| self.take_block(index)).collect();
+        self.save_document(cx);
+        cx.notify();
+        removed
+    }
+
+    /// Insert copies of the selected blocks after them and select the
+    /// copies
+    fn duplicate_selection(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(range) = self.selected_range() else {
+            return;
+        };
+        let originals: Vec<_> = self.blocks[range.clone()]
+            .iter()
+            .map(|block| block.to_stored(cx))
+            .collect();
+        let copies = with_new_ids(&originals);
+
+        let (first, last) = (copies[0].id, copies[copies.len() - 1].id);
+        let blocks = copies
+            .iter()
+            .map(|stored| Block::from_stored(stored, &self.storage, window, cx))
+            .collect();
+        self.insert_blocks(range.end, blocks, window, cx);
+        self.select_blocks(first, last, window, cx);
+    }
+
+    fn move_selection(&mut self, delta: isize, cx: &mut Context<Self>) {
+        let Some(range) = self.selected_range() else {
+            return;
+        };
+        if let Some(slot) = self.adjacent_slot(range.clone(), delta) {
+            self.move_blocks(range, slot, cx);
+        }
+    }
+
+    pub(super) fn turn_selection_into(
+        &mut self,
+        range: Range<usize>,
+        kind: BlockKind,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        for index in range {
+            if self.blocks[index].kind != kind.kind_string() {
+                self.convert_block(index, kind, window, cx);
+            }
+        }
+        self.turn_into_menu = None;
+        cx.notify();
+    }
+
+    fn copy_selection(&self, cx: &mut Context<Self>) {
+        if let Some(range) = self.selected_range() {
+            self.copy_blocks(range, cx);
+        }
+    }
+
+    /// Ask for a destination and export the selected blocks there as
+    /// Markdown
+    fn export_selection(&self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(range) = self.selected_range() else {
+            return;
+        };
+        let mut document = Document::new("Selection");
+        document.blocks = self.blocks[range]
+            .iter()
+            .map(|block| block.to_stored(cx))
+            .collect();
+        let name = export::file_name(&document.title, "md");
+        self.export_as(document, &name, export::write_markdown, window, cx);
+    }
+
+    /// Handle Escape, Delete and the clipboard shortcuts while blocks are
+    /// selected. Returns true if the keystroke was one.
+    pub(super) fn selection_shortcut(
+        &mut self,
+        keystroke: &Keystroke,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        if self.block_selection.is_none() {
+            return false;
+        }
+        let control = keystroke.modifiers.control;
+        match keystroke.key.as_str() {
+            "escape" => self.clear_selection(cx),
+            "backspace" | "delete" => {
+                self.delete_selection(cx);
+                true
+            }
+            "c" if control => {
+                self.copy_selection(cx);
+                true
+            }
+            "x" if control => {
+                self.cut_selection(cx);
+                true
+            }
+            "v" if control => self.paste_blocks(window, cx),
+            _ => false,
+        }
+    }
+
+    pub(super) fn render_selection_toolbar(&self, range: Range<usize>, cx: &mut Context<Self>) -> AnyElement {
+        let anchor = self.block_selection.map(|selection| selection.anchor);
+        let menu_open = anchor.is_some() && self.turn_into_menu == anchor;
+        let count = match range.len() {
+            1 => "1 block".to_string(),
+            n => format!("{} blocks", n),
+        };
+
+        let actions = div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .child(
+                div()
+                    .px_1()
+                    .text_xs()
+                    .text_color(rgb(0x9399b2))
+                    .child(count),
+            )
+            .child(
+                Button::new("selection-turn-into")
+                    .label("Turn into ▾")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        if let Some(anchor) = anchor {
+                            this.toggle_turn_into_menu(anchor, cx);
+                        }
+                    })),
+            )
+            .child(
+                Button::new("selection-duplicate")
+                    .label("Duplicate")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(|this, _, window, cx| {
+                        this.duplicate_selection(window, cx);
+                    })),
+            )
+            .child(
+                Button::new("selection-up")
+                    .label("↑")
+                    .xsmall()
+                    .ghost()
+                    .tooltip("Move up")
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.move_selection(-1, cx);
+                    })),
+            )
+            .child(
+                Button::new("selection-down")
+                    .label("↓")
+                    .xsmall()
+                    .ghost()
+                    .tooltip("Move down")
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.move_selection(1, cx);
+                    })),
+            )
+            .child(
+                Button::new("selection-copy")
+                    .label("Copy")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.copy_selection(cx);
+                    })),
+            )
+            .child(
+                Button::new("selection-export")
+                    .label("Export…")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(|this, _, window, cx| {
+                        this.export_selection(window, cx);
+                    })),
+            )
+            .child(
+                Button::new("selection-delete")
+                    .label("🗑")
+                    .xsmall()
+                    .ghost()
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.delete_selection(cx);
+                    })),
+            );
+
+        let menu = menu_open.then(|| self.render_turn_into_menu(None, cx));
+
+        div()
+            .absolute()
+            .top_2()
+            .right_2()
+            // Keep clicks on the toolbar from reaching the blocks below
+            .occlude()
+            .flex()
+            .flex_col()
+            .items_end()
+            .gap_1()
+            .px_2()
+            .py_1()
+            .bg(rgb(0x313244))
+            .rounded_md()
+            .shadow_md()
+            .child(actions)
+            .children(menu)
+            .into_any_element()
+    }
+}
@@ -0,0 +1,242 @@
+use gpui::{prelude::FluentBuilder, *};
+use love_note_plugin_api::BlockKindInfo;
+use uuid::Uuid;
+
+use super::LoveNote;
+use crate::block::{Block, BlockKind};
+use crate::storage::StoredBlock;
+
+/// An open slash command menu
+pub(super) struct SlashMenu {
+    /// Text block the command is being typed in
+    block_id: Uuid,
+    /// Highlighted entry among the filtered kinds
+    selected: usize,
+}
+
+/// A kind offered by the menu
+#[derive(Clone)]
+struct SlashItem {
+    /// Built-in kind, or `None` for a plugin-provided one
+    builtin: Option<BlockKind>,
+    info: BlockKindInfo,
+}
+
+impl SlashItem {
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        [
+            Some(&self.info.name),
+            Some(&self.info.kind),
+            self.info.description.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|text| text.to_lowercase().contains(&query))
+    }
+}
+
+impl LoveNote {
+    /// Offer block kinds provided by plugins in the slash menu
+    pub fn register_block_kinds(&mut self, kinds: Vec<BlockKindInfo>) {
+        for info in kinds {
+            self.plugin_kinds.retain(|k| k.kind != info.kind);
+            self.plugin_kinds.push(info);
+        }
+    }
+
+    /// The command typed in a block, if it is a text block starting with
+    /// `/` and holding a single word
+    fn slash_query(block: &Block, cx: &App) -> Option<String> {
+        if block.kind != BlockKind::Text.kind_string() {
+            return None;
+        }
+        let text = block.get_content(cx);
+        let query = text.strip_prefix('/')?;
+        (!query.contains(char::is_whitespace)).then(|| query.to_string())
+    }
+
+    /// Kinds matching `query`, built-in kinds first
+    fn slash_items(&self, query: &str) -> Vec<SlashItem> {
+        let builtin = BlockKind::all().iter().map(|kind| SlashItem {
+            builtin: Some(*kind),
+            info: kind.info(),
+        });
+        let plugins = self.plugin_kinds.iter().map(|info| SlashItem {
+            builtin: None,
+            info: info.clone(),
+        });
+        builtin
+            .chain(plugins)
+            .filter(|item| item.matches(query))
+            .collect()
+    }
+
+    /// Open, filter or close the menu after the block at `index` changed
+    pub(super) fn update_slash_menu(&mut self, index: usize, cx: &mut Context<Self>) {
+        let block = &self.blocks[index];
+        let open_here = self
+            .slash_menu
+            .as_ref()
+            .is_some_and(|menu| menu.block_id == block.id);
+
+        match Self::slash_query(block, cx) {
+            // Only a slash typed into an empty block opens the menu
+            Some(query) if open_here || query.is_empty() => {
+                self.slash_menu = Some(SlashMenu {
+                    block_id: block.id,
+                    selected: 0,
+                });
+                cx.notify();
+            }
+            _ if open_here => {
+                self.slash_menu = None;
+                cx.notify();
+            }
+            _ => {}
+        }
+    }
+
+    /// The open menu's block index and filtered kinds
+    fn slash_menu_items(&self, cx: &App) -> Option<(usize, Vec<SlashItem>)> {
+        let menu = self.slash_menu.as_ref()?;
+        let index = self.blocks.iter().position(|b| b.id == menu.block_id)?;
+        let query = Self::slash_query(&self.blocks[index], cx)?;
+        Some((index, self.slash_items(&query)))
+    }
+
+    /// Move the highlight. Returns false when no menu is open.
+    pub(super) fn move_slash_selection(&mut self, delta: isize, cx: &mut Context<Self>) -> bool {
+        let Some((_, items)) = self.slash_menu_items(cx) else {
+            return false;
+        };
+        if let Some(menu) = &mut self.slash_menu
+            && !items.is_empty()
+        {
+            let count = items.len() as isize;
+            menu.selected = (menu.selected as isize + delta).rem_euclid(count) as usize;
+            cx.notify();
+        }
+        true
+    }
+
+    /// Insert the highlighted kind. Returns false when no menu is open.
+    pub(super) fn confirm_slash_menu(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        let Some((index, items)) = self.slash_menu_items(cx) else {
+            return false;
+        };
+        let selected = self.slash_menu.as_ref().map_or(0, |menu| menu.selected);
+        if let Some(item) = items.get(selected) {
+            self.choose_slash_item(index, item.clone(), window, cx);
+        }
+        true
+    }
+
+    /// Close the menu, leaving the typed text. Returns false when no menu
+    /// is open.
+    pub(super) fn close_slash_menu(&mut self, cx: &mut Context<Self>) -> bool {
+        if self.slash_menu.take().is_none() {
+            return false;
+        }
+        cx.notify();
+        true
+    }
+
+    /// Turn the block holding the command into the chosen kind
+    fn choose_slash_item(
+        &mut self,
+        index: usize,
+        item: SlashItem,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.slash_menu = None;
+        match item.builtin {
+            Some(kind) => self.convert_text_block(index, kind, "", window, cx),
+            None => {
+                // Plugin blocks start from their stored form, keeping the
+                // place of the block the command was typed in
+                let block = &self.blocks[index];
+                let mut stored = StoredBlock::new(item.info.kind, "");
+                stored.id = block.id;
+                stored.parent = block.parent;
+                let converted = Block::from_stored(&stored, &self.storage, window, cx);
+                self.replace_block(index, converted, window, cx);
+                self.blocks[index].focus(window, cx);
+            }
+        }
+    }
+
+    /// The menu for the block at `index`, if it is open there
+    pub(super) fn render_slash_menu(&self, index: usize, cx: &mut Context<Self>) -> Option<AnyElement> {
+        let (menu_index, items) = self.slash_menu_items(cx)?;
+        if menu_index != index {
+            return None;
+        }
+        let selected = self.slash_menu.as_ref().map_or(0, |menu| menu.selected);
+
+        let mut menu = div()
+            .flex()
+            .flex_col()
+            .w(px(320.))
+            .py_1()
+            .bg(rgb(0x181825))
+            .border_1()
+            .border_color(rgb(0x45475a))
+            .rounded_md()
+            .shadow_md();
+
+        if items.is_empty() {
+            menu = menu.child(
+                div()
+                    .px_3()
+                    .py_1()
+                    .text_sm()
+                    .text_color(rgb(0x6c7086))
+                    .child("No matching blocks"),
+            );
+        }
+
+        for (i, item) in items.into_iter().enumerate() {
+            let icon = item.info.icon.clone().unwrap_or_default();
+            let name = item.info.name.clone();
+            let description = item.info.description.clone();
+            let row = div()
+                .id(("slash-item", i))
+                .flex()
+                .items_center()
+                .gap_2()
+                .px_3()
+                .py_1()
+                .cursor_pointer()
+                .when(i == selected, |this| this.bg(rgb(0x313244)))
+                .hover(|this| this.bg(rgb(0x313244)))
+                .child(
+                    div()
+                        .flex()
+                        .justify_center()
+                        .w(px(28.))
+                        .text_color(rgb(0x9399b2))
+                        .child(icon),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .child(div().text_sm().child(name))
+                        .children(description.map(|description| {
+                            div()
+                                .text_xs()
+                                .text_color(rgb(0x6c7086))
+                                .child(description)
+                        })),
+                )
+                .on_click(cx.listener(move |this, _, window, cx| {
+                    this.choose_slash_item(index, item.clone(), window, cx);
+                }));
+            menu = menu.child(row);
+        }
+
+        Some(menu.into_any_element())
+    }
+}
@@ -0,0 +1,419 @@
+use gpui::{prelude::FluentBuilder, *};
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    input::{IndentInline, Input, InputEvent, InputState, MoveDown, MoveUp, OutdentInline},
+    Sizable,
+};
+use uuid::Uuid;
+
+use super::LoveNote;
+use crate::block::{Block, ColumnType, TableBlock, TableData};
+
+/// The table cell being edited; `row` is `None` for the header
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(super) struct CellRef {
+    pub block_id: Uuid,
+    pub row: Option<usize>,
+    pub column: usize,
+}
+
+impl LoveNote {
+    /// Create the shared cell editor input
+    pub(super) fn new_cell_input(
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> (Entity<InputState>, Subscription) {
+        let input = cx.new(|cx| InputState::new(window, cx));
+        let subscription = cx.subscribe_in(&input, window, |this, _, event: &InputEvent, window, cx| {
+            this.on_cell_input_event(event, window, cx);
+        });
+        (input, subscription)
+    }
+
+    fn on_cell_input_event(&mut self, event: &InputEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(cell) = self.active_cell else {
+            return;
+        };
+
+        match event {
+            InputEvent::Change => {
+                let value = self.cell_input.read(cx).value().to_string();
+                self.update_table(cell.block_id, cx, |table| match cell.row {
+                    Some(row) => table.set_cell(row, cell.column, value),
+                    None => {
+                        if let Some(column) = table.columns.get_mut(cell.column) {
+                            column.name = value;
+                        }
+                    }
+                });
+            }
+            InputEvent::PressEnter { .. } => self.move_cell(1, 0, true, window, cx),
+            InputEvent::Blur => {
+                self.active_cell = None;
+                self.save_document(cx);
+                cx.notify();
+            }
+            _ => {}
+        }
+    }
+
+    /// Apply `f` to a table block's data and re-render
+    fn update_table(
+        &mut self,
+        block_id: Uuid,
+        cx: &mut Context<Self>,
+        f: impl FnOnce(&mut TableData),
+    ) {
+        let table = self
+            .blocks
+            .iter_mut()
+            .find(|b| b.id == block_id)
+            .and_then(Block::table_mut);
+        if let Some(table) = table {
+            f(table);
+            cx.notify();
+        }
+    }
+
+    fn table(&self, block_id: Uuid) -> Option<&TableData> {
+        self.blocks
+            .iter()
+            .find(|b| b.id == block_id)
+            .and_then(Block::table)
+    }
+
+    /// Start editing a cell
+    pub(super) fn select_cell(&mut self, cell: CellRef, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(table) = self.table(cell.block_id) else {
+            return;
+        };
+        let value = match cell.row {
+            Some(row) => table.cell(row, cell.column).to_string(),
+            None => table
+                .columns
+                .get(cell.column)
+                .map(|c| c.name.clone())
+                .unwrap_or_default(),
+        };
+
+        if self.active_cell.is_some() {
+            self.save_document(cx);
+        }
+        self.active_cell = Some(cell);
+        self.cell_input.update(cx, |state, cx| {
+            state.set_value(value, window, cx);
+            state.focus(window, cx);
+        });
+        cx.notify();
+    }
+
+    /// Move the active cell by the given offset. Moving right past the last
+    /// column wraps to the next row; with `grow`, moving down past the last
+    /// row appends a new row.
+    fn move_cell(
+        &mut self,
+        rows: isize,
+        columns: isize,
+        grow: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(cell) = self.active_cell else {
+            return;
+        };
+        let Some(table) = self.table(cell.block_id) else {
+            return;
+        };
+        let (row_count, column_count) = (table.row_count(), table.column_count());
+
+        // Header is row 0 of this combined index
+        let mut row = cell.row.map_or(0, |r| r + 1) as isize;
+        let mut column = cell.column as isize + columns;
+        if column >= column_count as isize {
+            column = 0;
+            row += 1;
+        } else if column < 0 {
+            column = column_count as isize - 1;
+            row -= 1;
+        }
+        row += rows;
+
+        if row > row_count as isize {
+            if !grow {
+                return;
+            }
+            self.update_table(cell.block_id, cx, |table| table.insert_row(row_count));
+            row = row_count as isize + 1;
+        }
+        if row < 0 {
+            return;
+        }
+
+        let next = CellRef {
+            block_id: cell.block_id,
+            row: (row > 0).then(|| row as usize - 1),
+            column: column as usize,
+        };
+        self.select_cell(next, window, cx);
+    }
+
+    fn edit_table(
+        &mut self,
+        block_id: Uuid,
+        cx: &mut Context<Self>,
+        f: impl FnOnce(&mut TableData, Option<CellRef>),
+    ) {
+        let active = self.active_cell.filter(|cell| cell.block_id == block_id);
+        self.update_table(block_id, cx, |table| f(table, active));
+        // Structure changed: the edited cell may no longer exist
+        self.active_cell = None;
+        self.save_document(cx);
+    }
+
+    fn add_row(&mut self, block_id: Uuid, cx: &mut Context<Self>) {
+        self.edit_table(block_id, cx, |table, active| {
+            let at = active
+                .and_then(|cell| cell.row)
+                .map_or(table.row_count(), |row| row + 1);
+            table.insert_row(at);
+        });
+    }
+
+    fn remove_row(&mut self, block_id: Uuid, cx: &mut Context<Self>) {
+        self.edit_table(block_id, cx, |table, active| {
+            let at = active
+                .and_then(|cell| cell.row)
+                .unwrap_or(table.row_count().saturating_sub(1));
+            table.remove_row(at);
+        });
+    }
+
+    fn add_column(&mut self, block_id: Uuid, cx: &mut Context<Self>) {
+        self.edit_table(block_id, cx, |table, active| {
+            let at = active.map_or(table.column_count(), |cell| cell.column + 1);
+            table.insert_column(at);
+        });
+    }
+
+    fn remove_column(&mut self, block_id: Uuid, cx: &mut Context<Self>) {
+        self.edit_table(block_id, cx, |table, active| {
+            // Keep at least one column so the table stays editable
+            if table.column_count() > 1 {
+                let at = active.map_or(table.column_count() - 1, |cell| cell.column);
+                table.remove_column(at);
+            }
+        });
+    }
+
+    fn cycle_column_type(&mut self, block_id: Uuid, column: usize, cx: &mut Context<Self>) {
+        self.update_table(block_id, cx, |table| {
+            if let Some(column) = table.columns.get_mut(column) {
+                column.kind = column.kind.next();
+            }
+        });
+        self.save_document(cx);
+    }
+
+    /// Paste delimited clipboard text as table data. Pasting into an active
+    /// cell fills cells from there; pasting into a focused text block turns
+    /// the text into a new table. Returns true if the paste was handled.
+    pub(super) fn paste_table(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) else {
+            return false;
+        };
+
+        if let Some(cell) = self.active_cell {
+            let Some(cells) = TableData::parse_cells(&text) else {
+                return false;
+            };
+            let row = cell.row.unwrap_or(0);
+            self.update_table(cell.block_id, cx, |table| table.paste(row, cell.column, cells));
+            self.select_cell(cell, window, cx);
+            self.save_document(cx);
+            return true;
+        }
+
+        let Some(index) = self.focused_block_index(window, cx) else {
+            return false;
+        };
+        if self.blocks[index].kind != "text" {
+            return false;
+        }
+        let Some(data) = TableData::from_delimited(&text) else {
+            return false;
+        };
+
+        let mut block = Block::new(TableBlock::new(data), window, cx);
+        block.parent = self.blocks[index].parent;
+        if self.blocks[index].get_content(cx).trim().is_empty() {
+            self.remove_block(index, cx);
+            self.insert_block(index, block, window, cx);
+        } else {
+            self.insert_block(index + 1, block, window, cx);
+        }
+        true
+    }
+
+    pub(super) fn render_table_block(
+        &self,
+        index: usize,
+        block: &Block,
+        table: &TableData,
+        window: &Window,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let block_id = block.id;
+        let active = self.active_cell.filter(|cell| cell.block_id == block_id);
+
+        let header = div()
+            .flex()
+            .bg(rgb(0x181825))
+            .font_weight(FontWeight::SEMIBOLD)
+            .children(table.columns.iter().enumerate().map(|(column, col)| {
+                let cell = CellRef {
+                    block_id,
+                    row: None,
+                    column,
+                };
+                self.render_cell(cell, &col.name, true, active == Some(cell), cx)
+                    .child(
+                        Button::new(ElementId::named_usize(format!("column-type-{}", index), column))
+                            .label(col.kind.display_name())
+                            .xsmall()
+                            .ghost()
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.cycle_column_type(block_id, column, cx);
+                            })),
+                    )
+                    .into_any_element()
+            }));
+
+        let rows: Vec<_> = table
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(row, cells)| {
+                div()
+                    .flex()
+                    .border_t_1()
+                    .border_color(rgb(0x313244))
+                    .children(cells.iter().enumerate().map(|(column, value)| {
+                        let cell = CellRef {
+                            block_id,
+                            row: Some(row),
+                            column,
+                        };
+                        let kind = table.columns[column].kind;
+                        self.render_cell(cell, value, kind.accepts(value), active == Some(cell), cx)
+                            .when(kind == ColumnType::Number, |this| this.justify_end())
+                            .into_any_element()
+                    }))
+            })
+            .collect();
+
+        let grid = div()
+            .flex()
+            .flex_col()
+            .border_1()
+            .border_color(rgb(0x45475a))
+            .rounded_md()
+            .text_sm()
+            .capture_action(cx.listener(|this, _: &IndentInline, window, cx| {
+                if this.active_cell.is_some() {
+                    this.move_cell(0, 1, false, window, cx);
+                    cx.stop_propagation();
+                }
+            }))
+            .capture_action(cx.listener(|this, _: &OutdentInline, window, cx| {
+                if this.active_cell.is_some() {
+                    this.move_cell(0, -1, false, window, cx);
+                    cx.stop_propagation();
+                }
+            }))
+            .capture_action(cx.listener(|this, _: &MoveUp, window, cx| {
+                if this.active_cell.is_some() {
+                    this.move_cell(-1, 0, false, window, cx);
+                    cx.stop_propagation();
+                }
+            }))
+            .capture_action(cx.listener(|this, _: &MoveDown, window, cx| {
+                if this.active_cell.is_some() {
+                    this.move_cell(1, 0, false, window, cx);
+                    cx.stop_propagation();
+                }
+            }))
+            .child(header)
+            .children(rows);
+
+        let toolbar = div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .child(Self::table_button(("row-add", index), "+ Row", block_id, Self::add_row, cx))
+            .child(Self::table_button(("row-remove", index), "− Row", block_id, Self::remove_row, cx))
+            .child(Self::table_button(("col-add", index), "+ Column", block_id, Self::add_column, cx))
+            .child(Self::table_button(
+                ("col-remove", index),
+                "− Column",
+                block_id,
+                Self::remove_column,
+                cx,
+            ));
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(toolbar)
+            .child(grid)
+            .child(block.render(window, cx))
+            .into_any_element()
+    }
+
+    fn table_button(
+        id: impl Into<ElementId>,
+        label: &'static str,
+        block_id: Uuid,
+        action: fn(&mut Self, Uuid, &mut Context<Self>),
+        cx: &mut Context<Self>,
+    ) -> Button {
+        Button::new(id)
+            .label(label)
+            .xsmall()
+            .ghost()
+            .on_click(cx.listener(move |this, _, _window, cx| {
+                action(this, block_id, cx);
+            }))
+    }
+
+    fn render_cell(
+        &self,
+        cell: CellRef,
+        value: &str,
+        valid: bool,
+        is_active: bool,
+        cx: &mut Context<Self>,
+    ) -> Div {
+        let base = div()
+            .flex()
+            .items_center()
+            .flex_1()
+            .min_w(px(80.))
+            .px_2()
+            .py_1();
+
+        if is_active {
+            return base.child(div().flex_1().child(Input::new(&self.cell_input)));
+        }
+
+        base.cursor_text()
+            .when(!valid, |this| this.text_color(rgb(0xf38ba8)))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _, window, cx| {
+                    this.select_cell(cell, window, cx);
+                }),
+            )
+            .child(value.to_string())
+    }
+}
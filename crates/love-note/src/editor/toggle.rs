@@ -0,0 +1,138 @@
+use gpui::*;
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    IconName, Sizable,
+};
+use uuid::Uuid;
+
+use super::LoveNote;
+use crate::block::Block;
+
+/// A place a new block can be inserted: before `index`, nested in `parent`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(super) struct InsertSlot {
+    pub index: usize,
+    pub parent: Option<Uuid>,
+}
+
+impl LoveNote {
+    /// Ids of the toggles each block is nested in, outermost first.
+    /// Blocks whose parent doesn't directly enclose them are treated as
+    /// top-level.
+    pub(super) fn ancestors(&self) -> Vec<Vec<Uuid>> {
+        let mut open: Vec<Uuid> = Vec::new();
+        self.blocks
+            .iter()
+            .map(|block| {
+                match block.parent.and_then(|parent| open.iter().position(|id| *id == parent)) {
+                    Some(position) => open.truncate(position + 1),
+                    None => open.clear(),
+                }
+                let ancestors = open.clone();
+                if block.is_toggle() {
+                    open.push(block.id);
+                }
+                ancestors
+            })
+            .collect()
+    }
+
+    /// Indices of the blocks not hidden inside collapsed toggles
+    pub(super) fn visible_indices(&self) -> Vec<usize> {
+        self.ancestors()
+            .iter()
+            .enumerate()
+            .filter(|(_, ancestors)| !ancestors.iter().any(|id| self.is_collapsed(*id)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Index just past the block at `index` and everything nested in it
+    pub(super) fn subtree_end(&self, index: usize) -> usize {
+        let mut nested = vec![self.blocks[index].id];
+        let mut end = index + 1;
+        while let Some(block) = self.blocks.get(end) {
+            match block.parent {
+                Some(parent) if nested.contains(&parent) => nested.push(block.id),
+                _ => break,
+            }
+            end += 1;
+        }
+        end
+    }
+
+    pub(super) fn is_collapsed(&self, block_id: Uuid) -> bool {
+        self.collapsed.contains(&block_id)
+    }
+
+    fn toggle_collapsed(&mut self, block_id: Uuid, cx: &mut Context<Self>) {
+        let collapsed = !self.collapsed.remove(&block_id);
+        if collapsed {
+            self.collapsed.insert(block_id);
+        }
+        if let Err(e) = self.storage.set_collapsed(block_id, collapsed) {
+            eprintln!("Failed to save toggle state: {}", e);
+        }
+        cx.notify();
+    }
+
+    /// Move the children of a removed block up to its parent and forget
+    /// its collapsed state
+    pub(super) fn release_children(&mut self, removed: &Block) {
+        for block in &mut self.blocks {
+            if block.parent == Some(removed.id) {
+                block.parent = removed.parent;
+            }
+        }
+        if self.collapsed.remove(&removed.id)
+            && let Err(e) = self.storage.set_collapsed(removed.id, false)
+        {
+            eprintln!("Failed to save toggle state: {}", e);
+        }
+    }
+
+    pub(super) fn render_toggle_block(
+        &self,
+        index: usize,
+        block: &Block,
+        window: &Window,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let block_id = block.id;
+        let collapsed = self.is_collapsed(block_id);
+        let hidden = self.subtree_end(index) - index - 1;
+
+        let chevron = if collapsed {
+            IconName::ChevronRight
+        } else {
+            IconName::ChevronDown
+        };
+        let count = (collapsed && hidden > 0).then(|| {
+            div()
+                .text_xs()
+                .text_color(rgb(0x6c7086))
+                .child(match hidden {
+                    1 => "1 block".to_string(),
+                    n => format!("{} blocks", n),
+                })
+        });
+
+        div()
+            .flex()
+            .items_start()
+            .gap_2()
+            .child(
+                Button::new(("toggle", index))
+                    .icon(chevron)
+                    .xsmall()
+                    .ghost()
+                    .tooltip(if collapsed { "Expand" } else { "Collapse" })
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.toggle_collapsed(block_id, cx);
+                    })),
+            )
+            .child(div().flex_1().child(block.render(window, cx)))
+            .children(count)
+            .into_any_element()
+    }
+}
@@ -0,0 +1,88 @@
+use crate::block::parse::chem::{self, Part, Reaction, Species};
+use crate::block::parse::latex::MathNode;
+
+/// A reaction written as math nodes
+pub(super) fn reaction_nodes(reaction: &Reaction) -> Vec<MathNode> {
+    let mut nodes = side_nodes(&reaction.reactants);
+    if let Some(arrow) = reaction.arrow {
+        nodes.push(MathNode::Operator(arrow.symbol().to_string()));
+        nodes.extend(side_nodes(&reaction.products));
+    }
+    nodes
+}
+
+fn side_nodes(side: &[Species]) -> Vec<MathNode> {
+    let mut nodes = Vec::new();
+    for (i, species) in side.iter().enumerate() {
+        if i > 0 {
+            nodes.push(MathNode::Operator("+".to_string()));
+        }
+        if species.coefficient != 1. {
+            nodes.push(MathNode::Number(chem::format_number(species.coefficient)));
+            nodes.push(MathNode::Space(0.1));
+        }
+        let mut formula = part_nodes(&species.parts);
+        if species.charge != 0 {
+            let charge = vec![MathNode::Text(chem::charge_text(species.charge))];
+            match formula.pop() {
+                Some(MathNode::Scripts { base, sub, .. }) => formula.push(MathNode::Scripts {
+                    base,
+                    sub,
+                    sup: Some(charge),
+                }),
+                last => formula.push(MathNode::Scripts {
+                    base: Box::new(last.unwrap_or(MathNode::Group(Vec::new()))),
+                    sub: None,
+                    sup: Some(charge),
+                }),
+            }
+        }
+        nodes.extend(formula);
+        if let Some(state) = &species.state {
+            nodes.push(MathNode::Text(format!("({})", state)));
+        }
+    }
+    nodes
+}
+
+fn part_nodes(parts: &[Part]) -> Vec<MathNode> {
+    let with_count = |base: MathNode, count: u32| {
+        if count > 1 {
+            MathNode::Scripts {
+                base: Box::new(base),
+                sub: Some(vec![MathNode::Number(count.to_string())]),
+                sup: None,
+            }
+        } else {
+            base
+        }
+    };
+
+    let mut nodes = Vec::new();
+    for part in parts {
+        match part {
+            Part::Element { symbol, count } => {
+                nodes.push(with_count(MathNode::Text(symbol.clone()), *count));
+            }
+            Part::Group {
+                open,
+                close,
+                parts,
+                count,
+            } => {
+                nodes.push(MathNode::Symbol(open.to_string()));
+                nodes.extend(part_nodes(parts));
+                nodes.push(with_count(MathNode::Symbol(close.to_string()), *count));
+            }
+            Part::Adduct { coefficient, parts } => {
+                nodes.push(MathNode::Operator("·".to_string()));
+                if *coefficient > 1 {
+                    nodes.push(MathNode::Number(coefficient.to_string()));
+                }
+                nodes.extend(part_nodes(parts));
+            }
+            Part::Electron => nodes.push(MathNode::Ident("e".to_string())),
+        }
+    }
+    nodes
+}
@@ -0,0 +1,541 @@
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use love_note_plugin_api::ExportFormat;
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::formula::reaction_nodes;
+use super::{media_type, PluginExports};
+use crate::block::parse::chem;
+use crate::block::parse::latex::{self, MathNode};
+use crate::block::{
+    CalloutStyle, ChartBlock, ChartSpec, Citation, CodeBlock, ColumnType, HeadingBlock, Marks,
+    Plot, TableData, TEXT_COLORS,
+};
+use crate::storage::{AttachmentStore, Document, StoredBlock};
+
+/// Catppuccin Mocha for the dark theme and Latte for the light one. Text
+/// colors are named so they can be swapped with the theme.
+const STYLE: &str = r#":root {
+  --base: #1e1e2e; --mantle: #181825; --surface: #313244; --overlay: #6c7086;
+  --subtext: #a6adc8; --text: #cdd6f4; --red: #f38ba8; --peach: #fab387;
+  --yellow: #f9e2af; --green: #a6e3a1; --blue: #89b4fa; --mauve: #cba6f7;
+  color-scheme: dark;
+}
+:root.light {
+  --base: #eff1f5; --mantle: #e6e9ef; --surface: #ccd0da; --overlay: #9ca0b0;
+  --subtext: #6c6f85; --text: #4c4f69; --red: #d20f39; --peach: #fe640b;
+  --yellow: #df8e1d; --green: #40a02b; --blue: #1e66f5; --mauve: #8839ef;
+  color-scheme: light;
+}
+body {
+  margin: 0; background: var(--base); color: var(--text);
+  font: 16px/1.6 system-ui, -apple-system, "Segoe UI", sans-serif;
+}
+main { max-width: 46rem; margin: 0 auto; padding: 3rem 1.5rem; }
+h1, h2, h3 { line-height: 1.25; margin: 1.6em 0 0.5em; }
+h1 { font-size: 2em; } h2 { font-size: 1.5em; } h3 { font-size: 1.25em; }
+p, ul, ol, blockquote, figure, details, .callout, .math, .table { margin: 0.6em 0; }
+a { color: var(--blue); }
+code, pre { font-family: ui-monospace, Menlo, Consolas, "DejaVu Sans Mono", monospace; font-size: 0.9em; }
+:not(pre) > code { background: var(--surface); border-radius: 4px; padding: 0.1em 0.3em; }
+pre { background: var(--mantle); border-radius: 6px; padding: 0.8em 1em; overflow-x: auto; margin: 0; }
+pre span { color: var(--dark); }
+:root.light pre span { color: var(--light); }
+figure { margin-left: 0; margin-right: 0; }
+figure img { display: block; max-width: 100%; margin: 0 auto; }
+figcaption { color: var(--subtext); font-size: 0.9em; text-align: center; margin-top: 0.3em; }
+figure.code figcaption { text-align: right; margin: 0 0 0.2em; }
+ul.todo { list-style: none; padding-left: 0.4em; }
+ul.todo input { margin-right: 0.5em; }
+blockquote { border-left: 3px solid var(--overlay); padding-left: 1em; margin-left: 0; color: var(--subtext); }
+.callout { border-left: 4px solid var(--blue); background: var(--mantle); border-radius: 4px; padding: 0.6em 1em; }
+.callout.warning { border-color: var(--yellow); }
+.callout.danger { border-color: var(--red); }
+summary { cursor: pointer; }
+details > :not(summary) { margin-left: 1.2em; }
+hr { border: none; border-top: 1px solid var(--surface); margin: 1.5em 0; }
+.math { display: flex; align-items: center; }
+.math math { flex: 1; font-size: 1.15em; }
+.math .number { color: var(--subtext); }
+.error { color: var(--red); }
+.missing { color: var(--subtext); font-style: italic; }
+.citation code { background: none; padding: 0; color: var(--subtext); }
+.table { overflow-x: auto; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid var(--surface); padding: 0.3em 0.7em; text-align: left; }
+th { background: var(--mantle); }
+.num { text-align: right; font-variant-numeric: tabular-nums; }
+.c0 { color: var(--red); } .c1 { color: var(--peach); } .c2 { color: var(--yellow); }
+.c3 { color: var(--green); } .c4 { color: var(--blue); } .c5 { color: var(--mauve); }
+button.theme {
+  position: fixed; top: 1rem; right: 1rem; border: 1px solid var(--surface); border-radius: 6px;
+  background: var(--mantle); color: var(--text); font-size: 1.1em; padding: 0.2em 0.5em; cursor: pointer;
+}
+@media print { button.theme { display: none; } }
+"#;
+
+/// Starts in the light theme when the reader's system prefers it
+const SCRIPT: &str = "if (matchMedia('(prefers-color-scheme: light)').matches) \
+document.documentElement.classList.add('light');";
+
+/// Export a document as a self-contained HTML page at `path` and return
+/// the path
+pub fn write_html(
+    document: &Document,
+    path: &Path,
+    images: &AttachmentStore,
+    plugins: &PluginExports,
+) -> Result<PathBuf> {
+    let path = path.with_extension("html");
+    let mut head = format!(
+        "<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta name=\"generator\" content=\"Love Note\">\n<title>{}</title>\n",
+        escape(&document.display_title())
+    );
+    if let Some(author) = document.metadata.get("author").and_then(Value::as_str) {
+        writeln!(head, "<meta name=\"author\" content=\"{}\">", escape(author))?;
+    }
+    let body = blocks_html(&document.blocks, images, plugins);
+    let page = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n{}<style>\n{}</style>\n<script>{}</script>\n</head>\n\
+         <body>\n<button class=\"theme\" title=\"Switch theme\" \
+         onclick=\"document.documentElement.classList.toggle('light')\">◐</button>\n\
+         <main>\n{}</main>\n</body>\n</html>\n",
+        head, STYLE, SCRIPT, body
+    );
+    std::fs::write(&path, page).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+fn blocks_html(blocks: &[StoredBlock], images: &AttachmentStore, plugins: &PluginExports) -> String {
+    let mut out = String::new();
+    // Toggles whose `<details>` element is open, innermost last
+    let mut open: Vec<Uuid> = Vec::new();
+    // Kind and parent of the list element that is open
+    let mut list: Option<(&str, Option<Uuid>)> = None;
+    let mut equations = 0;
+
+    for block in blocks {
+        while let Some(&toggle) = open.last()
+            && block.parent != Some(toggle)
+        {
+            close_list(&mut out, &mut list);
+            open.pop();
+            out.push_str("</details>\n");
+        }
+
+        let kind = block.kind.as_str();
+        let item = matches!(kind, "bullet" | "numbered" | "todo");
+        if list.is_some() && list != Some((kind, block.parent)) {
+            close_list(&mut out, &mut list);
+        }
+        if item && list.is_none() {
+            out.push_str(match kind {
+                "numbered" => "<ol>\n",
+                "todo" => "<ul class=\"todo\">\n",
+                _ => "<ul>\n",
+            });
+            list = Some((kind, block.parent));
+        }
+
+        let text = block.content.as_str();
+        match kind {
+            "toggle" => {
+                open.push(block.id);
+                writeln!(out, "<details open>\n<summary>{}</summary>", inline_html(block)).ok();
+            }
+            "heading" => {
+                let level = HeadingBlock::from_props(&block.props).level();
+                writeln!(
+                    out,
+                    "<h{level} id=\"{}\">{}</h{level}>",
+                    slug(text),
+                    inline_html(block)
+                )
+                .ok();
+            }
+            "bullet" | "numbered" => {
+                writeln!(out, "<li>{}</li>", inline_html(block)).ok();
+            }
+            "todo" => {
+                let checked = block.props.get("checked").and_then(Value::as_bool) == Some(true);
+                writeln!(
+                    out,
+                    "<li><input type=\"checkbox\" disabled{}>{}</li>",
+                    if checked { " checked" } else { "" },
+                    inline_html(block)
+                )
+                .ok();
+            }
+            "code" => {
+                let language = block.props.get("language").and_then(Value::as_str).unwrap_or("plain");
+                out.push_str(&code_html(language, text));
+            }
+            "math" => {
+                let label = (!latex::is_unnumbered(text)).then(|| {
+                    equations += 1;
+                    equations
+                });
+                match latex::parse(text) {
+                    Ok(nodes) => out.push_str(&formula_html(&nodes, label)),
+                    Err(e) => out.push_str(&source_error(text, &e)),
+                }
+            }
+            "chemistry" => match chem::parse(text) {
+                Ok(reaction) => out.push_str(&formula_html(&reaction_nodes(&reaction), None)),
+                Err(e) => out.push_str(&source_error(text, &e)),
+            },
+            "citation" => match Citation::parse(text) {
+                Ok(citation) => {
+                    writeln!(
+                        out,
+                        "<p class=\"citation\" id=\"{}\"><code>[{}]</code> {}</p>",
+                        escape(&citation.key),
+                        escape(&citation.key),
+                        escape(&citation.reference())
+                    )
+                    .ok();
+                }
+                Err(e) => out.push_str(&source_error(text, &e)),
+            },
+            "chart" => out.push_str(&chart_html(block, blocks)),
+            "table" => out.push_str(&table_html(&TableData::from_props(&block.props))),
+            "image" => out.push_str(&image_html(block, images)),
+            "quote" => {
+                writeln!(out, "<blockquote>{}</blockquote>", inline_html(block)).ok();
+            }
+            "callout" => {
+                let style: CalloutStyle = block
+                    .props
+                    .get("style")
+                    .cloned()
+                    .and_then(|style| serde_json::from_value(style).ok())
+                    .unwrap_or_default();
+                let class = match style {
+                    CalloutStyle::Info => "info",
+                    CalloutStyle::Warning => "warning",
+                    CalloutStyle::Danger => "danger",
+                };
+                writeln!(out, "<div class=\"callout {}\">{}</div>", class, inline_html(block)).ok();
+            }
+            "divider" => out.push_str("<hr>\n"),
+            "text" if text.trim().is_empty() => {}
+            "text" => {
+                writeln!(out, "<p>{}</p>", inline_html(block)).ok();
+            }
+            _ => match plugins.export(block, ExportFormat::Html) {
+                Some(html) => {
+                    writeln!(out, "{}", html.trim_end()).ok();
+                }
+                None if text.trim().is_empty() => {}
+                None => {
+                    writeln!(out, "<p>{}</p>", escape_lines(text)).ok();
+                }
+            },
+        }
+    }
+    close_list(&mut out, &mut list);
+    for _ in open {
+        out.push_str("</details>\n");
+    }
+    out
+}
+
+fn close_list(out: &mut String, list: &mut Option<(&str, Option<Uuid>)>) {
+    match list.take() {
+        Some(("numbered", _)) => out.push_str("</ol>\n"),
+        Some(_) => out.push_str("</ul>\n"),
+        None => {}
+    }
+}
+
+/// Escape text for use in element content and quoted attributes
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape text, keeping its line breaks
+fn escape_lines(text: &str) -> String {
+    escape(text).replace('\n', "<br>\n")
+}
+
+/// An id for a heading, so sections can be linked to
+fn slug(text: &str) -> String {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    words.join("-")
+}
+
+/// Text with its inline formatting as HTML. Links other than web and
+/// mail addresses are dropped, so a shared page can't run scripts.
+fn inline_html(block: &StoredBlock) -> String {
+    let marks = Marks::from_props(&block.props, &block.content);
+    let mut out = String::new();
+    for span in marks.spans() {
+        let mut html = escape_lines(&span.content);
+        if span.code {
+            html = format!("<code>{}</code>", html);
+        }
+        if span.strikethrough {
+            html = format!("<s>{}</s>", html);
+        }
+        if span.italic {
+            html = format!("<em>{}</em>", html);
+        }
+        if span.bold {
+            html = format!("<strong>{}</strong>", html);
+        }
+        if let Some(color) = &span.color {
+            let index = u32::from_str_radix(color.trim_start_matches('#'), 16)
+                .ok()
+                .and_then(|color| TEXT_COLORS.iter().position(|c| *c == color));
+            html = match index {
+                Some(index) => format!("<span class=\"c{}\">{}</span>", index, html),
+                None => format!("<span style=\"color: {}\">{}</span>", escape(color), html),
+            };
+        }
+        if let Some(url) = &span.link
+            && ["http://", "https://", "mailto:"]
+                .iter()
+                .any(|scheme| url.to_lowercase().starts_with(scheme))
+        {
+            html = format!("<a href=\"{}\">{}</a>", escape(url), html);
+        }
+        out.push_str(&html);
+    }
+    out
+}
+
+/// A code block highlighted in both themes. Each run carries its dark and
+/// light colors, and the stylesheet picks one.
+fn code_html(language: &str, text: &str) -> String {
+    let dark = CodeBlock::highlight_colors(language, text, false);
+    let light = CodeBlock::highlight_colors(language, text, true);
+    let color_at = |highlights: &[(Range<usize>, u32)], offset: usize| {
+        highlights
+            .iter()
+            .find(|(range, _)| range.contains(&offset))
+            .map(|(_, color)| *color)
+    };
+
+    let mut bounds = vec![0, text.len()];
+    for (range, _) in dark.iter().chain(&light) {
+        bounds.push(range.start);
+        bounds.push(range.end);
+    }
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    // Neighbouring runs of the same colors are joined
+    let mut runs: Vec<(_, Range<usize>)> = Vec::new();
+    for pair in bounds.windows(2) {
+        let colors = color_at(&dark, pair[0]).zip(color_at(&light, pair[0]));
+        match runs.last_mut() {
+            Some((last, range)) if *last == colors => range.end = pair[1],
+            _ => runs.push((colors, pair[0]..pair[1])),
+        }
+    }
+    let mut code = String::new();
+    for (colors, range) in runs {
+        let piece = escape(&text[range]);
+        match colors {
+            Some((dark, light)) => {
+                write!(
+                    code,
+                    "<span style=\"--dark: #{:06x}; --light: #{:06x}\">{}</span>",
+                    dark, light, piece
+                )
+                .ok();
+            }
+            None => code.push_str(&piece),
+        }
+    }
+
+    let mut out = String::from("<figure class=\"code\">");
+    if language != "plain" {
+        write!(out, "<figcaption>{}</figcaption>", escape(CodeBlock::language_name(language))).ok();
+    }
+    writeln!(out, "<pre><code>{}</code></pre></figure>", code).ok();
+    out
+}
+
+/// A display formula, with its equation number if it has one
+fn formula_html(nodes: &[MathNode], label: Option<usize>) -> String {
+    let number = label
+        .map(|label| format!("<span class=\"number\">({})</span>", label))
+        .unwrap_or_default();
+    format!(
+        "<div class=\"math\"><math display=\"block\">{}</math>{}</div>\n",
+        mathml(nodes),
+        number
+    )
+}
+
+/// Source that failed to parse, with the error
+fn source_error(source: &str, error: &str) -> String {
+    format!(
+        "<pre>{}</pre>\n<p class=\"error\">{}</p>\n",
+        escape(source),
+        escape(error)
+    )
+}
+
+fn mathml(nodes: &[MathNode]) -> String {
+    nodes.iter().map(mathml_node).collect()
+}
+
+fn mrow(nodes: &[MathNode]) -> String {
+    format!("<mrow>{}</mrow>", mathml(nodes))
+}
+
+fn mathml_node(node: &MathNode) -> String {
+    match node {
+        MathNode::Ident(s) => format!("<mi>{}</mi>", escape(s)),
+        MathNode::Number(s) => format!("<mn>{}</mn>", escape(s)),
+        MathNode::Operator(s) => format!("<mo>{}</mo>", escape(s)),
+        MathNode::Symbol(s) if s.chars().all(char::is_alphabetic) => {
+            format!("<mi mathvariant=\"normal\">{}</mi>", escape(s))
+        }
+        MathNode::Symbol(s) => format!("<mo stretchy=\"false\">{}</mo>", escape(s)),
+        MathNode::Text(s) => format!("<mtext>{}</mtext>", escape(s)),
+        MathNode::Bold(nodes) => format!("<mrow style=\"font-weight: bold\">{}</mrow>", mathml(nodes)),
+        MathNode::Group(nodes) => mrow(nodes),
+        MathNode::Scripts { base, sub, sup } => {
+            let base = mathml_node(base);
+            match (sub, sup) {
+                (Some(sub), Some(sup)) => format!("<msubsup>{}{}{}</msubsup>", base, mrow(sub), mrow(sup)),
+                (Some(sub), None) => format!("<msub>{}{}</msub>", base, mrow(sub)),
+                (None, Some(sup)) => format!("<msup>{}{}</msup>", base, mrow(sup)),
+                (None, None) => base,
+            }
+        }
+        MathNode::Frac(numerator, denominator) => {
+            format!("<mfrac>{}{}</mfrac>", mrow(numerator), mrow(denominator))
+        }
+        MathNode::Sqrt { index: None, radicand } => format!("<msqrt>{}</msqrt>", mathml(radicand)),
+        MathNode::Sqrt {
+            index: Some(index),
+            radicand,
+        } => format!("<mroot>{}{}</mroot>", mrow(radicand), mrow(index)),
+        MathNode::Space(em) => format!("<mspace width=\"{}em\"/>", em),
+        MathNode::Unknown(s) => format!("<merror><mtext>{}</mtext></merror>", escape(s)),
+    }
+}
+
+fn table_html(table: &TableData) -> String {
+    if table.columns.is_empty() {
+        return String::new();
+    }
+    let class = |kind: ColumnType| match kind {
+        ColumnType::Number => " class=\"num\"",
+        _ => "",
+    };
+    let mut out = String::from("<div class=\"table\"><table>\n<thead><tr>");
+    for column in &table.columns {
+        write!(out, "<th{}>{}</th>", class(column.kind), escape(&column.name)).ok();
+    }
+    out.push_str("</tr></thead>\n<tbody>\n");
+    for row in &table.rows {
+        out.push_str("<tr>");
+        for (column, value) in table.columns.iter().zip(row) {
+            write!(out, "<td{}>{}</td>", class(column.kind), escape_lines(value)).ok();
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</tbody>\n</table></div>\n");
+    out
+}
+
+fn image_html(block: &StoredBlock, images: &AttachmentStore) -> String {
+    let file = block.attachment().unwrap_or_default();
+    let caption = block.content.trim();
+    let Ok(bytes) = std::fs::read(images.path(file)) else {
+        let text = match caption {
+            "" => format!("[Image {}]", file),
+            caption => format!("[Image: {}]", caption),
+        };
+        return format!("<p class=\"missing\">{}</p>\n", escape(&text));
+    };
+    let mut out = format!(
+        "<figure><img src=\"{}\" alt=\"{}\">",
+        data_url(media_type(file), &bytes),
+        escape(caption)
+    );
+    if !caption.is_empty() {
+        write!(out, "<figcaption>{}</figcaption>", escape_lines(caption)).ok();
+    }
+    out.push_str("</figure>\n");
+    out
+}
+
+fn chart_html(block: &StoredBlock, blocks: &[StoredBlock]) -> String {
+    let spec: ChartSpec = serde_json::from_value(block.props.clone()).unwrap_or_default();
+    let data = match spec.source {
+        Some(source) => blocks
+            .iter()
+            .find(|b| b.id == source && b.kind == "table")
+            .map(|table| TableData::from_props(&table.props)),
+        None => ChartBlock::parse_inline(&block.content),
+    };
+    let plot = data
+        .ok_or_else(|| "No data to plot".to_string())
+        .and_then(|data| Plot::new(&spec, &data));
+    match plot {
+        Ok(plot) => format!(
+            "<figure><img src=\"{}\" alt=\"Chart\"></figure>\n",
+            data_url("image/svg+xml", plot.to_svg().as_bytes())
+        ),
+        Err(e) => format!("<p class=\"missing\">{}</p>\n", escape(&format!("[Chart: {}]", e))),
+    }
+}
+
+fn data_url(media_type: &str, bytes: &[u8]) -> String {
+    format!("data:{};base64,{}", media_type, BASE64.encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{plugins, sample, scratch_dir};
+
+    #[test]
+    fn writes_a_self_contained_page() {
+        let dir = scratch_dir();
+        let (document, images) = sample(&dir);
+        let path = write_html(&document, &dir.join("out"), &images, &plugins()).unwrap();
+        assert_eq!(path, dir.join("out.html"));
+
+        let page = std::fs::read_to_string(&path).unwrap();
+        assert!(page.contains("<title>Results</title>"));
+        assert!(page.contains("<meta name=\"author\" content=\"Ada\">"));
+        assert!(page.contains("Results</h1>"));
+        assert!(page.contains("<math"));
+        assert!(page.contains("src=\"data:image/png;base64,"));
+        assert!(page.contains("PLUGIN:Html"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(escape("<a href=\"x\">&</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+    }
+}
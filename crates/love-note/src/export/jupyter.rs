@@ -0,0 +1,243 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use love_note_plugin_api::ExportFormat;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use super::{media_type, PluginExports};
+use crate::markdown::to_markdown;
+use crate::storage::{AttachmentStore, Document, StoredBlock};
+
+/// Export a document as a Jupyter notebook at `path` and return the path.
+///
+/// Code blocks become code cells, or raw cells when they are plain text,
+/// and the blocks between them Markdown cells, split at headings, with
+/// images attached to the cells. Blocks
+/// imported as outputs of a code cell are written back as its outputs.
+pub fn write_notebook(
+    document: &Document,
+    path: &Path,
+    images: &AttachmentStore,
+    plugins: &PluginExports,
+) -> Result<PathBuf> {
+    let path = path.with_extension("ipynb");
+    let jupyter = document.metadata.get("jupyter");
+    let outputs: HashSet<Uuid> = jupyter
+        .and_then(|jupyter| jupyter.get("outputs"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|id| id.as_str()?.parse().ok())
+        .collect();
+
+    let blocks = &document.blocks;
+    let mut cells: Vec<Value> = Vec::new();
+    // Start of the blocks waiting to be written as a Markdown cell
+    let mut start: Option<usize> = None;
+    // Whether the last cell is code that outputs can still be added to
+    let mut in_code = false;
+
+    for (i, block) in blocks.iter().enumerate() {
+        if in_code && outputs.contains(&block.id) {
+            let output = output(block, images)?;
+            if let Some(cell_outputs) = cells
+                .last_mut()
+                .and_then(|cell| cell.get_mut("outputs"))
+                .and_then(Value::as_array_mut)
+            {
+                cell_outputs.push(output);
+            }
+            continue;
+        }
+        if matches!(block.kind.as_str(), "code" | "heading")
+            && let Some(start) = start.take()
+        {
+            cells.extend(markdown_cell(&blocks[start..i], images, plugins)?);
+        }
+        in_code = false;
+        if block.kind != "code" {
+            start.get_or_insert(i);
+        } else if block.props.get("language").and_then(Value::as_str) == Some("plain") {
+            // Plain text can't be run, so it's kept as a raw cell
+            cells.push(json!({
+                "cell_type": "raw",
+                "metadata": {},
+                "source": lines(&block.content),
+            }));
+        } else {
+            in_code = true;
+            cells.push(json!({
+                "cell_type": "code",
+                "execution_count": null,
+                "metadata": {},
+                "outputs": [],
+                "source": lines(&block.content),
+            }));
+        }
+    }
+    if let Some(start) = start {
+        cells.extend(markdown_cell(&blocks[start..], images, plugins)?);
+    }
+
+    let notebook = json!({
+        "cells": cells,
+        "metadata": notebook_metadata(blocks, jupyter),
+        "nbformat": 4,
+        "nbformat_minor": 4,
+    });
+    let text = serde_json::to_string_pretty(&notebook)? + "\n";
+    std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Source text as the list of lines notebooks store, each but the last
+/// keeping its newline
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// A Markdown cell of `blocks` with the images they show attached, or
+/// `None` when they hold no text
+fn markdown_cell(
+    blocks: &[StoredBlock],
+    images: &AttachmentStore,
+    plugins: &PluginExports,
+) -> Result<Option<Value>> {
+    let text = to_markdown(
+        blocks,
+        |file| format!("attachment:{}", file),
+        |block| plugins.export(block, ExportFormat::Markdown),
+    );
+    let text = text.trim_end();
+    if text.is_empty() {
+        return Ok(None);
+    }
+
+    let mut attachments = Map::new();
+    for file in blocks.iter().filter(|b| b.kind == "image").filter_map(StoredBlock::attachment) {
+        let bytes = std::fs::read(images.path(file))
+            .with_context(|| format!("Failed to read image {}", file))?;
+        attachments.insert(file.to_string(), image_data(file, &bytes));
+    }
+    let mut cell = json!({
+        "cell_type": "markdown",
+        "metadata": {},
+        "source": lines(text),
+    });
+    if !attachments.is_empty() {
+        cell["attachments"] = Value::Object(attachments);
+    }
+    Ok(Some(cell))
+}
+
+/// An image as a map of its media type to its contents, base64 encoded
+/// except for SVG
+fn image_data(file: &str, bytes: &[u8]) -> Value {
+    let media_type = media_type(file);
+    let content = match media_type {
+        "image/svg+xml" => String::from_utf8_lossy(bytes).into_owned(),
+        _ => BASE64.encode(bytes),
+    };
+    let mut data = Map::new();
+    data.insert(media_type.to_string(), Value::String(content));
+    Value::Object(data)
+}
+
+/// A code cell output for a block imported from one
+fn output(block: &StoredBlock, images: &AttachmentStore) -> Result<Value> {
+    if let Some(file) = block.attachment() {
+        let bytes = std::fs::read(images.path(file))
+            .with_context(|| format!("Failed to read image {}", file))?;
+        return Ok(json!({
+            "output_type": "display_data",
+            "data": image_data(file, &bytes),
+            "metadata": {},
+        }));
+    }
+    let text = format!("{}\n", block.content);
+    Ok(json!({
+        "output_type": "stream",
+        "name": "stdout",
+        "text": lines(&text),
+    }))
+}
+
+/// The kernel the notebook was imported with, or one for the language
+/// most code blocks are written in
+fn notebook_metadata(blocks: &[StoredBlock], jupyter: Option<&Value>) -> Value {
+    let mut metadata = Map::new();
+    for key in ["kernelspec", "language_info"] {
+        if let Some(value) = jupyter.and_then(|jupyter| jupyter.get(key)) {
+            metadata.insert(key.to_string(), value.clone());
+        }
+    }
+    if !metadata.is_empty() {
+        return Value::Object(metadata);
+    }
+
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for block in blocks.iter().filter(|b| b.kind == "code") {
+        let language = block.props.get("language").and_then(Value::as_str).unwrap_or("plain");
+        match counts.iter_mut().find(|(l, _)| *l == language) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((language, 1)),
+        }
+    }
+    let language = counts
+        .iter()
+        .filter(|(language, _)| *language != "plain")
+        .max_by_key(|(_, count)| *count)
+        .map_or("python", |(language, _)| language);
+    let kernel = match language {
+        "python" => Some(("python3", "Python 3", "python")),
+        "r" => Some(("ir", "R", "R")),
+        _ => None,
+    };
+    if let Some((name, display_name, kernel_language)) = kernel {
+        metadata.insert(
+            "kernelspec".to_string(),
+            json!({ "name": name, "display_name": display_name, "language": kernel_language }),
+        );
+    }
+    metadata.insert("language_info".to_string(), json!({ "name": language }));
+    Value::Object(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{plugins, sample, scratch_dir};
+
+    #[test]
+    fn writes_code_and_markdown_cells() {
+        let dir = scratch_dir();
+        let (document, images) = sample(&dir);
+        let path = write_notebook(&document, &dir.join("out"), &images, &plugins()).unwrap();
+        assert_eq!(path, dir.join("out.ipynb"));
+
+        let notebook: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(notebook["nbformat"], 4);
+        let cells = notebook["cells"].as_array().unwrap();
+        let source = |cell: &Value| match &cell["source"] {
+            Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect::<String>(),
+            source => source.as_str().unwrap_or_default().to_string(),
+        };
+        let code: Vec<&Value> = cells.iter().filter(|cell| cell["cell_type"] == "code").collect();
+        assert_eq!(code.len(), 1);
+        assert_eq!(source(code[0]), "print('hi')");
+
+        let markdown: String = cells
+            .iter()
+            .filter(|cell| cell["cell_type"] == "markdown")
+            .map(source)
+            .collect();
+        assert!(markdown.contains("# Results"));
+        assert!(markdown.contains("PLUGIN:Markdown"));
+        assert!(cells.iter().any(|cell| cell.get("attachments").is_some()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
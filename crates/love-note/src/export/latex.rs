@@ -0,0 +1,571 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use love_note_plugin_api::ExportFormat;
+use resvg::{tiny_skia, usvg};
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::formula::reaction_nodes;
+use super::{author, print_color, PluginExports};
+use crate::block::parse::chem;
+use crate::block::parse::latex::MathNode;
+use crate::block::{
+    CalloutStyle, ChartBlock, ChartSpec, Citation, ColumnType, HeadingBlock, Marks, Plot, TableData,
+};
+use crate::storage::{AttachmentStore, Document, StoredBlock};
+
+/// Folder the images and charts of an exported document are written to
+const IMAGE_DIR: &str = "images";
+
+const PREAMBLE: &str = r"\documentclass{article}
+\usepackage[utf8]{inputenc}
+\usepackage[T1]{fontenc}
+\usepackage{amsmath}
+\usepackage{amssymb}
+\usepackage{graphicx}
+\usepackage{booktabs}
+\usepackage{listings}
+\usepackage{xcolor}
+\usepackage[normalem]{ulem}
+\usepackage{hyperref}
+
+\lstset{basicstyle=\ttfamily\small, columns=fullflexible, breaklines=true, frame=single}
+";
+
+/// Names `listings` knows our code languages by
+const LISTINGS_LANGUAGES: &[(&str, &str)] = &[
+    ("python", "Python"),
+    ("r", "R"),
+    ("c", "C"),
+    ("cpp", "C++"),
+    ("java", "Java"),
+    ("bash", "bash"),
+    ("sql", "SQL"),
+    ("matlab", "Matlab"),
+    ("latex", "[LaTeX]TeX"),
+];
+
+/// Files written alongside the LaTeX source
+#[derive(Default)]
+struct Assets {
+    /// Image files for the images folder, by name
+    images: Vec<(String, Vec<u8>)>,
+    /// BibTeX entries by key, in the order they are first cited
+    entries: Vec<(String, String)>,
+}
+
+/// Export a document as LaTeX to `path`.
+///
+/// A document with images, charts or citations is written as a folder
+/// named after `path`, holding the `.tex` file, a `.bib` file of the
+/// citations and an `images` folder. Returns the path of the `.tex` file.
+pub fn write_latex(
+    document: &Document,
+    path: &Path,
+    images: &AttachmentStore,
+    plugins: &PluginExports,
+) -> Result<PathBuf> {
+    let mut path = path.with_extension("tex");
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("document")
+        .to_string();
+
+    let mut assets = Assets::default();
+    let body = blocks_latex(&document.blocks, images, plugins, &mut assets);
+
+    let mut out = PREAMBLE.to_string();
+    writeln!(out, "\n\\title{{{}}}", escape(&document.display_title()))?;
+    if let Some(author) = author(document) {
+        writeln!(out, "\\author{{{}}}", escape(&author))?;
+    }
+    write!(out, "\n\\begin{{document}}\n\\maketitle\n\n{}", body)?;
+    if !assets.entries.is_empty() {
+        write!(out, "\\bibliographystyle{{plain}}\n\\bibliography{{{}}}\n\n", stem)?;
+    }
+    out.push_str("\\end{document}\n");
+
+    if !assets.images.is_empty() || !assets.entries.is_empty() {
+        let folder = path.with_extension("");
+        std::fs::create_dir_all(&folder)
+            .with_context(|| format!("Failed to create {}", folder.display()))?;
+        if !assets.images.is_empty() {
+            let image_dir = folder.join(IMAGE_DIR);
+            std::fs::create_dir_all(&image_dir)
+                .with_context(|| format!("Failed to create {}", image_dir.display()))?;
+            for (name, bytes) in &assets.images {
+                std::fs::write(image_dir.join(name), bytes)
+                    .with_context(|| format!("Failed to write image {}", name))?;
+            }
+        }
+        if !assets.entries.is_empty() {
+            let bib_path = folder.join(format!("{}.bib", stem));
+            let entries: Vec<&str> = assets.entries.iter().map(|(_, entry)| entry.as_str()).collect();
+            std::fs::write(&bib_path, entries.join("\n\n") + "\n")
+                .with_context(|| format!("Failed to write {}", bib_path.display()))?;
+        }
+        path = folder.join(path.file_name().unwrap_or_default());
+    }
+
+    std::fs::write(&path, out).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+fn blocks_latex(
+    blocks: &[StoredBlock],
+    images: &AttachmentStore,
+    plugins: &PluginExports,
+    assets: &mut Assets,
+) -> String {
+    let mut out = String::new();
+    // Kind and parent of the list environment that is open
+    let mut list: Option<(&str, Option<Uuid>)> = None;
+    // The heading the title is taken from, which `\maketitle` prints
+    let title_heading = blocks.iter().position(|block| block.kind == "heading");
+    // Keys of consecutive citation blocks, cited together
+    let mut cites: Vec<String> = Vec::new();
+    // Whether the last thing written is a paragraph citations can follow
+    let mut after_paragraph = false;
+
+    for (index, block) in blocks.iter().enumerate() {
+        let kind = block.kind.as_str();
+        if kind != "citation" {
+            flush_cites(&mut out, &mut cites, after_paragraph);
+        }
+        let item = matches!(kind, "bullet" | "numbered" | "todo");
+        if list.is_some() && list != Some((kind, block.parent)) {
+            close_list(&mut out, &mut list);
+        }
+        if item && list.is_none() {
+            out.push_str(match kind {
+                "numbered" => "\\begin{enumerate}\n",
+                _ => "\\begin{itemize}\n",
+            });
+            list = Some((kind, block.parent));
+        }
+
+        let text = block.content.as_str();
+        let mut paragraph = false;
+        match kind {
+            "heading" if Some(index) == title_heading => {}
+            "heading" => {
+                let command = match HeadingBlock::from_props(&block.props).level() {
+                    1 => "section",
+                    2 => "subsection",
+                    _ => "subsubsection",
+                };
+                writeln!(out, "\\{}{{{}}}\n", command, inline_latex(block)).ok();
+            }
+            "toggle" => {
+                writeln!(out, "\\paragraph{{{}}}\n", inline_latex(block)).ok();
+            }
+            "bullet" | "numbered" => {
+                writeln!(out, "  \\item {}", inline_latex(block)).ok();
+            }
+            "todo" => {
+                let checked = block.props.get("checked").and_then(Value::as_bool) == Some(true);
+                let marker = if checked { "$\\boxtimes$" } else { "$\\square$" };
+                writeln!(out, "  \\item[{}] {}", marker, inline_latex(block)).ok();
+            }
+            "code" => {
+                let language = block.props.get("language").and_then(Value::as_str);
+                let option = LISTINGS_LANGUAGES
+                    .iter()
+                    .find(|(id, _)| Some(*id) == language)
+                    .map(|(_, name)| format!("[language={{{}}}]", name))
+                    .unwrap_or_default();
+                writeln!(
+                    out,
+                    "\\begin{{lstlisting}}{}\n{}\n\\end{{lstlisting}}\n",
+                    option,
+                    text.trim_end_matches('\n')
+                )
+                .ok();
+            }
+            // Numbering is left to LaTeX, which honours `\notag`
+            "math" => {
+                writeln!(out, "\\begin{{equation}}\n{}\n\\end{{equation}}\n", text.trim()).ok();
+            }
+            "chemistry" => match chem::parse(text) {
+                Ok(reaction) => {
+                    writeln!(
+                        out,
+                        "\\begin{{equation*}}\n{}\n\\end{{equation*}}\n",
+                        math_latex(&reaction_nodes(&reaction))
+                    )
+                    .ok();
+                }
+                Err(e) => out.push_str(&source_error(text, &e)),
+            },
+            "citation" => match Citation::parse(text) {
+                Ok(citation) => {
+                    if !assets.entries.iter().any(|(key, _)| *key == citation.key) {
+                        assets.entries.push((citation.key.clone(), text.trim().to_string()));
+                    }
+                    cites.push(citation.key);
+                    continue;
+                }
+                Err(e) => {
+                    flush_cites(&mut out, &mut cites, after_paragraph);
+                    out.push_str(&source_error(text, &e));
+                }
+            },
+            "chart" => out.push_str(&chart_latex(block, blocks, assets)),
+            "table" => out.push_str(&table_latex(&TableData::from_props(&block.props))),
+            "image" => out.push_str(&image_latex(block, images, assets)),
+            "quote" => {
+                writeln!(out, "\\begin{{quote}}\n{}\n\\end{{quote}}\n", inline_latex(block)).ok();
+            }
+            "callout" => {
+                let style: CalloutStyle = block
+                    .props
+                    .get("style")
+                    .cloned()
+                    .and_then(|style| serde_json::from_value(style).ok())
+                    .unwrap_or_default();
+                let label = match style {
+                    CalloutStyle::Info => "Note",
+                    CalloutStyle::Warning => "Warning",
+                    CalloutStyle::Danger => "Caution",
+                };
+                writeln!(
+                    out,
+                    "\\begin{{quote}}\n\\textbf{{{}:}} {}\n\\end{{quote}}\n",
+                    label,
+                    inline_latex(block)
+                )
+                .ok();
+            }
+            "divider" => out.push_str("\\noindent\\rule{\\linewidth}{0.4pt}\n\n"),
+            "text" if text.trim().is_empty() => {}
+            "text" => {
+                writeln!(out, "{}\n", inline_latex(block)).ok();
+                paragraph = true;
+            }
+            _ => match plugins.export(block, ExportFormat::Latex) {
+                Some(latex) => {
+                    writeln!(out, "{}\n", latex.trim_end()).ok();
+                }
+                None if text.trim().is_empty() => {}
+                None => {
+                    writeln!(out, "{}\n", escape_lines(text)).ok();
+                    paragraph = true;
+                }
+            },
+        }
+        after_paragraph = paragraph;
+    }
+    flush_cites(&mut out, &mut cites, after_paragraph);
+    close_list(&mut out, &mut list);
+    out
+}
+
+/// Cite the collected keys, at the end of the paragraph before them if
+/// there is one
+fn flush_cites(out: &mut String, cites: &mut Vec<String>, after_paragraph: bool) {
+    if cites.is_empty() {
+        return;
+    }
+    let cite = format!("\\cite{{{}}}", cites.join(","));
+    cites.clear();
+    if after_paragraph {
+        out.truncate(out.trim_end().len());
+        write!(out, "~{}\n\n", cite).ok();
+    } else {
+        write!(out, "{}\n\n", cite).ok();
+    }
+}
+
+fn close_list(out: &mut String, list: &mut Option<(&str, Option<Uuid>)>) {
+    match list.take() {
+        Some(("numbered", _)) => out.push_str("\\end{enumerate}\n\n"),
+        Some(_) => out.push_str("\\end{itemize}\n\n"),
+        None => {}
+    }
+}
+
+/// Escape characters LaTeX treats specially
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape text, keeping its line breaks
+fn escape_lines(text: &str) -> String {
+    escape(text).replace('\n', "\\\\\n")
+}
+
+/// Text with its inline formatting as LaTeX. Links other than web and
+/// mail addresses are written as plain text.
+fn inline_latex(block: &StoredBlock) -> String {
+    let marks = Marks::from_props(&block.props, &block.content);
+    let mut out = String::new();
+    for span in marks.spans() {
+        let mut latex = escape_lines(&span.content);
+        if span.code {
+            latex = format!("\\texttt{{{}}}", latex);
+        }
+        if span.strikethrough {
+            latex = format!("\\sout{{{}}}", latex);
+        }
+        if span.italic {
+            latex = format!("\\emph{{{}}}", latex);
+        }
+        if span.bold {
+            latex = format!("\\textbf{{{}}}", latex);
+        }
+        if let Some(color) = &span.color
+            && let Ok(color) = u32::from_str_radix(color.trim_start_matches('#'), 16)
+        {
+            latex = format!("\\textcolor[HTML]{{{:06X}}}{{{}}}", print_color(color), latex);
+        }
+        if let Some(url) = &span.link
+            && ["http://", "https://", "mailto:"]
+                .iter()
+                .any(|scheme| url.to_lowercase().starts_with(scheme))
+        {
+            latex = format!("\\href{{{}}}{{{}}}", escape_url(url), latex);
+        }
+        out.push_str(&latex);
+    }
+    out
+}
+
+/// Escape the characters that end or break a `\href` target
+fn escape_url(url: &str) -> String {
+    let mut out = String::with_capacity(url.len());
+    for c in url.chars() {
+        if matches!(c, '\\' | '{' | '}' | '#' | '%') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Source that failed to parse, kept as a comment so the document still
+/// compiles
+fn source_error(source: &str, error: &str) -> String {
+    let mut out = format!("% {}\n", error);
+    for line in source.trim().lines() {
+        writeln!(out, "% {}", line).ok();
+    }
+    out.push('\n');
+    out
+}
+
+/// Formulas built by the exporter, such as reactions, written as LaTeX
+/// math
+fn math_latex(nodes: &[MathNode]) -> String {
+    nodes.iter().map(node_latex).collect::<Vec<_>>().join(" ")
+}
+
+fn node_latex(node: &MathNode) -> String {
+    match node {
+        MathNode::Ident(s) | MathNode::Number(s) | MathNode::Operator(s) | MathNode::Symbol(s) => {
+            symbol_latex(s)
+        }
+        MathNode::Text(s) => format!("\\mathrm{{{}}}", escape(&s.replace('−', "-"))),
+        MathNode::Bold(nodes) => format!("\\mathbf{{{}}}", math_latex(nodes)),
+        MathNode::Group(nodes) => format!("{{{}}}", math_latex(nodes)),
+        MathNode::Scripts { base, sub, sup } => {
+            let mut out = node_latex(base);
+            if let Some(sub) = sub {
+                write!(out, "_{{{}}}", math_latex(sub)).ok();
+            }
+            if let Some(sup) = sup {
+                write!(out, "^{{{}}}", math_latex(sup)).ok();
+            }
+            out
+        }
+        MathNode::Frac(numerator, denominator) => {
+            format!("\\frac{{{}}}{{{}}}", math_latex(numerator), math_latex(denominator))
+        }
+        MathNode::Sqrt { index, radicand } => match index {
+            Some(index) => format!("\\sqrt[{}]{{{}}}", math_latex(index), math_latex(radicand)),
+            None => format!("\\sqrt{{{}}}", math_latex(radicand)),
+        },
+        MathNode::Space(em) => format!("\\hspace{{{}em}}", em),
+        MathNode::Unknown(s) => s.clone(),
+    }
+}
+
+/// Commands for symbols pdfLaTeX can't take as Unicode in math
+fn symbol_latex(symbol: &str) -> String {
+    match symbol {
+        "→" => "\\rightarrow",
+        "←" => "\\leftarrow",
+        "↔" => "\\leftrightarrow",
+        "⇌" => "\\rightleftharpoons",
+        "·" => "\\cdot",
+        "−" => "-",
+        "±" => "\\pm",
+        "×" => "\\times",
+        "{" => "\\{",
+        "}" => "\\}",
+        symbol => symbol,
+    }
+    .to_string()
+}
+
+fn table_latex(table: &TableData) -> String {
+    if table.columns.is_empty() {
+        return String::new();
+    }
+    let cell = |value: &str| escape(&value.split_whitespace().collect::<Vec<_>>().join(" "));
+    let alignment: String = table
+        .columns
+        .iter()
+        .map(|column| match column.kind {
+            ColumnType::Number => 'r',
+            _ => 'l',
+        })
+        .collect();
+
+    let mut out = format!(
+        "\\begin{{table}}[htbp]\n\\centering\n\\begin{{tabular}}{{{}}}\n\\toprule\n",
+        alignment
+    );
+    let header: Vec<String> = table.columns.iter().map(|column| cell(&column.name)).collect();
+    writeln!(out, "{} \\\\\n\\midrule", header.join(" & ")).ok();
+    for row in &table.rows {
+        let cells: Vec<String> = (0..table.columns.len())
+            .map(|i| cell(row.get(i).map_or("", String::as_str)))
+            .collect();
+        writeln!(out, "{} \\\\", cells.join(" & ")).ok();
+    }
+    out.push_str("\\bottomrule\n\\end{tabular}\n\\end{table}\n\n");
+    out
+}
+
+fn image_latex(block: &StoredBlock, images: &AttachmentStore, assets: &mut Assets) -> String {
+    let file = block.attachment().unwrap_or_default();
+    let Some((name, bytes)) = std::fs::read(images.path(file))
+        .ok()
+        .and_then(|bytes| printable_image(file, bytes))
+    else {
+        return format!("% Image could not be read: {}\n\n", file);
+    };
+    let width = block.props.get("width").and_then(Value::as_u64).unwrap_or(100).min(100);
+    let mut out = format!(
+        "\\begin{{figure}}[htbp]\n\\centering\n\\includegraphics[width={:.2}\\linewidth]{{{}/{}}}\n",
+        width as f64 / 100.,
+        IMAGE_DIR,
+        name
+    );
+    let caption = block.content.trim();
+    if !caption.is_empty() {
+        writeln!(out, "\\caption{{{}}}", escape(&caption.replace('\n', " "))).ok();
+    }
+    out.push_str("\\end{figure}\n\n");
+    assets.images.push((name, bytes));
+    out
+}
+
+/// An image in a format pdfLaTeX includes, with its file name. Vector
+/// images are rasterized to PNG.
+fn printable_image(file: &str, bytes: Vec<u8>) -> Option<(String, Vec<u8>)> {
+    let extension = Path::new(file)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "pdf" => Some((file.to_string(), bytes)),
+        "svg" => {
+            let mut options = usvg::Options::default();
+            options.fontdb_mut().load_system_fonts();
+            let tree = usvg::Tree::from_data(&bytes, &options).ok()?;
+            let size = tree.size().to_int_size();
+            let mut pixmap = tiny_skia::Pixmap::new(size.width() * 2, size.height() * 2)?;
+            resvg::render(
+                &tree,
+                tiny_skia::Transform::from_scale(2., 2.),
+                &mut pixmap.as_mut(),
+            );
+            let name = Path::new(file).with_extension("png");
+            Some((name.to_string_lossy().into_owned(), pixmap.encode_png().ok()?))
+        }
+        _ => None,
+    }
+}
+
+fn chart_latex(block: &StoredBlock, blocks: &[StoredBlock], assets: &mut Assets) -> String {
+    let spec: ChartSpec = serde_json::from_value(block.props.clone()).unwrap_or_default();
+    let data = match spec.source {
+        Some(source) => blocks
+            .iter()
+            .find(|b| b.id == source && b.kind == "table")
+            .map(|table| TableData::from_props(&table.props)),
+        None => ChartBlock::parse_inline(&block.content),
+    };
+    let png = data
+        .ok_or_else(|| "No data to plot".to_string())
+        .and_then(|data| Plot::new(&spec, &data))
+        .and_then(|plot| plot.to_png().map_err(|e| e.to_string()));
+    match png {
+        Ok(png) => {
+            let name = format!("chart-{}.png", block.id);
+            let out = format!(
+                "\\begin{{figure}}[htbp]\n\\centering\n\\includegraphics[width=0.8\\linewidth]{{{}/{}}}\n\\end{{figure}}\n\n",
+                IMAGE_DIR, name
+            );
+            assets.images.push((name, png));
+            out
+        }
+        Err(e) => format!("% Chart: {}\n\n", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{plugins, sample, scratch_dir};
+
+    #[test]
+    fn writes_a_folder_with_images_and_citations() {
+        let dir = scratch_dir();
+        let (mut document, images) = sample(&dir);
+        document.blocks.push(StoredBlock::new(
+            "citation",
+            "@book{knuth1984, title = {The TeXbook}, author = {Donald Knuth}, year = {1984}}",
+        ));
+        let path = write_latex(&document, &dir.join("out"), &images, &plugins()).unwrap();
+        let folder = dir.join("out");
+        assert_eq!(path, folder.join("out.tex"));
+
+        let tex = std::fs::read_to_string(&path).unwrap();
+        assert!(tex.contains("\\title{Results}"));
+        assert!(tex.contains("\\author{Ada}"));
+        assert!(tex.contains("Results}"));
+        assert!(tex.contains("\\includegraphics"));
+        assert!(tex.contains("PLUGIN:Latex"));
+        assert!(tex.contains("\\bibliography{out}"));
+        assert!(tex.trim_end().ends_with("\\end{document}"));
+
+        let bib = std::fs::read_to_string(folder.join("out.bib")).unwrap();
+        assert!(bib.contains("knuth1984"));
+        assert_eq!(std::fs::read_dir(folder.join(IMAGE_DIR)).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(escape("50% & $5"), "50\\% \\& \\$5");
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use love_note_plugin_api::ExportFormat;
+
+use super::PluginExports;
+use crate::markdown::to_markdown;
+use crate::storage::{AttachmentStore, Document, StoredBlock};
+
+/// Folder the images of an exported document are copied into
+const IMAGE_DIR: &str = "images";
+
+/// Export a document as Markdown to `path`.
+///
+/// A document with images is written as a folder named after `path`,
+/// holding the Markdown file and an `images` folder it links to. Returns
+/// the path of the Markdown file.
+pub fn write_markdown(
+    document: &Document,
+    path: &Path,
+    images: &AttachmentStore,
+    plugins: &PluginExports,
+) -> Result<PathBuf> {
+    let files: Vec<&str> = document
+        .blocks
+        .iter()
+        .filter(|block| block.kind == "image")
+        .filter_map(StoredBlock::attachment)
+        .collect();
+
+    let mut path = path.with_extension("md");
+    if !files.is_empty() {
+        let folder = path.with_extension("");
+        let image_dir = folder.join(IMAGE_DIR);
+        std::fs::create_dir_all(&image_dir)
+            .with_context(|| format!("Failed to create {}", image_dir.display()))?;
+        for file in &files {
+            std::fs::copy(images.path(file), image_dir.join(file))
+                .with_context(|| format!("Failed to copy image {}", file))?;
+        }
+        path = folder.join(path.file_name().unwrap_or_default());
+    }
+
+    let text = to_markdown(
+        &document.blocks,
+        |file| format!("{}/{}", IMAGE_DIR, file),
+        |block| plugins.export(block, ExportFormat::Markdown),
+    );
+    std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{plugins, sample, scratch_dir, PNG};
+
+    #[test]
+    fn writes_a_folder_with_the_images() {
+        let dir = scratch_dir();
+        let (document, images) = sample(&dir);
+        let path = write_markdown(&document, &dir.join("out.txt"), &images, &plugins()).unwrap();
+        assert_eq!(path, dir.join("out").join("out.md"));
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# Results\n\nWater boils at 100 °C\n\n- First point\n"));
+        assert!(text.contains("```python\nprint('hi')\n```"));
+        assert!(text.contains("PLUGIN:Markdown"));
+
+        let file = document.blocks[6].attachment().unwrap();
+        assert!(text.contains(&format!("![A red dot](images/{})", file)));
+        assert_eq!(std::fs::read(dir.join("out").join("images").join(file)).unwrap(), PNG);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn writes_a_single_file_without_images() {
+        let dir = scratch_dir();
+        let (mut document, images) = sample(&dir);
+        document.blocks.retain(|block| block.kind != "image");
+        let path = write_markdown(&document, &dir.join("out"), &images, &plugins()).unwrap();
+        assert_eq!(path, dir.join("out.md"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
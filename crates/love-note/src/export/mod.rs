@@ -0,0 +1,193 @@
+/// Chemistry written as math, so exporters can typeset reactions with
+/// the same code as formulas.
+mod formula;
+/// Exporting documents as a single self-contained HTML page.
+///
+/// Styles are inlined and images embedded as data URLs, so the page can
+/// be sent on its own. It follows the reader's light or dark preference,
+/// with a button to switch, and code is highlighted for both themes.
+/// Math and chemistry are written as MathML, which browsers typeset
+/// natively.
+mod html;
+mod jupyter;
+/// Exporting documents as LaTeX for drafting papers.
+///
+/// The first heading becomes the title and the others sections. Math
+/// blocks are already LaTeX and go into equation environments as they
+/// are; tables become `tabular` and images figures. Citation blocks are
+/// cited where they stand and collected into a BibTeX file that the
+/// bibliography at the end reads.
+mod latex;
+mod markdown;
+/// Exporting documents as print-ready PDF files.
+///
+/// Pages are laid out here rather than printed from the editor, so the
+/// export works offline and without a window. Headings become the PDF
+/// outline, math and chemistry are typeset as text, and every page gets
+/// a running header with the title and author and a footer with the
+/// export time and page number.
+mod pdf;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use love_note_plugin_api::{ExportFormat, ExportHook};
+use serde_json::{json, Value};
+
+use crate::block::TEXT_COLORS;
+use crate::storage::{Document, StoredBlock};
+
+pub use html::write_html;
+pub use jupyter::write_notebook;
+pub use latex::write_latex;
+pub use markdown::write_markdown;
+pub use pdf::{write_pdf, Margins, PageSize, PdfOptions};
+
+/// Export hooks registered for plugin block kinds
+#[derive(Clone, Default)]
+pub struct PluginExports {
+    hooks: HashMap<String, Arc<dyn ExportHook>>,
+}
+
+impl PluginExports {
+    pub fn register(&mut self, kind: impl Into<String>, hook: Arc<dyn ExportHook>) {
+        self.hooks.insert(kind.into(), hook);
+    }
+
+    /// A plugin's rendering of `block`, if the block's kind has a hook
+    /// that supports `format`
+    pub fn export(&self, block: &StoredBlock, format: ExportFormat) -> Option<String> {
+        let hook = self.hooks.get(&block.kind)?;
+        let block = love_note_plugin_api::Block {
+            id: block.id,
+            kind: block.kind.clone(),
+            content: json!({ "text": block.content, "props": block.props }),
+        };
+        hook.export(&block, format)
+    }
+}
+
+/// The author from the document's metadata, or the user's login name
+fn author(document: &Document) -> Option<String> {
+    document
+        .metadata
+        .get("author")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .filter(|author| !author.trim().is_empty())
+}
+
+/// Darker versions of the text colors, which are chosen for a dark
+/// background, in the same order
+const PRINT_COLORS: &[u32] = &[0xd20f39, 0xfe640b, 0xdf8e1d, 0x40a02b, 0x1e66f5, 0x8839ef];
+
+/// The print version of an editor color
+fn print_color(color: u32) -> u32 {
+    TEXT_COLORS
+        .iter()
+        .position(|c| *c == color)
+        .map_or(color, |i| PRINT_COLORS[i])
+}
+
+/// Media type of an image, from its file extension
+fn media_type(file: &str) -> &'static str {
+    let extension = Path::new(file)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        _ => "image/png",
+    }
+}
+
+/// A file name for a document titled `title`
+pub fn file_name(title: &str, extension: &str) -> String {
+    let stem: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || " -_".contains(c) { c } else { '_' })
+        .collect();
+    let stem = stem.trim();
+    let stem = if stem.is_empty() { "Untitled" } else { stem };
+    format!("{}.{}", stem, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::storage::AttachmentStore;
+
+    /// A tiny red PNG
+    pub(super) const PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0x00, 0x00, 0x90,
+        0x77, 0x53, 0xde, 0x00, 0x00, 0x00, 0x0c, 0x49, 0x44, 0x41, 0x54, 0x08, 0xd7, 0x63, 0xf8,
+        0xcf, 0xc0, 0x00, 0x00, 0x03, 0x01, 0x01, 0x00, 0x18, 0xdd, 0x8d, 0xb0, 0x00, 0x00, 0x00,
+        0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    /// An empty directory of its own under the system temp directory
+    pub(super) fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lovenote-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A document with a block of most kinds, and the store holding its
+    /// image
+    pub(super) fn sample(dir: &Path) -> (Document, AttachmentStore) {
+        let images = AttachmentStore::open(dir.join("store")).unwrap();
+        let file = images.import_bytes(PNG, "png").unwrap();
+
+        let mut document = Document::new("Sample");
+        document.metadata.insert("author".to_string(), json!("Ada"));
+        document.blocks = vec![
+            StoredBlock::new("heading", "Results").with_props(json!({ "level": 1 })),
+            StoredBlock::new("text", "Water boils at 100 °C"),
+            StoredBlock::new("bullet", "First point"),
+            StoredBlock::new("code", "print('hi')").with_props(json!({ "language": "python" })),
+            StoredBlock::new("math", r"\frac{a}{b}"),
+            StoredBlock::new("chemistry", "2H2 + O2 -> 2H2O"),
+            StoredBlock::new("image", "A red dot").with_props(json!({ "file": file })),
+            StoredBlock::new("plugin", "raw"),
+        ];
+        (document, images)
+    }
+
+    /// Export hooks writing plugin blocks as `PLUGIN:<format>`
+    pub(super) fn plugins() -> PluginExports {
+        struct Hook;
+        impl ExportHook for Hook {
+            fn export(&self, _: &love_note_plugin_api::Block, format: ExportFormat) -> Option<String> {
+                Some(format!("PLUGIN:{:?}", format))
+            }
+        }
+        let mut plugins = PluginExports::default();
+        plugins.register("plugin", Arc::new(Hook));
+        plugins
+    }
+
+    #[test]
+    fn names_files_after_titles() {
+        assert_eq!(file_name("Notes: a/b", "md"), "Notes_ a_b.md");
+        assert_eq!(file_name("  ", "pdf"), "Untitled.pdf");
+    }
+
+    #[test]
+    fn maps_colors_for_print() {
+        assert_eq!(print_color(TEXT_COLORS[0]), PRINT_COLORS[0]);
+        assert_eq!(print_color(0x123456), 0x123456);
+    }
+}
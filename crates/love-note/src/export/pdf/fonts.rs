@@ -0,0 +1,525 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+
+use anyhow::{bail, Result};
+use resvg::usvg::fontdb::{self, Database, Family, Query, Stretch, Style, Weight};
+use ttf_parser::{name_id, GlyphId, RawFace, Tag};
+
+use super::writer::{ObjectId, PdfWriter};
+use crate::block::MONO_FONT;
+
+const SANS_FAMILIES: &[Family] = &[
+    Family::Name("DejaVu Sans"),
+    Family::Name("Noto Sans"),
+    Family::Name("Liberation Sans"),
+    Family::Name("Arial"),
+    Family::Name("Helvetica"),
+    Family::SansSerif,
+];
+
+const MONO_FAMILIES: &[Family] = &[
+    Family::Name(MONO_FONT),
+    Family::Name("DejaVu Sans Mono"),
+    Family::Name("Liberation Mono"),
+    Family::Name("Noto Sans Mono"),
+    Family::Name("Courier New"),
+    Family::Monospace,
+];
+
+/// Tables kept in embedded fonts. Character maps, names and glyph names
+/// are not needed to draw glyphs by id.
+const KEPT_TABLES: &[&[u8; 4]] = &[
+    b"head", b"hhea", b"maxp", b"hmtx", b"loca", b"glyf", b"cvt ", b"fpgm", b"prep", b"OS/2",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(super) enum FontStyle {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+}
+
+impl FontStyle {
+    pub(super) fn new(bold: bool, italic: bool) -> Self {
+        match (bold, italic) {
+            (false, false) => FontStyle::Regular,
+            (true, false) => FontStyle::Bold,
+            (false, true) => FontStyle::Italic,
+            (true, true) => FontStyle::BoldItalic,
+        }
+    }
+}
+
+/// A glyph to draw: the font it comes from, its id in that font and its
+/// advance width in em
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Glyph {
+    pub(super) font: usize,
+    pub(super) id: u16,
+    pub(super) advance: f32,
+}
+
+/// A loaded font file and the glyphs used from it
+struct Face {
+    data: Vec<u8>,
+    index: u32,
+    name: String,
+    units_per_em: f32,
+    /// Used glyphs and the character each stands for
+    used: BTreeMap<u16, char>,
+}
+
+impl Face {
+    /// Load a face with TrueType outlines; others cannot be embedded as
+    /// `FontFile2`
+    fn parse(data: &[u8], index: u32) -> Option<Self> {
+        let face = ttf_parser::Face::parse(data, index).ok()?;
+        face.tables().glyf?;
+        let name = face
+            .names()
+            .into_iter()
+            .filter(|name| name.name_id == name_id::POST_SCRIPT_NAME)
+            .find_map(|name| name.to_string())
+            .unwrap_or_else(|| "Font".to_string());
+        Some(Self {
+            data: data.to_vec(),
+            index,
+            name: name.chars().filter(char::is_ascii_alphanumeric).collect(),
+            units_per_em: f32::from(face.units_per_em()),
+            used: BTreeMap::new(),
+        })
+    }
+
+    fn glyph(&self, font: usize, c: char) -> Option<Glyph> {
+        let face = ttf_parser::Face::parse(&self.data, self.index).ok()?;
+        let id = face.glyph_index(c)?;
+        Some(Glyph {
+            font,
+            id: id.0,
+            advance: self.advance(&face, id),
+        })
+    }
+
+    /// The box drawn for characters no font has
+    fn missing_glyph(&self, font: usize) -> Glyph {
+        let advance = ttf_parser::Face::parse(&self.data, self.index)
+            .map(|face| self.advance(&face, GlyphId(0)))
+            .unwrap_or(0.5);
+        Glyph {
+            font,
+            id: 0,
+            advance,
+        }
+    }
+
+    fn advance(&self, face: &ttf_parser::Face, id: GlyphId) -> f32 {
+        f32::from(face.glyph_hor_advance(id).unwrap_or(0)) / self.units_per_em
+    }
+
+    /// Scale font units to the 1000-unit glyph space of PDF fonts
+    fn scale(&self, value: impl Into<f32>) -> i32 {
+        (value.into() * 1000. / self.units_per_em).round() as i32
+    }
+}
+
+/// The fonts text is set in, loaded as the document needs them
+pub(super) struct Fonts {
+    db: Database,
+    faces: Vec<Face>,
+    /// Face index of each style
+    styles: HashMap<FontStyle, usize>,
+    /// Database faces already loaded, with their face index
+    loaded: HashMap<fontdb::ID, usize>,
+    /// Faces loaded for characters the style fonts lack
+    fallbacks: Vec<usize>,
+    /// Characters no installed font has
+    missing: HashSet<char>,
+    glyphs: HashMap<(FontStyle, char), Glyph>,
+}
+
+impl Fonts {
+    /// Find the installed fonts for each style
+    pub(super) fn load() -> Result<Self> {
+        let mut db = Database::new();
+        db.load_system_fonts();
+        let mut fonts = Self {
+            db,
+            faces: Vec::new(),
+            styles: HashMap::new(),
+            loaded: HashMap::new(),
+            fallbacks: Vec::new(),
+            missing: HashSet::new(),
+            glyphs: HashMap::new(),
+        };
+
+        let Some(regular) = fonts.query(SANS_FAMILIES, Weight::NORMAL, Style::Normal) else {
+            bail!("No TrueType sans-serif font is installed");
+        };
+        let bold = fonts.query(SANS_FAMILIES, Weight::BOLD, Style::Normal);
+        let italic = fonts.query(SANS_FAMILIES, Weight::NORMAL, Style::Italic);
+        let bold_italic = fonts.query(SANS_FAMILIES, Weight::BOLD, Style::Italic);
+        let mono = fonts.query(MONO_FAMILIES, Weight::NORMAL, Style::Normal);
+
+        fonts.styles = HashMap::from([
+            (FontStyle::Regular, regular),
+            (FontStyle::Bold, bold.unwrap_or(regular)),
+            (FontStyle::Italic, italic.unwrap_or(regular)),
+            (FontStyle::BoldItalic, bold_italic.or(bold).unwrap_or(regular)),
+            (FontStyle::Mono, mono.unwrap_or(regular)),
+        ]);
+        Ok(fonts)
+    }
+
+    fn query(&mut self, families: &[Family], weight: Weight, style: Style) -> Option<usize> {
+        let id = self.db.query(&Query {
+            families,
+            weight,
+            stretch: Stretch::Normal,
+            style,
+        })?;
+        self.load_face(id)
+    }
+
+    fn load_face(&mut self, id: fontdb::ID) -> Option<usize> {
+        if let Some(&index) = self.loaded.get(&id) {
+            return Some(index);
+        }
+        let face = self.db.with_face_data(id, Face::parse)??;
+        self.faces.push(face);
+        self.loaded.insert(id, self.faces.len() - 1);
+        Some(self.faces.len() - 1)
+    }
+
+    /// The glyph for `c` in `style`, from another font if the style's
+    /// font lacks it, and marked as used
+    pub(super) fn glyph(&mut self, c: char, style: FontStyle) -> Glyph {
+        if let Some(glyph) = self.glyphs.get(&(style, c)) {
+            return *glyph;
+        }
+        let primary = self.styles[&style];
+        let glyph = self.faces[primary]
+            .glyph(primary, c)
+            .or_else(|| {
+                self.fallbacks
+                    .iter()
+                    .find_map(|&font| self.faces[font].glyph(font, c))
+            })
+            .or_else(|| {
+                let font = self.find_fallback(c)?;
+                self.faces[font].glyph(font, c)
+            })
+            .unwrap_or_else(|| self.faces[primary].missing_glyph(primary));
+
+        self.faces[glyph.font].used.entry(glyph.id).or_insert(c);
+        self.glyphs.insert((style, c), glyph);
+        glyph
+    }
+
+    /// Width of `text` in em
+    pub(super) fn width(&mut self, text: &str, style: FontStyle) -> f32 {
+        text.chars().map(|c| self.glyph(c, style).advance).sum()
+    }
+
+    /// Load an installed font that has `c`
+    fn find_fallback(&mut self, c: char) -> Option<usize> {
+        if self.missing.contains(&c) || c.is_control() {
+            return None;
+        }
+        let candidates: Vec<fontdb::ID> = self
+            .db
+            .faces()
+            .map(|face| face.id)
+            .filter(|id| !self.loaded.contains_key(id))
+            .collect();
+        for id in candidates {
+            let covers = self.db.with_face_data(id, |data, index| {
+                ttf_parser::Face::parse(data, index)
+                    .is_ok_and(|face| face.tables().glyf.is_some() && face.glyph_index(c).is_some())
+            });
+            if covers == Some(true)
+                && let Some(font) = self.load_face(id)
+            {
+                self.fallbacks.push(font);
+                return Some(font);
+            }
+        }
+        self.missing.insert(c);
+        None
+    }
+
+    /// Write the used fonts and return the font resource entries naming
+    /// them `/F0`, `/F1`, ... by face index
+    pub(super) fn embed(&self, writer: &mut PdfWriter) -> Result<String> {
+        let mut resources = String::new();
+        for (index, face) in self.faces.iter().enumerate() {
+            if face.used.is_empty() {
+                continue;
+            }
+            let font = embed_face(face, writer)?;
+            write!(resources, "/F{} {} 0 R ", index, font)?;
+        }
+        Ok(resources)
+    }
+}
+
+/// Glyph ids in a text-showing string
+pub(super) fn glyph_string(glyphs: &[Glyph]) -> String {
+    let mut out = String::from("<");
+    for glyph in glyphs {
+        write!(out, "{:04X}", glyph.id).ok();
+    }
+    out.push('>');
+    out
+}
+
+fn embed_face(face: &Face, writer: &mut PdfWriter) -> Result<ObjectId> {
+    let Ok(parsed) = ttf_parser::Face::parse(&face.data, face.index) else {
+        bail!("Failed to read font {}", face.name);
+    };
+    let Some(program) = subset(&face.data, face.index, &face.used) else {
+        bail!("Failed to embed font {}", face.name);
+    };
+
+    let file = writer.reserve();
+    writer.stream(file, &format!("/Length1 {}", program.len()), &program);
+
+    let bbox = parsed.global_bounding_box();
+    let mut flags = 4;
+    if parsed.is_monospaced() {
+        flags |= 1;
+    }
+    if parsed.is_italic() {
+        flags |= 64;
+    }
+    let cap_height = parsed.capital_height().unwrap_or(parsed.ascender());
+    let descriptor = writer.reserve();
+    writer.object(
+        descriptor,
+        &format!(
+            "<< /Type /FontDescriptor /FontName /{} /Flags {} /FontBBox [{} {} {} {}] \
+             /ItalicAngle {} /Ascent {} /Descent {} /CapHeight {} /StemV 80 /FontFile2 {} 0 R >>",
+            face.name,
+            flags,
+            face.scale(bbox.x_min),
+            face.scale(bbox.y_min),
+            face.scale(bbox.x_max),
+            face.scale(bbox.y_max),
+            parsed.italic_angle().round(),
+            face.scale(parsed.ascender()),
+            face.scale(parsed.descender()),
+            face.scale(cap_height),
+            file,
+        ),
+    );
+
+    let mut widths = String::new();
+    for &id in face.used.keys() {
+        let advance = parsed.glyph_hor_advance(GlyphId(id)).unwrap_or(0);
+        write!(widths, "{} [{}] ", id, face.scale(advance))?;
+    }
+    let cid_font = writer.reserve();
+    writer.object(
+        cid_font,
+        &format!(
+            "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{} \
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+             /FontDescriptor {} 0 R /CIDToGIDMap /Identity /W [{}] >>",
+            face.name, descriptor, widths
+        ),
+    );
+
+    let to_unicode = writer.reserve();
+    writer.stream(to_unicode, "", to_unicode_cmap(&face.used).as_bytes());
+
+    let font = writer.reserve();
+    writer.object(
+        font,
+        &format!(
+            "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H \
+             /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+            face.name, cid_font, to_unicode
+        ),
+    );
+    Ok(font)
+}
+
+/// The character map that lets readers turn glyph ids back into text
+fn to_unicode_cmap(used: &BTreeMap<u16, char>) -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let entries: Vec<_> = used.iter().filter(|(id, _)| **id != 0).collect();
+    // A section may hold at most 100 entries
+    for chunk in entries.chunks(100) {
+        writeln!(cmap, "{} beginbfchar", chunk.len()).ok();
+        for (id, c) in chunk {
+            let mut units = [0; 2];
+            let text: String = c
+                .encode_utf16(&mut units)
+                .iter()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            writeln!(cmap, "<{:04X}> <{}>", id, text).ok();
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CIDFont defineresource pop\nend\nend\n");
+    cmap
+}
+
+/// A standalone font file holding the outlines of the used glyphs, and
+/// of the glyphs composite ones are built from. Other glyphs are left
+/// empty so glyph ids stay the same.
+fn subset(data: &[u8], index: u32, used: &BTreeMap<u16, char>) -> Option<Vec<u8>> {
+    let raw = RawFace::parse(data, index).ok()?;
+    let head = raw.table(Tag::from_bytes(b"head"))?;
+    let loca = raw.table(Tag::from_bytes(b"loca"))?;
+    let glyf = raw.table(Tag::from_bytes(b"glyf"))?;
+    let long_offsets = read_u16(head, 50)? == 1;
+
+    let glyph_count = loca.len() / if long_offsets { 4 } else { 2 } - 1;
+    let glyph_range = |id: usize| -> Option<std::ops::Range<usize>> {
+        let (start, end) = if long_offsets {
+            (read_u32(loca, id * 4)? as usize, read_u32(loca, id * 4 + 4)? as usize)
+        } else {
+            (read_u16(loca, id * 2)? as usize * 2, read_u16(loca, id * 2 + 2)? as usize * 2)
+        };
+        (start <= end && end <= glyf.len()).then_some(start..end)
+    };
+
+    // Used glyphs and the components of composite glyphs
+    let mut keep: HashSet<usize> = used.keys().map(|&id| usize::from(id)).collect();
+    keep.insert(0);
+    let mut pending: Vec<usize> = keep.iter().copied().collect();
+    while let Some(id) = pending.pop() {
+        let Some(range) = glyph_range(id) else {
+            continue;
+        };
+        for component in components(&glyf[range]) {
+            if keep.insert(component) {
+                pending.push(component);
+            }
+        }
+    }
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity((glyph_count + 1) * 4);
+    for id in 0..glyph_count {
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        if keep.contains(&id)
+            && let Some(range) = glyph_range(id)
+        {
+            new_glyf.extend_from_slice(&glyf[range]);
+            while new_glyf.len() % 4 != 0 {
+                new_glyf.push(0);
+            }
+        }
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+    let mut new_head = head.to_vec();
+    // Clear the whole-file checksum adjustment and switch to long offsets
+    new_head.get_mut(8..12)?.fill(0);
+    new_head.get_mut(50..52)?.copy_from_slice(&1u16.to_be_bytes());
+
+    let mut tables = Vec::new();
+    for tag in KEPT_TABLES {
+        let table = match *tag {
+            b"head" => new_head.clone(),
+            b"loca" => new_loca.clone(),
+            b"glyf" => new_glyf.clone(),
+            _ => match raw.table(Tag::from_bytes(tag)) {
+                Some(table) => table.to_vec(),
+                None => continue,
+            },
+        };
+        tables.push((**tag, table));
+    }
+    Some(font_file(tables))
+}
+
+/// Glyph ids a composite glyph is built from
+fn components(glyph: &[u8]) -> Vec<usize> {
+    const ARGS_ARE_WORDS: u16 = 0x1;
+    const HAVE_SCALE: u16 = 0x8;
+    const MORE_COMPONENTS: u16 = 0x20;
+    const HAVE_X_AND_Y_SCALE: u16 = 0x40;
+    const HAVE_TWO_BY_TWO: u16 = 0x80;
+
+    let mut ids = Vec::new();
+    let Some(contours) = read_u16(glyph, 0) else {
+        return ids;
+    };
+    // Simple glyphs have a non-negative contour count
+    if (contours as i16) >= 0 {
+        return ids;
+    }
+    let mut offset = 10;
+    while let (Some(flags), Some(id)) = (read_u16(glyph, offset), read_u16(glyph, offset + 2)) {
+        ids.push(usize::from(id));
+        offset += 4;
+        offset += if flags & ARGS_ARE_WORDS != 0 { 4 } else { 2 };
+        if flags & HAVE_SCALE != 0 {
+            offset += 2;
+        } else if flags & HAVE_X_AND_Y_SCALE != 0 {
+            offset += 4;
+        } else if flags & HAVE_TWO_BY_TWO != 0 {
+            offset += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    ids
+}
+
+/// A TrueType file holding `tables`
+fn font_file(tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    let count = tables.len() as u16;
+    let entry_selector = 15 - count.max(1).leading_zeros() as u16;
+    let search_range = 16u16 << entry_selector;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    out.extend_from_slice(&count.to_be_bytes());
+    out.extend_from_slice(&search_range.to_be_bytes());
+    out.extend_from_slice(&entry_selector.to_be_bytes());
+    out.extend_from_slice(&(count * 16 - search_range).to_be_bytes());
+
+    let mut offset = 12 + 16 * tables.len();
+    let mut body = Vec::new();
+    for (tag, table) in &tables {
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&checksum(table).to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        body.extend_from_slice(table);
+        while body.len() % 4 != 0 {
+            body.push(0);
+        }
+        offset = 12 + 16 * tables.len() + body.len();
+    }
+    out.extend_from_slice(&body);
+    out
+}
+
+fn checksum(table: &[u8]) -> u32 {
+    table.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
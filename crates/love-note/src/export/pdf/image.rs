@@ -0,0 +1,238 @@
+use resvg::tiny_skia::Pixmap;
+use resvg::usvg;
+
+use super::writer::{ObjectId, PdfWriter};
+
+/// Points per pixel, taking images to be at 96 dpi
+const POINTS_PER_PIXEL: f32 = 0.75;
+/// Scale vector images are rasterized at, so they stay sharp in print
+const SVG_SCALE: f32 = 2.;
+/// Largest side of a rasterized vector image, in pixels
+const MAX_SVG_PIXELS: f32 = 4096.;
+
+enum Pixels {
+    /// JPEG data, embedded as is
+    Jpeg { data: Vec<u8>, components: u8 },
+    /// 8-bit RGB samples with an optional alpha channel
+    Rgb { rgb: Vec<u8>, alpha: Option<Vec<u8>> },
+}
+
+/// An image to embed, with its size on the page when printed at its
+/// natural size
+pub(super) struct Picture {
+    width: u32,
+    height: u32,
+    /// Natural size in points
+    pub(super) size: (f32, f32),
+    pixels: Pixels,
+}
+
+impl Picture {
+    /// Decode PNG, JPEG, GIF, WebP, BMP, TIFF or SVG data
+    pub(super) fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG") {
+            let pixmap = Pixmap::decode_png(bytes).ok()?;
+            let size = (
+                pixmap.width() as f32 * POINTS_PER_PIXEL,
+                pixmap.height() as f32 * POINTS_PER_PIXEL,
+            );
+            Some(Self::from_pixmap(&pixmap, size))
+        } else if bytes.starts_with(&[0xff, 0xd8]) {
+            Self::jpeg(bytes)
+        } else if image::guess_format(bytes).is_ok() {
+            Self::raster(bytes)
+        } else {
+            Self::svg(bytes)
+        }
+    }
+
+    fn from_pixmap(pixmap: &Pixmap, size: (f32, f32)) -> Self {
+        let pixels = pixmap.pixels().iter().map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        });
+        Self::from_rgba(pixmap.width(), pixmap.height(), size, pixels)
+    }
+
+    /// Formats without a decoder of their own. Animations show their
+    /// first frame.
+    fn raster(bytes: &[u8]) -> Option<Self> {
+        let image = image::load_from_memory(bytes).ok()?.into_rgba8();
+        let (width, height) = image.dimensions();
+        let size = (
+            width as f32 * POINTS_PER_PIXEL,
+            height as f32 * POINTS_PER_PIXEL,
+        );
+        Some(Self::from_rgba(width, height, size, image.pixels().map(|pixel| pixel.0)))
+    }
+
+    fn from_rgba(
+        width: u32,
+        height: u32,
+        size: (f32, f32),
+        pixels: impl ExactSizeIterator<Item = [u8; 4]>,
+    ) -> Self {
+        let mut rgb = Vec::with_capacity(pixels.len() * 3);
+        let mut alpha = Vec::with_capacity(pixels.len());
+        for [red, green, blue, a] in pixels {
+            rgb.extend_from_slice(&[red, green, blue]);
+            alpha.push(a);
+        }
+        let opaque = alpha.iter().all(|a| *a == u8::MAX);
+        Self {
+            width,
+            height,
+            size,
+            pixels: Pixels::Rgb {
+                rgb,
+                alpha: (!opaque).then_some(alpha),
+            },
+        }
+    }
+
+    /// Read the frame header for the size; the data is embedded as is
+    fn jpeg(bytes: &[u8]) -> Option<Self> {
+        let mut offset = 2;
+        while offset + 4 <= bytes.len() {
+            if bytes[offset] != 0xff {
+                return None;
+            }
+            let marker = bytes[offset + 1];
+            let length = usize::from(u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]));
+            // Start-of-frame markers, excluding the table markers in range
+            if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+                let frame = bytes.get(offset + 4..offset + 10)?;
+                let height = u32::from(u16::from_be_bytes([frame[1], frame[2]]));
+                let width = u32::from(u16::from_be_bytes([frame[3], frame[4]]));
+                return Some(Self {
+                    width,
+                    height,
+                    size: (
+                        width as f32 * POINTS_PER_PIXEL,
+                        height as f32 * POINTS_PER_PIXEL,
+                    ),
+                    pixels: Pixels::Jpeg {
+                        data: bytes.to_vec(),
+                        components: frame[5],
+                    },
+                });
+            }
+            offset += 2 + length;
+        }
+        None
+    }
+
+    fn svg(bytes: &[u8]) -> Option<Self> {
+        let mut options = usvg::Options::default();
+        options.fontdb_mut().load_system_fonts();
+        let tree = usvg::Tree::from_data(bytes, &options).ok()?;
+        let size = tree.size();
+        let scale = SVG_SCALE.min(MAX_SVG_PIXELS / size.width().max(size.height()));
+        let mut pixmap = Pixmap::new(
+            (size.width() * scale).ceil() as u32,
+            (size.height() * scale).ceil() as u32,
+        )?;
+        resvg::render(
+            &tree,
+            resvg::tiny_skia::Transform::from_scale(scale, scale),
+            &mut pixmap.as_mut(),
+        );
+        Some(Self::from_pixmap(
+            &pixmap,
+            (
+                size.width() * POINTS_PER_PIXEL,
+                size.height() * POINTS_PER_PIXEL,
+            ),
+        ))
+    }
+
+    /// Write the image and return its object
+    pub(super) fn embed(&self, writer: &mut PdfWriter) -> ObjectId {
+        let image = writer.reserve();
+        let dict = format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /BitsPerComponent 8",
+            self.width, self.height
+        );
+        match &self.pixels {
+            Pixels::Jpeg { data, components } => {
+                let colors = match components {
+                    1 => "/ColorSpace /DeviceGray",
+                    // CMYK JPEGs are written inverted by most software
+                    4 => "/ColorSpace /DeviceCMYK /Decode [1 0 1 0 1 0 1 0]",
+                    _ => "/ColorSpace /DeviceRGB",
+                };
+                writer.raw_stream(
+                    image,
+                    &format!("{} {} /Filter /DCTDecode", dict, colors),
+                    data,
+                );
+            }
+            Pixels::Rgb { rgb, alpha } => {
+                let mut mask = String::new();
+                if let Some(alpha) = alpha {
+                    let soft_mask = writer.reserve();
+                    writer.stream(
+                        soft_mask,
+                        &format!(
+                            "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                             /BitsPerComponent 8 /ColorSpace /DeviceGray",
+                            self.width, self.height
+                        ),
+                        alpha,
+                    );
+                    mask = format!(" /SMask {} 0 R", soft_mask);
+                }
+                writer.stream(image, &format!("{} /ColorSpace /DeviceRGB{}", dict, mask), rgb);
+            }
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::export::tests::PNG;
+
+    fn encode(format: image::ImageFormat) -> Vec<u8> {
+        let pixels = image::RgbaImage::from_pixel(4, 2, image::Rgba([255, 0, 0, 255]));
+        let mut bytes = Cursor::new(Vec::new());
+        pixels.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn decodes_png() {
+        let picture = Picture::decode(PNG).unwrap();
+        assert_eq!((picture.width, picture.height), (1, 1));
+        assert_eq!(picture.size, (0.75, 0.75));
+    }
+
+    #[test]
+    fn decodes_other_raster_formats() {
+        for format in [image::ImageFormat::Gif, image::ImageFormat::WebP, image::ImageFormat::Bmp] {
+            let picture = Picture::decode(&encode(format)).unwrap();
+            assert_eq!((picture.width, picture.height), (4, 2), "{:?}", format);
+            let Pixels::Rgb { rgb, alpha } = &picture.pixels else {
+                panic!("{:?} should decode to pixels", format);
+            };
+            assert_eq!(&rgb[..3], &[255, 0, 0]);
+            assert!(alpha.is_none());
+        }
+    }
+
+    #[test]
+    fn rasterizes_svg() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="20"/>"#;
+        let picture = Picture::decode(svg).unwrap();
+        assert_eq!(picture.size, (7.5, 15.));
+        assert_eq!((picture.width, picture.height), (20, 40));
+    }
+
+    #[test]
+    fn rejects_unknown_data() {
+        assert!(Picture::decode(b"not an image").is_none());
+    }
+}
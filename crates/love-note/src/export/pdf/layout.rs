@@ -0,0 +1,890 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::block::parse::chem;
+use crate::block::parse::latex::{self, MathNode};
+use crate::block::{
+    CalloutStyle, ChartBlock, ChartSpec, Citation, ColumnType, HeadingBlock, Marks, Plot, TableData,
+};
+use crate::export::formula::reaction_nodes;
+use crate::export::print_color;
+use crate::storage::{AttachmentStore, StoredBlock};
+
+use super::fonts::{glyph_string, FontStyle, Fonts, Glyph};
+use super::image::Picture;
+use super::math::{self, MathItem};
+use super::writer::number;
+
+const BODY_SIZE: f32 = 11.;
+const CODE_SIZE: f32 = 9.;
+const TABLE_SIZE: f32 = 9.5;
+const MATH_SIZE: f32 = 13.;
+/// Line height as a multiple of the font size
+const LINE_HEIGHT: f32 = 1.45;
+/// Indent of each level of nesting
+const INDENT: f32 = 18.;
+/// Space after each block
+const BLOCK_GAP: f32 = 6.;
+/// Padding inside code blocks, callouts and table cells
+const PADDING: f32 = 6.;
+
+const TEXT: u32 = 0x1e1e2e;
+const MUTED: u32 = 0x6c6f85;
+const LINK: u32 = 0x1e66f5;
+const ERROR: u32 = 0xd20f39;
+const RULE: u32 = 0xbcc0cc;
+const SHADE: u32 = 0xeff1f5;
+
+/// `color` mixed with white, `amount` being the share of white
+fn tint(color: u32, amount: f32) -> u32 {
+    let channel = |shift: u32| {
+        let value = ((color >> shift) & 0xff) as f32;
+        ((value + (255. - value) * amount).round() as u32) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+fn rgb(color: u32) -> String {
+    let channel = |shift: u32| number(((color >> shift) & 0xff) as f32 / 255.);
+    format!("{} {} {}", channel(16), channel(8), channel(0))
+}
+
+/// A drawn page
+#[derive(Default)]
+pub(super) struct Page {
+    pub(super) content: String,
+    /// Link areas as `[left, bottom, right, top]` with their URLs
+    pub(super) links: Vec<([f32; 4], String)>,
+}
+
+/// A heading, listed in the PDF outline
+pub(super) struct Bookmark {
+    pub(super) level: u8,
+    pub(super) title: String,
+    pub(super) page: usize,
+    /// Distance of the heading from the bottom of the page
+    pub(super) top: f32,
+}
+
+/// A run of text in one style
+#[derive(Clone)]
+struct Span {
+    text: String,
+    style: FontStyle,
+    color: u32,
+    strikethrough: bool,
+    link: Option<String>,
+}
+
+impl Span {
+    fn new(text: impl Into<String>, style: FontStyle, color: u32) -> Self {
+        Self {
+            text: text.into(),
+            style,
+            color,
+            strikethrough: false,
+            link: None,
+        }
+    }
+}
+
+/// Part of a line taken from one span
+struct Fragment {
+    span: usize,
+    text: String,
+    width: f32,
+}
+
+#[derive(Default)]
+struct Line {
+    fragments: Vec<Fragment>,
+    width: f32,
+}
+
+impl Line {
+    fn push(&mut self, span: usize, c: char, width: f32) {
+        match self.fragments.last_mut() {
+            Some(last) if last.span == span => {
+                last.text.push(c);
+                last.width += width;
+            }
+            _ => self.fragments.push(Fragment {
+                span,
+                text: c.to_string(),
+                width,
+            }),
+        }
+        self.width += width;
+    }
+}
+
+/// Characters lines may break around without spaces, such as CJK
+fn breaks_anywhere(c: char) -> bool {
+    c >= '\u{2e80}'
+}
+
+pub(super) struct Layout<'a> {
+    pub(super) fonts: Fonts,
+    /// Images to embed, named `/Im0`, `/Im1`, ... by index
+    pub(super) pictures: Vec<Picture>,
+    pub(super) pages: Vec<Page>,
+    pub(super) bookmarks: Vec<Bookmark>,
+    images: &'a AttachmentStore,
+    width: f32,
+    height: f32,
+    margin: f32,
+    /// Page being drawn on
+    page: usize,
+    /// Distance of the next block from the top of the page
+    y: f32,
+}
+
+impl<'a> Layout<'a> {
+    pub(super) fn new(fonts: Fonts, images: &'a AttachmentStore, size: (f32, f32), margin: f32) -> Self {
+        Self {
+            fonts,
+            pictures: Vec::new(),
+            pages: vec![Page::default()],
+            bookmarks: Vec::new(),
+            images,
+            width: size.0,
+            height: size.1,
+            margin,
+            page: 0,
+            y: margin,
+        }
+    }
+
+    fn content_width(&self) -> f32 {
+        self.width - self.margin * 2.
+    }
+
+    fn bottom(&self) -> f32 {
+        self.height - self.margin
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Page::default());
+        self.page = self.pages.len() - 1;
+        self.y = self.margin;
+    }
+
+    /// Start a new page unless `height` fits on this one. A page that is
+    /// still empty is kept, so oversized content does not loop.
+    fn ensure(&mut self, height: f32) {
+        if self.y + height > self.bottom() && self.y > self.margin {
+            self.new_page();
+        }
+    }
+
+    fn content(&mut self) -> &mut String {
+        &mut self.pages[self.page].content
+    }
+
+    /// PDF y coordinate of a distance from the top of the page
+    fn flip(&self, y: f32) -> f32 {
+        self.height - y
+    }
+
+    // Drawing, with y measured from the top of the page
+
+    fn text(&mut self, x: f32, baseline: f32, size: f32, style: FontStyle, text: &str, color: u32) {
+        let glyphs: Vec<Glyph> = text.chars().map(|c| self.fonts.glyph(c, style)).collect();
+        let y = self.flip(baseline);
+        let mut out = format!("{} rg\n", rgb(color));
+        let mut x = x;
+        for run in glyphs.chunk_by(|a, b| a.font == b.font) {
+            writeln!(
+                out,
+                "BT /F{} {} Tf {} {} Td {} Tj ET",
+                run[0].font,
+                number(size),
+                number(x),
+                number(y),
+                glyph_string(run)
+            )
+            .ok();
+            x += run.iter().map(|glyph| glyph.advance).sum::<f32>() * size;
+        }
+        self.content().push_str(&out);
+    }
+
+    fn fill(&mut self, x: f32, y: f32, width: f32, height: f32, color: u32) {
+        let bottom = self.flip(y + height);
+        let out = format!(
+            "{} rg {} {} {} {} re f\n",
+            rgb(color),
+            number(x),
+            number(bottom),
+            number(width),
+            number(height)
+        );
+        self.content().push_str(&out);
+    }
+
+    fn stroke(&mut self, x: f32, y: f32, width: f32, height: f32, color: u32) {
+        let bottom = self.flip(y + height);
+        let out = format!(
+            "{} RG 0.5 w {} {} {} {} re S\n",
+            rgb(color),
+            number(x),
+            number(bottom),
+            number(width),
+            number(height)
+        );
+        self.content().push_str(&out);
+    }
+
+    /// Draw picture `index` with its top left corner at `(x, y)`
+    fn picture(&mut self, index: usize, x: f32, y: f32, width: f32, height: f32) {
+        let bottom = self.flip(y + height);
+        let out = format!(
+            "q {} 0 0 {} {} {} cm /Im{} Do Q\n",
+            number(width),
+            number(height),
+            number(x),
+            number(bottom),
+            index
+        );
+        self.content().push_str(&out);
+    }
+
+    fn link(&mut self, x: f32, top: f32, width: f32, height: f32, url: &str) {
+        let rect = [x, self.flip(top + height), x + width, self.flip(top)];
+        self.pages[self.page].links.push((rect, url.to_string()));
+    }
+
+    // Text
+
+    /// Break spans into lines no wider than `width`
+    fn wrap(&mut self, spans: &[Span], size: f32, width: f32) -> Vec<Line> {
+        let mut lines = vec![Line::default()];
+        let mut word = Line::default();
+        for (index, span) in spans.iter().enumerate() {
+            for c in span.text.chars() {
+                if c == '\n' {
+                    self.place_word(&mut lines, &mut word, spans, size, width);
+                    self.trim_end(lines.last_mut(), spans, size);
+                    lines.push(Line::default());
+                } else if c.is_whitespace() {
+                    self.place_word(&mut lines, &mut word, spans, size, width);
+                    let line = lines.last_mut().unwrap_or_else(|| unreachable!());
+                    // Spaces at the start of a wrapped line are dropped
+                    if !line.fragments.is_empty() {
+                        let advance = self.fonts.glyph(' ', span.style).advance * size;
+                        line.push(index, ' ', advance);
+                    }
+                } else {
+                    let advance = self.fonts.glyph(c, span.style).advance * size;
+                    if breaks_anywhere(c) {
+                        self.place_word(&mut lines, &mut word, spans, size, width);
+                        word.push(index, c, advance);
+                        self.place_word(&mut lines, &mut word, spans, size, width);
+                    } else {
+                        word.push(index, c, advance);
+                    }
+                }
+            }
+        }
+        self.place_word(&mut lines, &mut word, spans, size, width);
+        self.trim_end(lines.last_mut(), spans, size);
+        lines
+    }
+
+    /// Move a word to the last line, or to a new line if it does not
+    /// fit. Words wider than a line are broken between characters.
+    fn place_word(&mut self, lines: &mut Vec<Line>, word: &mut Line, spans: &[Span], size: f32, width: f32) {
+        if word.fragments.is_empty() {
+            return;
+        }
+        let word = std::mem::take(word);
+        if let Some(line) = lines.last_mut()
+            && !line.fragments.is_empty()
+            && line.width + word.width > width
+        {
+            self.trim_end(Some(line), spans, size);
+            lines.push(Line::default());
+        }
+
+        let fits = word.width <= width;
+        for fragment in word.fragments {
+            let style = spans[fragment.span].style;
+            for c in fragment.text.chars() {
+                let advance = self.fonts.glyph(c, style).advance * size;
+                let line = lines.last_mut().unwrap_or_else(|| unreachable!());
+                if !fits && !line.fragments.is_empty() && line.width + advance > width {
+                    lines.push(Line::default());
+                }
+                let line = lines.last_mut().unwrap_or_else(|| unreachable!());
+                line.push(fragment.span, c, advance);
+            }
+        }
+    }
+
+    fn trim_end(&mut self, line: Option<&mut Line>, spans: &[Span], size: f32) {
+        let Some(line) = line else {
+            return;
+        };
+        while let Some(last) = line.fragments.last_mut() {
+            if !last.text.ends_with(' ') {
+                break;
+            }
+            last.text.pop();
+            let advance = self.fonts.glyph(' ', spans[last.span].style).advance * size;
+            last.width -= advance;
+            line.width -= advance;
+            if last.text.is_empty() {
+                line.fragments.pop();
+            }
+        }
+    }
+
+    fn draw_line(&mut self, line: &Line, spans: &[Span], x: f32, top: f32, size: f32) {
+        let baseline = top + size * 1.1;
+        let mut x = x;
+        for fragment in &line.fragments {
+            let span = &spans[fragment.span];
+            self.text(x, baseline, size, span.style, &fragment.text, span.color);
+            if span.strikethrough {
+                self.fill(x, baseline - size * 0.3, fragment.width, size * 0.06, span.color);
+            }
+            if let Some(url) = &span.link {
+                self.fill(x, baseline + size * 0.12, fragment.width, size * 0.06, span.color);
+                self.link(x, top, fragment.width, size * LINE_HEIGHT, url);
+            }
+            x += fragment.width;
+        }
+    }
+
+    /// Lay out spans at the current position, breaking pages between
+    /// lines. `background` shades the full width behind the lines.
+    fn paragraph(&mut self, spans: &[Span], size: f32, x: f32, width: f32, background: Option<u32>) {
+        let lines = self.wrap(spans, size, width);
+        self.draw_lines(&lines, spans, size, x, width, background);
+    }
+
+    fn draw_lines(&mut self, lines: &[Line], spans: &[Span], size: f32, x: f32, width: f32, background: Option<u32>) {
+        let line_height = size * LINE_HEIGHT;
+        for line in lines {
+            self.ensure(line_height);
+            if let Some(color) = background {
+                self.fill(x - PADDING, self.y, width + PADDING * 2., line_height, color);
+            }
+            self.draw_line(line, spans, x, self.y, size);
+            self.y += line_height;
+        }
+    }
+
+    /// A paragraph in a shaded box with an optional bar on its left
+    fn boxed(&mut self, spans: &[Span], size: f32, x: f32, width: f32, background: u32, bar: Option<u32>) {
+        let inner_x = x + PADDING;
+        let inner_width = width - PADDING * 2.;
+        let lines = self.wrap(spans, size, inner_width);
+        self.ensure(PADDING + size * LINE_HEIGHT);
+
+        let start = (self.page, self.y);
+        self.fill(x, self.y, width, PADDING, background);
+        self.y += PADDING;
+        self.draw_lines(&lines, spans, size, inner_x, inner_width, Some(background));
+        self.fill(x, self.y, width, PADDING, background);
+        self.y += PADDING;
+
+        if let Some(bar) = bar {
+            // One bar piece on each page the box spans
+            let end = (self.page, self.y);
+            for page in start.0..=end.0 {
+                self.page = page;
+                let top = if page == start.0 { start.1 } else { self.margin };
+                let bottom = if page == end.0 { end.1 } else { self.bottom() };
+                self.fill(x, top, 3., bottom - top, bar);
+            }
+            self.page = end.0;
+        }
+    }
+
+    // Blocks
+
+    /// Lay out a document's blocks
+    pub(super) fn blocks(&mut self, blocks: &[StoredBlock]) {
+        let mut depths: HashMap<Uuid, usize> = HashMap::new();
+        // Kind and parent of the list the previous block belongs to
+        let mut list: Option<(&str, Option<Uuid>)> = None;
+        let mut number = 0;
+        let mut equations = 0;
+
+        for block in blocks {
+            let depth = block
+                .parent
+                .and_then(|parent| depths.get(&parent))
+                .map_or(0, |depth| depth + 1);
+            depths.insert(block.id, depth);
+
+            let kind = block.kind.as_str();
+            let item = matches!(kind, "bullet" | "numbered" | "todo");
+            let continues_list = item && list == Some((kind, block.parent));
+            number = if continues_list { number + 1 } else { 1 };
+            list = item.then_some((kind, block.parent));
+
+            let indent = depth as f32 * INDENT;
+            let x = self.margin + indent;
+            let width = self.content_width() - indent;
+            match kind {
+                "heading" => self.heading(block, x, width),
+                "bullet" => self.list_item(block, "•", x, width),
+                "numbered" => self.list_item(block, &format!("{}.", number), x, width),
+                "todo" => {
+                    let checked = block.props.get("checked").and_then(Value::as_bool) == Some(true);
+                    self.list_item(block, if checked { "☑" } else { "☐" }, x, width);
+                }
+                "toggle" => {
+                    let mut spans = rich_spans(block);
+                    spans.insert(0, Span::new("▾ ", FontStyle::Regular, MUTED));
+                    self.paragraph(&spans, BODY_SIZE, x, width, None);
+                }
+                "quote" => {
+                    let mut spans = rich_spans(block);
+                    for span in &mut spans {
+                        span.color = MUTED;
+                    }
+                    self.boxed(&spans, BODY_SIZE, x, width, 0xffffff, Some(RULE));
+                }
+                "callout" => {
+                    let style: CalloutStyle = block
+                        .props
+                        .get("style")
+                        .cloned()
+                        .and_then(|style| serde_json::from_value(style).ok())
+                        .unwrap_or_default();
+                    let accent = print_color(style.color());
+                    self.boxed(&rich_spans(block), BODY_SIZE, x, width, tint(accent, 0.9), Some(accent));
+                }
+                "code" => {
+                    let text = block.content.replace('\t', "    ");
+                    let spans = [Span::new(text, FontStyle::Mono, TEXT)];
+                    self.code(&spans, x, width);
+                }
+                "math" => {
+                    let label = (!latex::is_unnumbered(&block.content)).then(|| {
+                        equations += 1;
+                        equations
+                    });
+                    match latex::parse(&block.content) {
+                        Ok(nodes) => self.formula(&nodes, x, width, label),
+                        Err(e) => self.source_error(&block.content, &e, x, width),
+                    }
+                }
+                "chemistry" => match chem::parse(&block.content) {
+                    Ok(reaction) => self.formula(&reaction_nodes(&reaction), x, width, None),
+                    Err(e) => self.source_error(&block.content, &e, x, width),
+                },
+                "citation" => match Citation::parse(&block.content) {
+                    Ok(citation) => {
+                        let spans = [
+                            Span::new(format!("[{}] ", citation.key), FontStyle::Mono, MUTED),
+                            Span::new(citation.reference(), FontStyle::Regular, TEXT),
+                        ];
+                        self.paragraph(&spans, BODY_SIZE, x, width, None);
+                    }
+                    Err(e) => self.source_error(&block.content, &e, x, width),
+                },
+                "chart" => self.chart(block, blocks, x, width),
+                "table" => self.table(&TableData::from_props(&block.props), x, width),
+                "image" => self.image(block, x, width),
+                "divider" => {
+                    self.ensure(BLOCK_GAP * 2.);
+                    self.y += BLOCK_GAP;
+                    self.fill(x, self.y, width, 0.75, RULE);
+                    self.y += BLOCK_GAP;
+                }
+                "text" if block.content.trim().is_empty() => {
+                    self.y += BODY_SIZE * LINE_HEIGHT;
+                }
+                _ => self.paragraph(&rich_spans(block), BODY_SIZE, x, width, None),
+            }
+            self.y += BLOCK_GAP;
+        }
+    }
+
+    fn heading(&mut self, block: &StoredBlock, x: f32, width: f32) {
+        let level = HeadingBlock::from_props(&block.props).level();
+        let (size, space) = match level {
+            1 => (22., 14.),
+            2 => (18., 12.),
+            3 => (15., 10.),
+            4 => (13., 9.),
+            5 => (12., 8.),
+            _ => (BODY_SIZE, 8.),
+        };
+        // Keep the heading on the page of the text after it
+        self.ensure(space + size * LINE_HEIGHT + BODY_SIZE * LINE_HEIGHT * 2.);
+        if self.y > self.margin {
+            self.y += space;
+        }
+
+        let title = block.content.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !title.is_empty() {
+            self.bookmarks.push(Bookmark {
+                level,
+                title,
+                page: self.page,
+                top: self.flip(self.y),
+            });
+        }
+        let mut spans = rich_spans(block);
+        for span in &mut spans {
+            span.style = FontStyle::new(true, span.style == FontStyle::Italic);
+        }
+        self.paragraph(&spans, size, x, width, None);
+    }
+
+    fn list_item(&mut self, block: &StoredBlock, marker: &str, x: f32, width: f32) {
+        let marker_width = (self.fonts.width(marker, FontStyle::Regular) * BODY_SIZE + 6.).max(14.);
+        let spans = rich_spans(block);
+        let lines = self.wrap(&spans, BODY_SIZE, width - marker_width);
+        self.ensure(BODY_SIZE * LINE_HEIGHT);
+        self.text(x, self.y + BODY_SIZE * 1.1, BODY_SIZE, FontStyle::Regular, marker, TEXT);
+        self.draw_lines(&lines, &spans, BODY_SIZE, x + marker_width, width - marker_width, None);
+    }
+
+    /// Code keeps its spacing and is broken between characters
+    fn code(&mut self, spans: &[Span], x: f32, width: f32) {
+        let inner = width - PADDING * 2.;
+        let mut lines = Vec::new();
+        let span = &spans[0];
+        for source in span.text.trim_end_matches('\n').split('\n') {
+            let mut line = Line::default();
+            for c in source.chars() {
+                let advance = self.fonts.glyph(c, span.style).advance * CODE_SIZE;
+                if !line.fragments.is_empty() && line.width + advance > inner {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(0, c, advance);
+            }
+            lines.push(line);
+        }
+
+        self.ensure(PADDING + CODE_SIZE * LINE_HEIGHT);
+        self.fill(x, self.y, width, PADDING, SHADE);
+        self.y += PADDING;
+        self.draw_lines(&lines, spans, CODE_SIZE, x + PADDING, inner, Some(SHADE));
+        self.fill(x, self.y, width, PADDING, SHADE);
+        self.y += PADDING;
+    }
+
+    /// A formula centered in the column, with its equation number at the
+    /// right edge
+    fn formula(&mut self, nodes: &[MathNode], x: f32, width: f32, label: Option<usize>) {
+        let label = label.map(|n| format!("({})", n));
+        let label_width = label
+            .as_deref()
+            .map_or(0., |label| self.fonts.width(label, FontStyle::Regular) * BODY_SIZE);
+        let available = width - label_width * 2.;
+
+        let mut formula = math::layout_row(nodes, MATH_SIZE, false, &mut self.fonts);
+        if formula.width > available && formula.width > 0. {
+            let size = MATH_SIZE * available / formula.width;
+            formula = math::layout_row(nodes, size, false, &mut self.fonts);
+        }
+
+        let gap = 4.;
+        self.ensure(formula.ascent + formula.descent + gap * 2.);
+        self.y += gap;
+        let baseline = self.y + formula.ascent;
+        let left = x + (width - formula.width) / 2.;
+        self.draw_formula(formula.items, left, baseline);
+        if let Some(label) = label {
+            self.text(x + width - label_width, baseline, BODY_SIZE, FontStyle::Regular, &label, TEXT);
+        }
+        self.y = baseline + formula.descent + gap;
+    }
+
+    fn draw_formula(&mut self, items: Vec<MathItem>, left: f32, baseline: f32) {
+        for item in items {
+            match item {
+                MathItem::Text {
+                    x,
+                    y,
+                    size,
+                    style,
+                    text,
+                    error,
+                } => {
+                    let color = if error { ERROR } else { TEXT };
+                    self.text(left + x, baseline - y, size, style, &text, color);
+                }
+                MathItem::Rule {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => self.fill(left + x, baseline - y - height, width, height, TEXT),
+            }
+        }
+    }
+
+    /// Source that failed to parse, with the reason
+    fn source_error(&mut self, source: &str, error: &str, x: f32, width: f32) {
+        let spans = [Span::new(source.trim(), FontStyle::Mono, TEXT)];
+        self.code(&spans, x, width);
+        let spans = [Span::new(error, FontStyle::Italic, ERROR)];
+        self.paragraph(&spans, CODE_SIZE, x, width, None);
+    }
+
+    /// A caption or note under an image
+    fn caption(&mut self, text: &str, x: f32, width: f32) {
+        let spans = [Span::new(text.trim(), FontStyle::Italic, MUTED)];
+        let lines = self.wrap(&spans, CODE_SIZE, width);
+        for line in &lines {
+            self.ensure(CODE_SIZE * LINE_HEIGHT);
+            let left = x + (width - line.width) / 2.;
+            self.draw_line(line, &spans, left, self.y, CODE_SIZE);
+            self.y += CODE_SIZE * LINE_HEIGHT;
+        }
+    }
+
+    /// Draw a picture centered in the column, shrunk to fit the column
+    /// and the page
+    fn place_picture(&mut self, picture: Picture, x: f32, width: f32) {
+        let (natural_width, natural_height) = picture.size;
+        let max_height = (self.bottom() - self.margin) * 0.8;
+        let scale = (width / natural_width).min(max_height / natural_height).min(1.);
+        let (w, h) = (natural_width * scale, natural_height * scale);
+
+        self.ensure(h);
+        self.pictures.push(picture);
+        let index = self.pictures.len() - 1;
+        self.picture(index, x + (width - w) / 2., self.y, w, h);
+        self.y += h + 4.;
+    }
+
+    fn image(&mut self, block: &StoredBlock, x: f32, width: f32) {
+        let picture = block
+            .attachment()
+            .and_then(|file| std::fs::read(self.images.path(file)).ok())
+            .and_then(|bytes| Picture::decode(&bytes));
+        match picture {
+            Some(picture) => {
+                self.place_picture(picture, x, width);
+                if !block.content.trim().is_empty() {
+                    self.caption(&block.content, x, width);
+                }
+            }
+            None => {
+                let name = block.attachment().unwrap_or_default();
+                let text = match block.content.trim() {
+                    "" => format!("[Image {}]", name),
+                    caption => format!("[Image: {}]", caption),
+                };
+                self.caption(&text, x, width);
+            }
+        }
+    }
+
+    fn chart(&mut self, block: &StoredBlock, blocks: &[StoredBlock], x: f32, width: f32) {
+        let spec: ChartSpec = serde_json::from_value(block.props.clone()).unwrap_or_default();
+        let data = match spec.source {
+            Some(source) => blocks
+                .iter()
+                .find(|b| b.id == source && b.kind == "table")
+                .map(|table| TableData::from_props(&table.props)),
+            None => ChartBlock::parse_inline(&block.content),
+        };
+        let plot = data
+            .ok_or_else(|| "No data to plot".to_string())
+            .and_then(|data| Plot::new(&spec, &data));
+        let picture = plot.and_then(|plot| {
+            let png = plot.to_png().map_err(|e| e.to_string())?;
+            Picture::decode(&png).ok_or_else(|| "Failed to render chart".to_string())
+        });
+        match picture {
+            Ok(picture) => self.place_picture(picture, x, width),
+            Err(e) => self.caption(&format!("[Chart: {}]", e), x, width),
+        }
+    }
+
+    fn table(&mut self, data: &TableData, x: f32, width: f32) {
+        if data.columns.is_empty() {
+            return;
+        }
+        let header: Vec<Span> = data
+            .columns
+            .iter()
+            .map(|column| Span::new(column.name.clone(), FontStyle::Bold, TEXT))
+            .collect();
+        let rows: Vec<Vec<Span>> = data
+            .rows
+            .iter()
+            .map(|row| {
+                (0..data.columns.len())
+                    .map(|i| {
+                        let value = row.get(i).map_or("", String::as_str);
+                        Span::new(value, FontStyle::Regular, TEXT)
+                    })
+                    .collect()
+            })
+            .collect();
+        let right: Vec<bool> = data
+            .columns
+            .iter()
+            .map(|column| column.kind == ColumnType::Number)
+            .collect();
+
+        // Columns get their natural width, shrunk in proportion when the
+        // table is wider than the column of text
+        let mut widths: Vec<f32> = header
+            .iter()
+            .enumerate()
+            .map(|(i, span)| {
+                rows.iter()
+                    .map(|row| &row[i])
+                    .chain([span])
+                    .map(|span| {
+                        span.text
+                            .lines()
+                            .map(|line| self.fonts.width(line, span.style) * TABLE_SIZE)
+                            .fold(0., f32::max)
+                    })
+                    .fold(0., f32::max)
+                    + PADDING * 2.
+            })
+            .collect();
+        let total: f32 = widths.iter().sum();
+        if total > width {
+            for column in &mut widths {
+                *column *= width / total;
+            }
+        }
+
+        self.table_row(&header, &widths, &right, x, true);
+        for row in &rows {
+            let height = self.row_height(row, &widths);
+            if self.y + height > self.bottom() {
+                self.new_page();
+                self.table_row(&header, &widths, &right, x, true);
+            }
+            self.table_row(row, &widths, &right, x, false);
+        }
+    }
+
+    fn row_lines(&mut self, cells: &[Span], widths: &[f32]) -> Vec<Vec<Line>> {
+        cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| self.wrap(std::slice::from_ref(cell), TABLE_SIZE, width - PADDING * 2.))
+            .collect()
+    }
+
+    fn row_height(&mut self, cells: &[Span], widths: &[f32]) -> f32 {
+        let lines = self.row_lines(cells, widths);
+        let count = lines.iter().map(Vec::len).max().unwrap_or(1);
+        count as f32 * TABLE_SIZE * LINE_HEIGHT + PADDING
+    }
+
+    fn table_row(&mut self, cells: &[Span], widths: &[f32], right: &[bool], x: f32, header: bool) {
+        let lines = self.row_lines(cells, widths);
+        let count = lines.iter().map(Vec::len).max().unwrap_or(1);
+        let height = count as f32 * TABLE_SIZE * LINE_HEIGHT + PADDING;
+        self.ensure(height);
+
+        let top = self.y;
+        let mut left = x;
+        for (i, cell_lines) in lines.iter().enumerate() {
+            let width = widths[i];
+            if header {
+                self.fill(left, top, width, height, SHADE);
+            }
+            self.stroke(left, top, width, height, RULE);
+            let mut line_top = top + PADDING / 2.;
+            for line in cell_lines {
+                let offset = if right[i] && !header {
+                    width - PADDING - line.width
+                } else {
+                    PADDING
+                };
+                self.draw_line(line, std::slice::from_ref(&cells[i]), left + offset, line_top, TABLE_SIZE);
+                line_top += TABLE_SIZE * LINE_HEIGHT;
+            }
+            left += width;
+        }
+        self.y = top + height;
+    }
+
+    /// Draw the running header and footer on every page
+    pub(super) fn decorate(&mut self, header: &str, author: Option<&str>, footer: &str) {
+        let size = 8.;
+        let count = self.pages.len();
+        let header_y = self.margin / 2.;
+        let footer_y = self.height - self.margin / 2.;
+        for page in 0..count {
+            self.page = page;
+            let right = self.width - self.margin;
+            let available = self.content_width();
+
+            let title = self.fit(header, FontStyle::Regular, size, available * 0.6);
+            self.text(self.margin, header_y, size, FontStyle::Regular, &title, MUTED);
+            if let Some(author) = author {
+                let author = self.fit(author, FontStyle::Regular, size, available * 0.35);
+                let author_width = self.fonts.width(&author, FontStyle::Regular) * size;
+                self.text(right - author_width, header_y, size, FontStyle::Regular, &author, MUTED);
+            }
+            self.fill(self.margin, header_y + size * 0.5, available, 0.5, RULE);
+
+            self.text(self.margin, footer_y, size, FontStyle::Regular, footer, MUTED);
+            let number = format!("Page {} of {}", page + 1, count);
+            let number_width = self.fonts.width(&number, FontStyle::Regular) * size;
+            self.text(right - number_width, footer_y, size, FontStyle::Regular, &number, MUTED);
+        }
+    }
+
+    /// `text`, shortened with an ellipsis to fit `width`
+    fn fit(&mut self, text: &str, style: FontStyle, size: f32, width: f32) -> String {
+        if self.fonts.width(text, style) * size <= width {
+            return text.to_string();
+        }
+        let mut fitted = String::new();
+        let mut used = self.fonts.width("…", style) * size;
+        for c in text.chars() {
+            used += self.fonts.glyph(c, style).advance * size;
+            if used > width {
+                break;
+            }
+            fitted.push(c);
+        }
+        fitted.push('…');
+        fitted
+    }
+}
+
+/// A block's text with its inline formatting
+fn rich_spans(block: &StoredBlock) -> Vec<Span> {
+    Marks::from_props(&block.props, &block.content)
+        .spans()
+        .into_iter()
+        .map(|element| {
+            let style = if element.code {
+                FontStyle::Mono
+            } else {
+                FontStyle::new(element.bold, element.italic)
+            };
+            let color = element
+                .color
+                .as_deref()
+                .and_then(|hex| u32::from_str_radix(hex.trim_start_matches('#'), 16).ok())
+                .map(print_color);
+            Span {
+                color: match (&element.link, color) {
+                    (_, Some(color)) => color,
+                    (Some(_), None) => LINK,
+                    (None, None) => TEXT,
+                },
+                text: element.content,
+                style,
+                strikethrough: element.strikethrough,
+                link: element.link,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,207 @@
+use crate::block::parse::latex::MathNode;
+
+use super::fonts::{FontStyle, Fonts};
+
+/// Scale of subscripts, superscripts and fractions
+const SCRIPT_SCALE: f32 = 0.7;
+/// Smallest size scripts are shrunk to
+const MIN_SIZE: f32 = 6.;
+/// Height of the fraction bar above the baseline, in em
+const AXIS: f32 = 0.3;
+
+/// Something drawn in a formula. Positions are relative to the start of
+/// the formula's baseline, with y pointing up.
+pub(super) enum MathItem {
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+        style: FontStyle,
+        text: String,
+        /// Commands the parser does not know are shown in red
+        error: bool,
+    },
+    Rule {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+}
+
+/// A laid out formula or part of one
+#[derive(Default)]
+pub(super) struct MathBox {
+    pub(super) width: f32,
+    /// Extent above the baseline
+    pub(super) ascent: f32,
+    /// Extent below the baseline
+    pub(super) descent: f32,
+    pub(super) items: Vec<MathItem>,
+}
+
+impl MathBox {
+    /// Place `other` at `(x, y)` in this box, growing it to fit
+    fn append(&mut self, other: MathBox, x: f32, y: f32) {
+        self.width = self.width.max(x + other.width);
+        self.ascent = self.ascent.max(y + other.ascent);
+        self.descent = self.descent.max(other.descent - y);
+        self.items.extend(other.items.into_iter().map(|item| match item {
+            MathItem::Text {
+                x: ix,
+                y: iy,
+                size,
+                style,
+                text,
+                error,
+            } => MathItem::Text {
+                x: ix + x,
+                y: iy + y,
+                size,
+                style,
+                text,
+                error,
+            },
+            MathItem::Rule {
+                x: ix,
+                y: iy,
+                width,
+                height,
+            } => MathItem::Rule {
+                x: ix + x,
+                y: iy + y,
+                width,
+                height,
+            },
+        }));
+    }
+
+    /// Add `other` after the current content
+    fn push(&mut self, other: MathBox) {
+        let x = self.width;
+        self.append(other, x, 0.);
+    }
+}
+
+fn script_size(size: f32) -> f32 {
+    (size * SCRIPT_SCALE).max(MIN_SIZE)
+}
+
+/// Lay out nodes on one line
+pub(super) fn layout_row(nodes: &[MathNode], size: f32, bold: bool, fonts: &mut Fonts) -> MathBox {
+    let mut row = MathBox::default();
+    for node in nodes {
+        row.push(layout_node(node, size, bold, fonts));
+    }
+    row
+}
+
+fn text_box(text: &str, size: f32, style: FontStyle, error: bool, fonts: &mut Fonts) -> MathBox {
+    MathBox {
+        width: fonts.width(text, style) * size,
+        ascent: size * 0.75,
+        descent: size * 0.25,
+        items: vec![MathItem::Text {
+            x: 0.,
+            y: 0.,
+            size,
+            style,
+            text: text.to_string(),
+            error,
+        }],
+    }
+}
+
+fn layout_node(node: &MathNode, size: f32, bold: bool, fonts: &mut Fonts) -> MathBox {
+    let upright = if bold { FontStyle::Bold } else { FontStyle::Regular };
+    match node {
+        MathNode::Ident(s) => text_box(s, size, FontStyle::new(bold, true), false, fonts),
+        MathNode::Number(s) | MathNode::Symbol(s) | MathNode::Text(s) => {
+            text_box(s, size, upright, false, fonts)
+        }
+        MathNode::Operator(s) => {
+            let pad = size * 0.22;
+            let mut padded = MathBox::default();
+            padded.append(text_box(s, size, upright, false, fonts), pad, 0.);
+            padded.width += pad;
+            padded
+        }
+        MathNode::Bold(nodes) => layout_row(nodes, size, true, fonts),
+        MathNode::Group(nodes) => layout_row(nodes, size, bold, fonts),
+        MathNode::Scripts { base, sub, sup } => {
+            let small = script_size(size);
+            let mut scripts = layout_node(base, size, bold, fonts);
+            let x = scripts.width;
+            if let Some(sup) = sup {
+                let sup = layout_row(sup, small, bold, fonts);
+                let raise = (size * 0.45).max(scripts.ascent - sup.ascent * 0.5);
+                scripts.append(sup, x, raise);
+            }
+            if let Some(sub) = sub {
+                let sub = layout_row(sub, small, bold, fonts);
+                scripts.append(sub, x, -size * 0.25);
+            }
+            scripts
+        }
+        MathNode::Frac(numerator, denominator) => {
+            let small = script_size(size);
+            let numerator = layout_row(numerator, small, bold, fonts);
+            let denominator = layout_row(denominator, small, bold, fonts);
+            let pad = size * 0.15;
+            let gap = size * 0.12;
+            let rule = (size * 0.05).max(0.5);
+            let axis = size * AXIS;
+            let width = numerator.width.max(denominator.width);
+
+            let mut frac = MathBox::default();
+            let numerator_y = axis + rule / 2. + gap + numerator.descent;
+            let denominator_y = axis - rule / 2. - gap - denominator.ascent;
+            let numerator_x = pad + (width - numerator.width) / 2.;
+            let denominator_x = pad + (width - denominator.width) / 2.;
+            frac.append(numerator, numerator_x, numerator_y);
+            frac.append(denominator, denominator_x, denominator_y);
+            frac.items.push(MathItem::Rule {
+                x: pad,
+                y: axis - rule / 2.,
+                width,
+                height: rule,
+            });
+            frac.width = width + pad * 2.;
+            frac
+        }
+        MathNode::Sqrt { index, radicand } => {
+            let radicand = layout_row(radicand, size, bold, fonts);
+            // Grow the radical sign with tall radicands
+            let height = (radicand.ascent + radicand.descent) * 1.15;
+            let sign_size = size.max(height);
+            let sign = text_box("√", sign_size, FontStyle::Regular, false, fonts);
+            let sign_y = radicand.ascent - sign_size * 0.8;
+
+            let mut root = MathBox::default();
+            if let Some(index) = index {
+                let index = layout_row(index, script_size(script_size(size)), bold, fonts);
+                root.append(index, 0., radicand.ascent * 0.4);
+            }
+            let sign_x = (root.width - sign.width * 0.4).max(0.);
+            let sign_width = sign.width;
+            root.append(sign, sign_x, sign_y);
+            let radicand_x = sign_x + sign_width;
+            let rule = (size * 0.05).max(0.5);
+            root.items.push(MathItem::Rule {
+                x: radicand_x,
+                y: radicand.ascent + size * 0.08,
+                width: radicand.width,
+                height: rule,
+            });
+            let top = radicand.ascent + size * 0.08 + rule;
+            root.append(radicand, radicand_x, 0.);
+            root.ascent = root.ascent.max(top);
+            root
+        }
+        MathNode::Space(em) => MathBox {
+            width: size * em,
+            ..MathBox::default()
+        },
+        MathNode::Unknown(s) => text_box(s, size, upright, true, fonts),
+    }
+}
@@ -0,0 +1,306 @@
+/// Finding system fonts for PDF text and embedding the glyphs it uses.
+///
+/// Fonts are embedded as CID fonts addressed by glyph id, with a
+/// `ToUnicode` map so text can be searched and copied. Only the outlines
+/// of used glyphs are kept. Characters missing from the chosen fonts are
+/// looked up in the other installed fonts.
+mod fonts;
+/// Decoding images and charts into PDF image objects.
+mod image;
+/// Laying out blocks on printed pages.
+mod layout;
+/// Typesetting math for PDF pages as text and rules.
+mod math;
+/// Serializing PDF objects, streams and the cross-reference table.
+mod writer;
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use super::author;
+use crate::storage::{AttachmentStore, Document};
+
+use fonts::Fonts;
+use layout::{Bookmark, Layout};
+use writer::{literal_string, number, text_string, ObjectId, PdfWriter};
+
+/// Paper size of exported pages
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PageSize {
+    #[default]
+    A4,
+    Letter,
+    A5,
+}
+
+impl PageSize {
+    pub fn display_name(self) -> &'static str {
+        match self {
+            PageSize::A4 => "A4",
+            PageSize::Letter => "Letter",
+            PageSize::A5 => "A5",
+        }
+    }
+
+    /// Width and height in points
+    pub fn dimensions(self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (595.28, 841.89),
+            PageSize::Letter => (612., 792.),
+            PageSize::A5 => (419.53, 595.28),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            PageSize::A4 => PageSize::Letter,
+            PageSize::Letter => PageSize::A5,
+            PageSize::A5 => PageSize::A4,
+        }
+    }
+}
+
+/// Space between the page edges and the text
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Margins {
+    Narrow,
+    #[default]
+    Normal,
+    Wide,
+}
+
+impl Margins {
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Margins::Narrow => "Narrow margins",
+            Margins::Normal => "Normal margins",
+            Margins::Wide => "Wide margins",
+        }
+    }
+
+    /// Margin in points
+    pub fn points(self) -> f32 {
+        match self {
+            Margins::Narrow => 36.,
+            Margins::Normal => 60.,
+            Margins::Wide => 90.,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Margins::Narrow => Margins::Normal,
+            Margins::Normal => Margins::Wide,
+            Margins::Wide => Margins::Narrow,
+        }
+    }
+}
+
+/// Page setup for PDF export
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PdfOptions {
+    pub page_size: PageSize,
+    pub margins: Margins,
+}
+
+/// Export a document as a PDF file at `path` and return the path
+pub fn write_pdf(
+    document: &Document,
+    path: &Path,
+    images: &AttachmentStore,
+    options: &PdfOptions,
+) -> Result<PathBuf> {
+    let path = path.with_extension("pdf");
+    let title = document.display_title();
+    let author = author(document);
+    let now = chrono::Local::now();
+
+    let fonts = Fonts::load()?;
+    let mut layout = Layout::new(fonts, images, options.page_size.dimensions(), options.margins.points());
+    layout.blocks(&document.blocks);
+    let footer = format!("Exported {}", now.format("%Y-%m-%d %H:%M"));
+    layout.decorate(&title, author.as_deref(), &footer);
+
+    let mut writer = PdfWriter::new();
+    let catalog = writer.reserve();
+    let pages_id = writer.reserve();
+    let page_ids: Vec<ObjectId> = layout.pages.iter().map(|_| writer.reserve()).collect();
+
+    let fonts = layout.fonts.embed(&mut writer)?;
+    let mut pictures = String::new();
+    for (index, picture) in layout.pictures.iter().enumerate() {
+        let id = picture.embed(&mut writer);
+        write!(pictures, "/Im{} {} 0 R ", index, id)?;
+    }
+    let resources = writer.reserve();
+    writer.object(
+        resources,
+        &format!("<< /Font << {}>> /XObject << {}>> >>", fonts, pictures),
+    );
+
+    let (width, height) = options.page_size.dimensions();
+    for (page, &id) in layout.pages.iter().zip(&page_ids) {
+        let content = writer.reserve();
+        writer.stream(content, "", page.content.as_bytes());
+
+        let mut annotations = String::new();
+        for (rect, url) in &page.links {
+            let link = writer.reserve();
+            writer.object(
+                link,
+                &format!(
+                    "<< /Type /Annot /Subtype /Link /Rect [{} {} {} {}] /Border [0 0 0] \
+                     /A << /S /URI /URI {} >> >>",
+                    number(rect[0]),
+                    number(rect[1]),
+                    number(rect[2]),
+                    number(rect[3]),
+                    literal_string(url)
+                ),
+            );
+            write!(annotations, "{} 0 R ", link)?;
+        }
+        writer.object(
+            id,
+            &format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources {} 0 R \
+                 /Contents {} 0 R /Annots [{}] >>",
+                pages_id,
+                number(width),
+                number(height),
+                resources,
+                content,
+                annotations
+            ),
+        );
+    }
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    writer.object(
+        pages_id,
+        &format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), page_ids.len()),
+    );
+
+    let outline = write_outline(&mut writer, &layout.bookmarks, &page_ids);
+    let outline_entry = match outline {
+        Some(outline) => format!(" /Outlines {} 0 R /PageMode /UseOutlines", outline),
+        None => String::new(),
+    };
+    writer.object(
+        catalog,
+        &format!("<< /Type /Catalog /Pages {} 0 R{} >>", pages_id, outline_entry),
+    );
+
+    let info = writer.reserve();
+    let mut info_dict = format!(
+        "<< /Title {} /Creator (Love Note) /CreationDate {}",
+        text_string(&title),
+        literal_string(&now.format("D:%Y%m%d%H%M%S").to_string())
+    );
+    if let Some(author) = &author {
+        write!(info_dict, " /Author {}", text_string(author))?;
+    }
+    info_dict.push_str(" >>");
+    writer.object(info, &info_dict);
+
+    std::fs::write(&path, writer.finish(catalog, info))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Write the headings as nested bookmarks and return the outline object
+fn write_outline(writer: &mut PdfWriter, bookmarks: &[Bookmark], pages: &[ObjectId]) -> Option<ObjectId> {
+    if bookmarks.is_empty() {
+        return None;
+    }
+    let root = writer.reserve();
+    let ids: Vec<ObjectId> = bookmarks.iter().map(|_| writer.reserve()).collect();
+
+    // Each heading nests under the closest earlier heading of a higher level
+    let mut parents: Vec<Option<usize>> = Vec::with_capacity(bookmarks.len());
+    let mut stack: Vec<usize> = Vec::new();
+    for (i, bookmark) in bookmarks.iter().enumerate() {
+        while stack.last().is_some_and(|&top| bookmarks[top].level >= bookmark.level) {
+            stack.pop();
+        }
+        parents.push(stack.last().copied());
+        stack.push(i);
+    }
+    let children = |parent: Option<usize>| -> Vec<usize> {
+        (0..bookmarks.len()).filter(|&i| parents[i] == parent).collect()
+    };
+    // Descendants are the headings up to the next one of the same or a
+    // higher level
+    let descendants = |item: usize| -> usize {
+        bookmarks[item + 1..]
+            .iter()
+            .take_while(|bookmark| bookmark.level > bookmarks[item].level)
+            .count()
+    };
+
+    for (i, bookmark) in bookmarks.iter().enumerate() {
+        let siblings = children(parents[i]);
+        let position = siblings.iter().position(|&s| s == i).unwrap_or_default();
+        let mut entry = format!(
+            "<< /Title {} /Parent {} 0 R /Dest [{} 0 R /XYZ null {} null]",
+            text_string(&bookmark.title),
+            parents[i].map_or(root, |parent| ids[parent]),
+            pages[bookmark.page],
+            number(bookmark.top)
+        );
+        if position > 0 {
+            write!(entry, " /Prev {} 0 R", ids[siblings[position - 1]]).ok();
+        }
+        if let Some(&next) = siblings.get(position + 1) {
+            write!(entry, " /Next {} 0 R", ids[next]).ok();
+        }
+        let kids = children(Some(i));
+        if let (Some(first), Some(last)) = (kids.first(), kids.last()) {
+            write!(
+                entry,
+                " /First {} 0 R /Last {} 0 R /Count {}",
+                ids[*first],
+                ids[*last],
+                descendants(i)
+            )
+            .ok();
+        }
+        entry.push_str(" >>");
+        writer.object(ids[i], &entry);
+    }
+
+    let top = children(None);
+    writer.object(
+        root,
+        &format!(
+            "<< /Type /Outlines /First {} 0 R /Last {} 0 R /Count {} >>",
+            ids[top[0]],
+            ids[top[top.len() - 1]],
+            bookmarks.len()
+        ),
+    );
+    Some(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{sample, scratch_dir};
+
+    #[test]
+    fn writes_a_pdf_with_the_image_embedded() {
+        let dir = scratch_dir();
+        let (document, images) = sample(&dir);
+        let path = write_pdf(&document, &dir.join("out"), &images, &PdfOptions::default()).unwrap();
+        assert_eq!(path, dir.join("out.pdf"));
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"%PDF-"));
+        assert!(bytes.trim_ascii_end().ends_with(b"%%EOF"));
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains("/Subtype /Image"));
+        assert!(text.contains("/Outlines"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+/// Number of a PDF object
+pub(super) type ObjectId = usize;
+
+/// A PDF file being written, with objects numbered in the order they
+/// were reserved and written in any order
+pub(super) struct PdfWriter {
+    buf: Vec<u8>,
+    /// Byte offset of each object, indexed by object number minus one
+    offsets: Vec<Option<usize>>,
+}
+
+impl PdfWriter {
+    pub(super) fn new() -> Self {
+        Self {
+            // The comment with high bytes marks the file as binary
+            buf: b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n".to_vec(),
+            offsets: Vec::new(),
+        }
+    }
+
+    /// A number for an object written later
+    pub(super) fn reserve(&mut self) -> ObjectId {
+        self.offsets.push(None);
+        self.offsets.len()
+    }
+
+    pub(super) fn object(&mut self, id: ObjectId, body: &str) {
+        self.begin(id);
+        self.buf.extend_from_slice(body.as_bytes());
+        self.buf.extend_from_slice(b"\nendobj\n");
+    }
+
+    /// Write a stream object. `dict` holds the entries besides the
+    /// length and filter, which are added here.
+    pub(super) fn stream(&mut self, id: ObjectId, dict: &str, data: &[u8]) {
+        let compressed = deflate(data);
+        self.raw_stream(id, &format!("{} /Filter /FlateDecode", dict), &compressed);
+    }
+
+    /// Write a stream object whose data is already encoded as `dict`
+    /// describes
+    pub(super) fn raw_stream(&mut self, id: ObjectId, dict: &str, data: &[u8]) {
+        self.begin(id);
+        let header = format!("<< {} /Length {} >>\nstream\n", dict, data.len());
+        self.buf.extend_from_slice(header.as_bytes());
+        self.buf.extend_from_slice(data);
+        self.buf.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn begin(&mut self, id: ObjectId) {
+        self.offsets[id - 1] = Some(self.buf.len());
+        self.buf.extend_from_slice(format!("{} 0 obj\n", id).as_bytes());
+    }
+
+    /// Write the cross-reference table and trailer
+    pub(super) fn finish(mut self, catalog: ObjectId, info: ObjectId) -> Vec<u8> {
+        let xref = self.buf.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            // Reserved objects that were never written are marked free
+            match offset {
+                Some(offset) => writeln!(table, "{:010} 00000 n ", offset),
+                None => writeln!(table, "0000000000 65535 f "),
+            }
+            .ok();
+        }
+        write!(
+            table,
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1,
+            catalog,
+            info,
+            xref
+        )
+        .ok();
+        self.buf.extend_from_slice(table.as_bytes());
+        self.buf
+    }
+}
+
+pub(super) fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // Writing to a Vec cannot fail
+    encoder.write_all(data).ok();
+    encoder.finish().unwrap_or_default()
+}
+
+/// A text string as UTF-16 with a byte order mark, so any character can
+/// be used in titles and bookmarks
+pub(super) fn text_string(text: &str) -> String {
+    let mut out = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        write!(out, "{:04X}", unit).ok();
+    }
+    out.push('>');
+    out
+}
+
+/// A byte string literal, for ASCII values such as URLs
+pub(super) fn literal_string(text: &str) -> String {
+    let mut out = String::from("(");
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            // Non-ASCII characters are percent-encoded as in URLs
+            _ => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    write!(out, "%{:02X}", byte).ok();
+                }
+            }
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// A number without needless decimals
+pub(super) fn number(value: f32) -> String {
+    let rounded = (value * 100.).round() / 100.;
+    if rounded == rounded.trunc() {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{}", rounded)
+    }
+}
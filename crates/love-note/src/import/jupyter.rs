@@ -0,0 +1,207 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+
+use super::Imported;
+use crate::markdown::{code_block, from_markdown, import_images};
+use crate::storage::{AttachmentStore, Document, StoredBlock};
+
+/// Image outputs read from notebooks with their file extensions, most
+/// preferred first
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/svg+xml", "svg"),
+    ("image/gif", "gif"),
+];
+
+/// Import a Jupyter notebook as a document.
+///
+/// Markdown cells become blocks as Markdown files do, code cells code
+/// blocks, and their outputs image and text blocks after them. The
+/// notebook's kernel and which blocks are outputs are kept in the
+/// document's `jupyter` metadata, so it can be exported back.
+pub fn import_notebook(path: &Path, images: &AttachmentStore) -> Result<Imported> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let notebook: Value = serde_json::from_str(&text)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    if notebook.get("nbformat").and_then(Value::as_u64).unwrap_or(0) < 4 {
+        bail!("Only notebooks in format 4 can be imported");
+    }
+
+    let metadata = notebook.get("metadata").cloned().unwrap_or_default();
+    let language = metadata
+        .pointer("/language_info/name")
+        .or_else(|| metadata.pointer("/kernelspec/language"))
+        .and_then(Value::as_str)
+        .unwrap_or("python")
+        .to_lowercase();
+    let base = path.parent().unwrap_or(Path::new("."));
+
+    let mut skipped = Vec::new();
+    let mut blocks = Vec::new();
+    let mut outputs = Vec::new();
+    let cells = notebook.get("cells").and_then(Value::as_array);
+    for cell in cells.into_iter().flatten() {
+        let source = multiline(cell.get("source"));
+        match cell.get("cell_type").and_then(Value::as_str) {
+            Some("markdown") => {
+                let mut cell_blocks = from_markdown(&source);
+                import_attachments(&mut cell_blocks, cell.get("attachments"), images, &mut skipped)?;
+                skipped.extend(import_images(&mut cell_blocks, images, base, base));
+                blocks.extend(cell_blocks);
+            }
+            Some("code") => {
+                blocks.push(code_block(&language, source));
+                let cell_outputs = cell.get("outputs").and_then(Value::as_array);
+                for output in cell_outputs.into_iter().flatten() {
+                    if let Some(block) = output_block(output, images, &mut skipped)? {
+                        outputs.push(Value::String(block.id.to_string()));
+                        blocks.push(block);
+                    }
+                }
+            }
+            _ if source.trim().is_empty() => {}
+            _ => blocks.push(code_block("plain", source)),
+        }
+    }
+
+    let title = blocks
+        .iter()
+        .find(|block| block.kind == "heading")
+        .map(|block| block.content.clone())
+        .or_else(|| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "Untitled".to_string());
+
+    let mut jupyter = Map::new();
+    for key in ["kernelspec", "language_info"] {
+        if let Some(value) = metadata.get(key) {
+            jupyter.insert(key.to_string(), value.clone());
+        }
+    }
+    jupyter.insert("outputs".to_string(), Value::Array(outputs));
+
+    let mut document = Document::new(title);
+    document.blocks = blocks;
+    document
+        .metadata
+        .insert("jupyter".to_string(), Value::Object(jupyter));
+    Ok(Imported {
+        documents: vec![document],
+        skipped,
+    })
+}
+
+/// Text stored as a string or as a list of lines
+fn multiline(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Store the first image in `data`, a map of media types to contents,
+/// and return its attachment name. Images that can't be decoded are
+/// added to `skipped`.
+fn import_image(data: &Value, images: &AttachmentStore, skipped: &mut Vec<String>) -> Result<Option<String>> {
+    for (media_type, extension) in IMAGE_TYPES {
+        let content = multiline(data.get(*media_type));
+        if content.is_empty() {
+            continue;
+        }
+        let bytes = if *extension == "svg" {
+            content.into_bytes()
+        } else {
+            let encoded: String = content.split_whitespace().collect();
+            match BASE64.decode(encoded) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    skipped.push(format!("{} image: {}", media_type, e));
+                    continue;
+                }
+            }
+        };
+        return images.import_bytes(&bytes, extension).map(Some);
+    }
+    Ok(None)
+}
+
+/// Point images linked as `attachment:name` at the cell's attachments
+fn import_attachments(
+    blocks: &mut [StoredBlock],
+    attachments: Option<&Value>,
+    images: &AttachmentStore,
+    skipped: &mut Vec<String>,
+) -> Result<()> {
+    for block in blocks.iter_mut().filter(|b| b.kind == "image") {
+        let data = block
+            .props
+            .get("src")
+            .and_then(Value::as_str)
+            .and_then(|src| src.strip_prefix("attachment:"))
+            .and_then(|name| attachments?.get(name));
+        if let Some(data) = data
+            && let Some(file) = import_image(data, images, skipped)?
+        {
+            block.props = json!({ "file": file });
+        }
+    }
+    Ok(())
+}
+
+/// A block showing a code cell output, or `None` for outputs with
+/// nothing to show
+fn output_block(output: &Value, images: &AttachmentStore, skipped: &mut Vec<String>) -> Result<Option<StoredBlock>> {
+    let Some(output_type) = output.get("output_type").and_then(Value::as_str) else {
+        return Ok(None);
+    };
+    let text = match output_type {
+        "stream" => multiline(output.get("text")),
+        "execute_result" | "display_data" => {
+            let Some(data) = output.get("data") else {
+                return Ok(None);
+            };
+            if let Some(file) = import_image(data, images, skipped)? {
+                return Ok(Some(StoredBlock::new("image", "").with_props(json!({ "file": file }))));
+            }
+            multiline(data.get("text/plain"))
+        }
+        "error" => {
+            let field = |name| output.get(name).and_then(Value::as_str).unwrap_or_default();
+            format!("{}: {}", field("ename"), field("evalue"))
+        }
+        _ => return Ok(None),
+    };
+    let text = strip_ansi(&text);
+    let text = text.trim_end();
+    Ok((!text.is_empty()).then(|| StoredBlock::new("text", text)))
+}
+
+/// Remove terminal color codes, which kernels put in tracebacks and
+/// progress bars
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Skip `ESC [`, its parameters and the final letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
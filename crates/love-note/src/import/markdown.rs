@@ -0,0 +1,262 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde_json::{Map, Value};
+
+use super::Imported;
+use crate::markdown::{from_markdown, import_images};
+use crate::storage::{AttachmentStore, Document};
+
+/// Extensions of the files read when importing a folder
+const EXTENSIONS: &[&str] = &["md", "markdown"];
+
+/// Import a Markdown file, or every Markdown file in a folder and its
+/// subfolders, as documents. Local images they link to are copied into
+/// `images`. Files in a folder that fail to import are skipped.
+pub fn import_markdown(path: &Path, images: &AttachmentStore) -> Result<Imported> {
+    let mut imported = Imported::default();
+    if !path.is_dir() {
+        let root = path.parent().unwrap_or(Path::new("."));
+        let document = import_file(path, root, images, &mut imported.skipped)?;
+        imported.documents.push(document);
+        return Ok(imported);
+    }
+
+    let mut files = Vec::new();
+    collect_files(path, &mut files)?;
+    files.sort();
+
+    for file in files {
+        match import_file(&file, path, images, &mut imported.skipped) {
+            Ok(document) => imported.documents.push(document),
+            Err(e) => imported.skipped.push(format!("{}: {}", file.display(), e)),
+        }
+    }
+    Ok(imported)
+}
+
+/// Markdown files under `dir`, skipping hidden folders such as `.git`.
+/// Symbolic links aren't followed, so links back up the tree can't loop.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let hidden = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'));
+        let file_type = entry.file_type()?;
+        if hidden || file_type.is_symlink() {
+            continue;
+        }
+        if file_type.is_dir() {
+            collect_files(&path, files)?;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Import a single file, adding the images it links to that couldn't be
+/// read to `skipped`. Only images inside `root` are read.
+fn import_file(
+    path: &Path,
+    root: &Path,
+    images: &AttachmentStore,
+    skipped: &mut Vec<String>,
+) -> Result<Document> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let (mut metadata, body) = split_front_matter(&text);
+
+    let mut blocks = from_markdown(body);
+    let base = path.parent().unwrap_or(Path::new("."));
+    skipped.extend(import_images(&mut blocks, images, base, root));
+
+    // Title from the front-matter, else the first heading, else the file
+    let title = match metadata.remove("title") {
+        Some(Value::String(title)) => Some(title),
+        _ => None,
+    }
+    .or_else(|| {
+        blocks
+            .iter()
+            .find(|block| block.kind == "heading")
+            .map(|block| block.content.clone())
+    })
+    .or_else(|| {
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(str::to_string)
+    })
+    .unwrap_or_else(|| "Untitled".to_string());
+
+    let mut document = Document::new(title);
+    document.blocks = blocks;
+    document.tags = take_tags(&mut metadata);
+    if let Some(created) = take_date(&mut metadata, &["created", "date"]) {
+        document.created_at = created;
+    }
+    if let Some(updated) = take_date(&mut metadata, &["updated", "modified", "lastmod"]) {
+        document.updated_at = updated;
+    }
+    document.metadata = metadata;
+    Ok(document)
+}
+
+/// Split YAML front-matter off the text. Only the flat subset notes use
+/// is understood: scalars, `[a, b]` lists and lists of `- item` lines.
+fn split_front_matter(text: &str) -> (Map<String, Value>, &str) {
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (Map::new(), text);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            let body = &rest[offset + line.len()..];
+            return (parse_yaml(&rest[..offset]), body);
+        }
+        offset += line.len();
+    }
+    (Map::new(), text)
+}
+
+fn parse_yaml(text: &str) -> Map<String, Value> {
+    let mut map = Map::new();
+    // Key whose `- item` lines are being read
+    let mut list_key: Option<String> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(item) = trimmed.strip_prefix("- ")
+            && let Some(Value::Array(items)) = list_key.as_ref().and_then(|key| map.get_mut(key))
+        {
+            items.push(yaml_scalar(item));
+            continue;
+        }
+        // Nested mappings aren't supported
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_string(), value.trim());
+        if value.is_empty() {
+            map.insert(key.clone(), Value::Array(Vec::new()));
+            list_key = Some(key);
+        } else {
+            map.insert(key, yaml_scalar(value));
+            list_key = None;
+        }
+    }
+
+    // Keys with nothing listed under them had no value
+    for value in map.values_mut() {
+        if value.as_array().is_some_and(Vec::is_empty) {
+            *value = Value::Null;
+        }
+    }
+    map
+}
+
+fn yaml_scalar(value: &str) -> Value {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return Value::String(inner.to_string());
+        }
+    }
+    if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        return Value::Array(
+            inner
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(yaml_scalar)
+                .collect(),
+        );
+    }
+    match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "null" | "~" => Value::Null,
+        _ => serde_json::from_str::<serde_json::Number>(value)
+            .map(Value::Number)
+            .unwrap_or_else(|_| Value::String(value.to_string())),
+    }
+}
+
+/// Remove the tags from the metadata. Tags may be a list or a string of
+/// comma or space separated words, with or without a leading `#`.
+fn take_tags(metadata: &mut Map<String, Value>) -> Vec<String> {
+    let values = match metadata.remove("tags") {
+        Some(Value::Array(values)) => values,
+        Some(Value::String(text)) => text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .map(|tag| Value::String(tag.to_string()))
+            .collect(),
+        Some(other) => {
+            metadata.insert("tags".to_string(), other);
+            return Vec::new();
+        }
+        None => return Vec::new(),
+    };
+
+    let mut tags: Vec<String> = Vec::new();
+    for value in values {
+        let tag = match value {
+            Value::String(tag) => tag,
+            other => other.to_string(),
+        };
+        let tag = tag.trim().trim_start_matches('#').to_string();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Remove and parse the first of `keys` that holds a date
+fn take_date(metadata: &mut Map<String, Value>, keys: &[&str]) -> Option<DateTime<Utc>> {
+    for key in keys {
+        if let Some(date) = metadata.get(*key).and_then(Value::as_str).and_then(parse_date) {
+            metadata.remove(*key);
+            return Some(date);
+        }
+    }
+    None
+}
+
+fn parse_date(text: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Some(date.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(text, format) {
+            return Some(date.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
@@ -0,0 +1,15 @@
+mod jupyter;
+mod markdown;
+
+pub use jupyter::import_notebook;
+pub use markdown::import_markdown;
+
+use crate::storage::Document;
+
+/// Documents read by an import, and what had to be left out of them
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub documents: Vec<Document>,
+    /// Files and images that couldn't be read, each with why
+    pub skipped: Vec<String>,
+}
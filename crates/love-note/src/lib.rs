@@ -4,8 +4,24 @@
 
 pub mod block;
 pub mod editor;
+/// Writing documents out in other formats.
+pub mod export;
+/// Reading documents written in other formats.
+pub mod import;
+/// Conversion between blocks and Markdown, used for the clipboard and
+/// for importing and exporting documents.
+///
+/// Toggles are written as `<details>` elements and callouts as GitHub
+/// alerts (`> [!NOTE]`), so both survive a round trip. Kinds Markdown has
+/// no syntax for, such as charts, are written as fenced code named after
+/// the kind, except citations, which are fenced as `bibtex`.
+pub mod markdown;
 pub mod storage;
 
-pub use block::{Block, BlockContent, BlockKind, HeadingBlock, TextBlock};
+pub use block::{
+    Block, BlockContent, BlockKind, CalloutBlock, ChartBlock, ChemistryBlock, CitationBlock,
+    CodeBlock, DividerBlock, HeadingBlock, ImageBlock, ListBlock, ListStyle, MathBlock,
+    QuoteBlock, TableBlock, TableData, TextBlock, ToggleBlock,
+};
 pub use editor::LoveNote;
-pub use storage::{Document, Storage, StoredBlock};
+pub use storage::{AttachmentStore, Document, Storage, StoredBlock};
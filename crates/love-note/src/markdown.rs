@@ -0,0 +1,1168 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::block::{
+    CalloutStyle, CodeBlock, ColumnType, HeadingBlock, ImageBlock, MarkKind, Marks, TableData,
+};
+use crate::storage::{AttachmentStore, StoredBlock};
+
+/// Write blocks as Markdown. `image_url` maps an image's attachment name
+/// to the link written for it, and `plugin_block` writes blocks of plugin
+/// kinds, falling back to their text.
+pub fn to_markdown(
+    blocks: &[StoredBlock],
+    image_url: impl Fn(&str) -> String,
+    plugin_block: impl Fn(&StoredBlock) -> Option<String>,
+) -> String {
+    let mut out = String::new();
+    let parents: HashMap<Uuid, Option<Uuid>> = blocks.iter().map(|b| (b.id, b.parent)).collect();
+    // Whether a block with `parent` is nested somewhere inside `ancestor`
+    let within = |mut parent: Option<Uuid>, ancestor: Uuid| {
+        while let Some(id) = parent {
+            if id == ancestor {
+                return true;
+            }
+            parent = parents.get(&id).copied().flatten();
+        }
+        false
+    };
+    // Toggles whose `<details>` element is open, innermost last
+    let mut open: Vec<Uuid> = Vec::new();
+    // Parent, kind and last number of each list still open, outermost first
+    let mut lists: Vec<(Option<Uuid>, &str, usize)> = Vec::new();
+    // Indentation of the blocks nested in each list item
+    let mut indents: HashMap<Uuid, String> = HashMap::new();
+    let mut previous = None;
+
+    for block in blocks {
+        while let Some(&toggle) = open.last()
+            && !within(block.parent, toggle)
+        {
+            open.pop();
+            push_chunk(&mut out, "</details>", false);
+        }
+
+        let kind = block.kind.as_str();
+        let item = matches!(kind, "bullet" | "numbered" | "todo");
+        lists.retain(|(parent, _, _)| parent.is_none_or(|parent| within(block.parent, parent)));
+        let sibling = lists
+            .last()
+            .filter(|(parent, _, _)| *parent == block.parent)
+            .map(|&(_, kind, number)| (kind, number));
+        if sibling.is_some() {
+            lists.pop();
+        }
+        let number = match sibling {
+            Some((list, number)) if item && list == kind => number + 1,
+            _ => 1,
+        };
+        if item {
+            lists.push((block.parent, kind, number));
+        }
+        // Items stay together, including the first item of a nested list
+        let tight = item && (number > 1 || block.parent.is_some_and(|parent| previous == Some(parent)));
+        previous = Some(block.id);
+
+        let indent = block
+            .parent
+            .and_then(|parent| indents.get(&parent))
+            .cloned()
+            .unwrap_or_default();
+        if item {
+            let marker = if kind == "numbered" { number.to_string().len() + 2 } else { 2 };
+            indents.insert(block.id, format!("{}{}", indent, " ".repeat(marker)));
+        }
+
+        let chunk = match kind {
+            "toggle" => {
+                open.push(block.id);
+                format!("<details>\n<summary>{}</summary>", escape(&single_line(&block.content)))
+            }
+            _ => match block_markdown(block, number, &image_url, &plugin_block) {
+                Some(chunk) => chunk,
+                None => continue,
+            },
+        };
+        push_chunk(&mut out, &indent_lines(&indent, &indent, &chunk), tight);
+    }
+    for _ in open {
+        push_chunk(&mut out, "</details>", false);
+    }
+
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+/// Append a chunk, separated from the previous one by a blank line
+/// unless `tight`
+fn push_chunk(out: &mut String, chunk: &str, tight: bool) {
+    if !out.is_empty() {
+        out.push_str(if tight { "\n" } else { "\n\n" });
+    }
+    out.push_str(chunk);
+}
+
+/// Markdown for a single block, or `None` for an empty paragraph
+fn block_markdown(
+    block: &StoredBlock,
+    number: usize,
+    image_url: &impl Fn(&str) -> String,
+    plugin_block: &impl Fn(&StoredBlock) -> Option<String>,
+) -> Option<String> {
+    let text = block.content.as_str();
+    let chunk = match block.kind.as_str() {
+        "heading" => {
+            let level = HeadingBlock::from_props(&block.props).level();
+            format!("{} {}", "#".repeat(level as usize), inline_markdown(&single_line(text), &block.props))
+        }
+        "bullet" => indent_lines("- ", "  ", &formatted_lines(block)),
+        "numbered" => {
+            let marker = format!("{}. ", number);
+            let indent = " ".repeat(marker.len());
+            indent_lines(&marker, &indent, &formatted_lines(block))
+        }
+        "todo" => {
+            let checked = block.props.get("checked").and_then(Value::as_bool) == Some(true);
+            let marker = if checked { "- [x] " } else { "- [ ] " };
+            indent_lines(marker, "  ", &formatted_lines(block))
+        }
+        "code" => {
+            let language = block.props.get("language").and_then(Value::as_str);
+            fenced(language.filter(|l| *l != "plain").unwrap_or(""), text)
+        }
+        "math" => format!("$$\n{}\n$$", text.trim()),
+        "chemistry" => fenced("chemistry", text),
+        "citation" => fenced("bibtex", text),
+        "chart" => {
+            let spec = serde_json::to_string_pretty(&block.props).unwrap_or_default();
+            fenced("chart", &spec)
+        }
+        "table" => table_markdown(&TableData::from_props(&block.props)),
+        "image" => {
+            let file = block.attachment()?;
+            format!("![{}]({})", escape(&single_line(text)), destination(&image_url(file)))
+        }
+        "quote" => indent_lines("> ", "> ", &formatted_lines(block)),
+        "callout" => {
+            let style: CalloutStyle = block
+                .props
+                .get("style")
+                .cloned()
+                .and_then(|style| serde_json::from_value(style).ok())
+                .unwrap_or_default();
+            let alert = match style {
+                CalloutStyle::Info => "NOTE",
+                CalloutStyle::Warning => "WARNING",
+                CalloutStyle::Danger => "CAUTION",
+            };
+            format!("> [!{}]\n{}", alert, indent_lines("> ", "> ", &formatted_lines(block)))
+        }
+        "divider" => "---".to_string(),
+        "text" if text.trim().is_empty() => return None,
+        "text" => formatted_lines(block),
+        _ => match plugin_block(block) {
+            Some(markdown) => markdown,
+            None if text.trim().is_empty() => return None,
+            None => escape_lines(text),
+        },
+    };
+    Some(chunk)
+}
+
+/// Prefix the first line with `first` and the others with `rest`
+fn indent_lines(first: &str, rest: &str, text: &str) -> String {
+    let mut out = String::new();
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(if i == 0 { first } else { rest });
+        out.push_str(line);
+    }
+    out
+}
+
+fn single_line(text: &str) -> String {
+    text.split('\n').collect::<Vec<_>>().join(" ")
+}
+
+/// Fence `text` as code, with a fence longer than any backtick run in it
+fn fenced(info: &str, text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, info, text.trim_end_matches('\n'), fence)
+}
+
+fn table_markdown(table: &TableData) -> String {
+    let cell = |value: &str| single_line(value).replace('|', "\\|");
+    let row = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+
+    let mut lines = vec![
+        row(table.columns.iter().map(|c| cell(&c.name)).collect()),
+        row(table
+            .columns
+            .iter()
+            .map(|c| match c.kind {
+                ColumnType::Number => "---:".to_string(),
+                _ => "---".to_string(),
+            })
+            .collect()),
+    ];
+    for cells in &table.rows {
+        lines.push(row(cells.iter().map(|value| cell(value)).collect()));
+    }
+    lines.join("\n")
+}
+
+/// A block's text with its inline formatting, escaped for any lines
+fn formatted_lines(block: &StoredBlock) -> String {
+    escape_line_starts(&inline_markdown(&block.content, &block.props))
+}
+
+/// Text with its inline formatting written as Markdown. Colors have no
+/// Markdown syntax and are dropped.
+fn inline_markdown(text: &str, props: &Value) -> String {
+    let marks = Marks::from_props(props, text);
+    let mut out = String::new();
+    let mut open: Vec<MarkKind> = Vec::new();
+    // Text of the code span being written, fenced once it closes
+    let mut code = String::new();
+
+    for span in marks.spans() {
+        // Outermost first; code last since nothing can be nested in it
+        let mut wanted = Vec::new();
+        if let Some(url) = span.link {
+            wanted.push(MarkKind::Link(url));
+        }
+        if span.bold {
+            wanted.push(MarkKind::Bold);
+        }
+        if span.italic {
+            wanted.push(MarkKind::Italic);
+        }
+        if span.strikethrough {
+            wanted.push(MarkKind::Strikethrough);
+        }
+        if span.code {
+            wanted.push(MarkKind::Code);
+        }
+
+        let keep = open.iter().zip(&wanted).take_while(|(a, b)| a == b).count();
+        for kind in open.drain(keep..).rev() {
+            out.push_str(&closing_delimiter(&kind, &mut code));
+        }
+        for kind in &wanted[keep..] {
+            out.push_str(opening_delimiter(kind));
+            open.push(kind.clone());
+        }
+        if span.code {
+            code.push_str(&span.content);
+        } else {
+            out.push_str(&escape(&span.content));
+        }
+    }
+    for kind in open.iter().rev() {
+        out.push_str(&closing_delimiter(kind, &mut code));
+    }
+    out
+}
+
+fn opening_delimiter(kind: &MarkKind) -> &'static str {
+    match kind {
+        MarkKind::Bold => "**",
+        MarkKind::Italic => "*",
+        // Written with its text, since the fence depends on it
+        MarkKind::Code => "",
+        MarkKind::Strikethrough => "~~",
+        MarkKind::Link(_) => "[",
+        MarkKind::Color(_) => "",
+    }
+}
+
+/// The delimiter closing `kind`. Closing code writes the `code` taken
+/// since it opened.
+fn closing_delimiter(kind: &MarkKind, code: &mut String) -> String {
+    match kind {
+        MarkKind::Link(url) => format!("]({})", destination(url)),
+        MarkKind::Code => code_span(&std::mem::take(code)),
+        kind => opening_delimiter(kind).to_string(),
+    }
+}
+
+/// Inline code, fenced by more backticks than any run in it. Padding
+/// keeps backticks at either end apart from the fence.
+fn code_span(text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+    let padded = text.starts_with('`')
+        || text.ends_with('`')
+        || (text.starts_with(' ') && text.ends_with(' ') && !text.trim().is_empty());
+    let pad = if padded { " " } else { "" };
+    format!("{}{}{}{}{}", fence, pad, text, pad, fence)
+}
+
+/// A link destination, in angle brackets when it holds characters that
+/// would end a bare one
+fn destination(url: &str) -> String {
+    if url.contains(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '<' | '>')) {
+        format!("<{}>", url.replace('<', "\\<").replace('>', "\\>"))
+    } else {
+        url.to_string()
+    }
+}
+
+/// Escape characters that would otherwise start inline formatting
+fn escape(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let escaped = match c {
+            '\\' | '*' | '`' | '[' | ']' => true,
+            '~' => chars.get(i + 1) == Some(&'~') || (i > 0 && chars[i - 1] == '~'),
+            // Underscores inside words never delimit emphasis
+            '_' => {
+                let word = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric());
+                !(i > 0 && word(chars.get(i - 1)) && word(chars.get(i + 1)))
+            }
+            _ => false,
+        };
+        if escaped {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Escape plain text for a paragraph: inline formatting characters and
+/// anything at the start of a line that would begin another block
+fn escape_lines(text: &str) -> String {
+    escape_line_starts(&escape(text))
+}
+
+fn escape_line_starts(text: &str) -> String {
+    text.split('\n')
+        .map(|line| {
+            let rest = line.trim_start();
+            let indent = &line[..line.len() - rest.len()];
+            if starts_block(rest) {
+                format!("{}\\{}", indent, rest)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whether a line would start something other than a paragraph
+fn starts_block(line: &str) -> bool {
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    ["#", ">", "-", "+", "|", "$$", "<details", "</details>", "~~~", "!["]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+        || (digits > 0 && matches!(line.as_bytes().get(digits), Some(b'.' | b')')))
+}
+
+/// Parse Markdown into blocks. Image blocks hold the link they were
+/// written with as `src`; see [`import_images`].
+pub fn from_markdown(text: &str) -> Vec<StoredBlock> {
+    Parser {
+        lines: text.lines().collect(),
+        pos: 0,
+        blocks: Vec::new(),
+        toggles: Vec::new(),
+        lists: Vec::new(),
+    }
+    .parse()
+}
+
+struct Parser<'a> {
+    lines: Vec<&'a str>,
+    pos: usize,
+    blocks: Vec<StoredBlock>,
+    /// Toggles whose `<details>` element is open, innermost last
+    toggles: Vec<Uuid>,
+    /// Indentation and id of the list items that can hold nested items,
+    /// innermost last
+    lists: Vec<(usize, Uuid)>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(mut self) -> Vec<StoredBlock> {
+        while let Some(&line) = self.lines.get(self.pos) {
+            let trimmed = line.trim();
+            if !trimmed.is_empty() && list_item(line).is_none() {
+                self.lists.clear();
+            }
+            if trimmed.is_empty() {
+                self.pos += 1;
+            } else if let Some((fence, info)) = fence_start(trimmed) {
+                self.fenced(fence, info);
+            } else if trimmed.starts_with("$$") {
+                self.math();
+            } else if trimmed.starts_with("<details") {
+                self.details();
+            } else if trimmed == "</details>" {
+                self.toggles.pop();
+                self.pos += 1;
+            } else if let Some((level, rest)) = HeadingBlock::parse_shortcut(trimmed) {
+                let text = rest.trim_end_matches('#').trim();
+                self.push(formatted_block("heading", text, json!({ "level": level })));
+                self.pos += 1;
+            } else if is_divider(trimmed) {
+                self.push(StoredBlock::new("divider", ""));
+                self.pos += 1;
+            } else if trimmed.starts_with('>') {
+                self.quote();
+            } else if list_item(line).is_some() {
+                self.list_item();
+            } else if let Some((alt, src)) = image_link(trimmed) {
+                self.push(StoredBlock::new("image", plain(alt)).with_props(json!({ "src": src })));
+                self.pos += 1;
+            } else if self.starts_table() {
+                self.table();
+            } else {
+                self.paragraph();
+            }
+        }
+        self.blocks
+    }
+
+    fn push(&mut self, mut block: StoredBlock) {
+        block.parent = self.toggles.last().copied();
+        self.blocks.push(block);
+    }
+
+    /// Lines from the current one while `keep` holds for them
+    fn take_while(&mut self, mut keep: impl FnMut(&str) -> bool) -> Vec<&'a str> {
+        let start = self.pos;
+        while self.lines.get(self.pos).is_some_and(|line| keep(line)) {
+            self.pos += 1;
+        }
+        self.lines[start..self.pos].to_vec()
+    }
+
+    fn fenced(&mut self, fence: &str, info: &str) {
+        self.pos += 1;
+        let fence_char = fence.chars().next().unwrap_or('`');
+        let closes = |line: &str| {
+            let line = line.trim();
+            line.len() >= fence.len() && line.chars().all(|c| c == fence_char)
+        };
+        let text = self.take_while(|line| !closes(line)).join("\n");
+        self.pos += 1;
+
+        let language = info.split_whitespace().next().unwrap_or("").to_lowercase();
+        let block = match language.as_str() {
+            "math" => StoredBlock::new("math", text),
+            "chemistry" => StoredBlock::new("chemistry", text),
+            "bibtex" => StoredBlock::new("citation", text),
+            "chart" => match serde_json::from_str::<Value>(&text) {
+                Ok(spec) => StoredBlock::new("chart", "").with_props(spec),
+                Err(_) => code_block(&language, text),
+            },
+            _ => code_block(&language, text),
+        };
+        self.push(block);
+    }
+
+    fn math(&mut self) {
+        let first = self.lines[self.pos];
+        let first = first.trim().trim_start_matches("$$");
+        self.pos += 1;
+        let text = if let Some(inline) = first.strip_suffix("$$") {
+            inline.to_string()
+        } else {
+            let mut lines = vec![first];
+            lines.extend(self.take_while(|line| !line.trim_end().ends_with("$$")));
+            if let Some(&last) = self.lines.get(self.pos) {
+                lines.push(last.trim_end().trim_end_matches("$$"));
+                self.pos += 1;
+            }
+            lines.join("\n")
+        };
+        self.push(StoredBlock::new("math", text.trim()));
+    }
+
+    /// A `<details>` element becomes a toggle holding the blocks up to
+    /// its closing tag
+    fn details(&mut self) {
+        let line = self.lines[self.pos];
+        self.pos += 1;
+        let mut rest = line.split_once('>').map_or("", |(_, rest)| rest).trim();
+        if rest.is_empty() {
+            self.take_while(|line| line.trim().is_empty());
+            if let Some(&next) = self.lines.get(self.pos)
+                && next.trim().starts_with("<summary")
+            {
+                rest = next.trim();
+                self.pos += 1;
+            }
+        }
+        let summary = rest
+            .split_once('>')
+            .filter(|_| rest.starts_with("<summary"))
+            .map_or("", |(_, summary)| summary);
+        let summary = summary.split("</summary>").next().unwrap_or("").trim();
+
+        let toggle = StoredBlock::new("toggle", plain(summary));
+        let id = toggle.id;
+        self.push(toggle);
+        self.toggles.push(id);
+    }
+
+    fn quote(&mut self) {
+        let lines: Vec<&str> = self
+            .take_while(|line| line.trim_start().starts_with('>'))
+            .into_iter()
+            .map(|line| {
+                let line = line.trim_start().trim_start_matches('>');
+                line.strip_prefix(' ').unwrap_or(line)
+            })
+            .collect();
+
+        let style = lines.first().and_then(|first| match first.trim() {
+            "[!NOTE]" | "[!TIP]" | "[!IMPORTANT]" => Some(CalloutStyle::Info),
+            "[!WARNING]" => Some(CalloutStyle::Warning),
+            "[!CAUTION]" => Some(CalloutStyle::Danger),
+            _ => None,
+        });
+        let block = match style {
+            Some(style) => formatted_block("callout", &lines[1..].join("\n"), json!({ "style": style })),
+            None => formatted_block("quote", &lines.join("\n"), Value::Null),
+        };
+        self.push(block);
+    }
+
+    /// A list item and the indented lines continuing it. Items indented
+    /// past the marker of an earlier one are nested in it.
+    fn list_item(&mut self) {
+        let line = self.lines[self.pos];
+        let Some((kind, checked, first)) = list_item(line) else {
+            return;
+        };
+        let indent = line.len() - line.trim_start().len();
+        while self.lists.last().is_some_and(|(outer, _)| indent < outer + 2) {
+            self.lists.pop();
+        }
+        let parent = self.lists.last().map(|(_, id)| *id);
+        self.pos += 1;
+        let mut lines = vec![first];
+        lines.extend(
+            self.take_while(|line| line.starts_with("  ") && !line.trim().is_empty() && list_item(line).is_none())
+                .into_iter()
+                .map(str::trim),
+        );
+
+        let props = match kind {
+            "todo" => json!({ "checked": checked }),
+            _ => Value::Null,
+        };
+        let block = formatted_block(kind, &lines.join("\n"), props);
+        let id = block.id;
+        self.push(block);
+        if let Some(block) = self.blocks.last_mut()
+            && parent.is_some()
+        {
+            block.parent = parent;
+        }
+        self.lists.push((indent, id));
+    }
+
+    /// Whether a pipe table starts here: a row followed by a delimiter row
+    fn starts_table(&self) -> bool {
+        self.lines[self.pos].contains('|')
+            && self
+                .lines
+                .get(self.pos + 1)
+                .is_some_and(|next| is_delimiter_row(next))
+    }
+
+    fn table(&mut self) {
+        let header = table_cells(self.lines[self.pos]);
+        self.pos += 2;
+        let rows = self
+            .take_while(|line| line.contains('|') && !line.trim().is_empty())
+            .into_iter()
+            .map(table_cells)
+            .collect();
+        let data = TableData::with_header(header, rows);
+        let props = serde_json::to_value(&data).unwrap_or_default();
+        self.push(StoredBlock::new("table", "").with_props(props));
+    }
+
+    /// Lines up to a blank line or the start of another block
+    fn paragraph(&mut self) {
+        let first = self.lines[self.pos];
+        self.pos += 1;
+        let mut lines = vec![first.trim()];
+        while let Some(&line) = self.lines.get(self.pos) {
+            let trimmed = line.trim();
+            if trimmed.is_empty()
+                || fence_start(trimmed).is_some()
+                || starts_block(trimmed)
+                || self.starts_table()
+            {
+                break;
+            }
+            lines.push(trimmed);
+            self.pos += 1;
+        }
+        self.push(formatted_block("text", &lines.join("\n"), Value::Null));
+    }
+}
+
+/// A code block in `language`, given as an id or file extension.
+/// Languages without highlighting are plain text.
+pub(crate) fn code_block(language: &str, text: String) -> StoredBlock {
+    let language = CodeBlock::language_id(language).unwrap_or("plain");
+    StoredBlock::new("code", text).with_props(json!({ "language": language }))
+}
+
+/// The fence and info string of a line opening fenced code
+fn fence_start(line: &str) -> Option<(&str, &str)> {
+    let fence_char = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.chars().take_while(|c| *c == fence_char).count();
+    if len < 3 {
+        return None;
+    }
+    let (fence, info) = line.split_at(len);
+    (fence_char == '~' || !info.contains('`')).then_some((fence, info.trim()))
+}
+
+/// `---`, `***` or `___`, possibly spaced out
+fn is_divider(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3 && ['-', '*', '_'].iter().any(|m| chars.iter().all(|c| c == m))
+}
+
+/// The kind, checked state and text of a list item line
+fn list_item(line: &str) -> Option<(&'static str, bool, &str)> {
+    let line = line.trim_start();
+    if let Some(rest) = ["- ", "* ", "+ "].iter().find_map(|m| line.strip_prefix(m)) {
+        for (box_, checked) in [("[ ] ", false), ("[x] ", true), ("[X] ", true)] {
+            if let Some(text) = rest.strip_prefix(box_) {
+                return Some(("todo", checked, text));
+            }
+        }
+        return Some(("bullet", false, rest));
+    }
+
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    if !(1..=9).contains(&digits) {
+        return None;
+    }
+    let rest = line[digits..]
+        .strip_prefix(". ")
+        .or_else(|| line[digits..].strip_prefix(") "))?;
+    Some(("numbered", false, rest))
+}
+
+/// The alt text and link of a line holding only an image
+fn image_link(line: &str) -> Option<(&str, String)> {
+    let rest = line.strip_prefix('!')?;
+    let (alt, src, len) = link(rest)?;
+    (len == rest.len() && !src.is_empty()).then_some((alt, src))
+}
+
+fn is_delimiter_row(line: &str) -> bool {
+    let cells = table_cells(line);
+    line.contains('|')
+        && line.contains('-')
+        && cells.iter().all(|cell| {
+            let cell = cell.trim_matches(':');
+            !cell.is_empty() && cell.chars().all(|c| c == '-')
+        })
+}
+
+/// Cells of a pipe table row, unescaped
+fn table_cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').filter(|l| !l.ends_with('\\')).unwrap_or(line);
+
+    let mut cells = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('|') => cells.last_mut().unwrap().push('|'),
+                Some(next) => {
+                    let cell = cells.last_mut().unwrap();
+                    cell.push('\\');
+                    cell.push(next);
+                }
+                None => cells.last_mut().unwrap().push('\\'),
+            },
+            '|' => cells.push(String::new()),
+            c => cells.last_mut().unwrap().push(c),
+        }
+    }
+    cells.into_iter().map(|cell| plain(cell.trim())).collect()
+}
+
+/// A block of `kind` holding `text` with its inline formatting as marks,
+/// stored alongside `props`
+fn formatted_block(kind: &str, text: &str, props: Value) -> StoredBlock {
+    let (text, marks) = parse_inline(text);
+    let mut formatted = Marks::from_props(&Value::Null, &text);
+    for (range, kind) in marks {
+        formatted.add(range, kind);
+    }
+    StoredBlock::new(kind, text).with_props(formatted.add_to_props(props))
+}
+
+/// Text with inline formatting removed, for blocks that can't hold it
+fn plain(text: &str) -> String {
+    parse_inline(text).0
+}
+
+/// Split Markdown inline formatting off `text`, returning the plain text
+/// and the formatted byte ranges of it
+fn parse_inline(text: &str) -> (String, Vec<(std::ops::Range<usize>, MarkKind)>) {
+    let mut out = String::new();
+    let mut marks = Vec::new();
+    // Open delimiters with where their text starts
+    let mut open: Vec<(&str, usize)> = Vec::new();
+    let mut i = 0;
+
+    while i < text.len() {
+        let rest = &text[i..];
+
+        if let Some(next) = rest.strip_prefix('\\')
+            && let Some(c) = next.chars().next()
+            && c.is_ascii_punctuation()
+        {
+            out.push(c);
+            i += 1 + c.len_utf8();
+            continue;
+        }
+
+        if rest.starts_with('`') {
+            let ticks = rest.chars().take_while(|c| *c == '`').count();
+            if let Some(end) = closing_fence(&rest[ticks..], ticks) {
+                let code = &rest[ticks..ticks + end];
+                let code = match code.strip_prefix(' ').and_then(|c| c.strip_suffix(' ')) {
+                    Some(inner) if !code.trim().is_empty() => inner,
+                    _ => code,
+                };
+                let start = out.len();
+                out.push_str(code);
+                marks.push((start..out.len(), MarkKind::Code));
+                i += ticks * 2 + end;
+                continue;
+            }
+        }
+
+        if rest.starts_with('[')
+            && let Some((label, url, len)) = link(rest)
+        {
+            let (label, inner) = parse_inline(label);
+            let start = out.len();
+            out.push_str(&label);
+            marks.extend(inner.into_iter().map(|(r, k)| (r.start + start..r.end + start, k)));
+            marks.push((start..out.len(), MarkKind::Link(url)));
+            i += len;
+            continue;
+        }
+
+        let delimiter = ["**", "__", "~~", "*", "_"]
+            .into_iter()
+            .find(|d| rest.starts_with(d))
+            .filter(|d| !d.starts_with('_') || flanks_word(text, i, d.len()));
+        if let Some(delimiter) = delimiter {
+            // Delimiters hug the text they format, so `2 * 3 * 4` stays
+            // literal
+            let can_close = text[..i].chars().next_back().is_some_and(|c| !c.is_whitespace());
+            let can_open = rest[delimiter.len()..]
+                .chars()
+                .next()
+                .is_some_and(|c| !c.is_whitespace());
+            if can_close && let Some(at) = open.iter().rposition(|(d, _)| *d == delimiter) {
+                let (_, start) = open.remove(at);
+                let kind = match delimiter {
+                    "**" | "__" => MarkKind::Bold,
+                    "~~" => MarkKind::Strikethrough,
+                    _ => MarkKind::Italic,
+                };
+                marks.push((start..out.len(), kind));
+                i += delimiter.len();
+                continue;
+            }
+            if can_open && rest[delimiter.len()..].contains(delimiter) {
+                open.push((delimiter, out.len()));
+                i += delimiter.len();
+                continue;
+            }
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        out.push(c);
+        i += c.len_utf8();
+    }
+
+    marks.retain(|(range, _)| !range.is_empty());
+    (out, marks)
+}
+
+/// Whether an underscore delimiter at `i` touches a word boundary, since
+/// underscores inside words are literal
+fn flanks_word(text: &str, i: usize, len: usize) -> bool {
+    let word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    let before = text[..i].chars().next_back();
+    let after = text[i + len..].chars().next();
+    !(word(before) && word(after))
+}
+
+/// Where a run of exactly `ticks` backticks starts in `text`
+fn closing_fence(text: &str, ticks: usize) -> Option<usize> {
+    let mut i = 0;
+    while let Some(start) = text[i..].find('`').map(|at| i + at) {
+        let run = text[start..].chars().take_while(|c| *c == '`').count();
+        if run == ticks {
+            return Some(start);
+        }
+        i = start + run;
+    }
+    None
+}
+
+/// The label, URL and length of a `[label](url)` link at the start of
+/// `text`
+fn link(text: &str) -> Option<(&str, String, usize)> {
+    let mut depth = 0;
+    let mut label_end = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    label_end = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let label_end = label_end?;
+    let after = text[label_end + 1..].strip_prefix('(')?;
+    let (url, url_len) = link_destination(after)?;
+    // Skip any title after the destination
+    let close = url_len + after[url_len..].find(')')?;
+    let len = label_end + 2 + close + 1;
+    Some((&text[1..label_end], url, len))
+}
+
+/// The unescaped destination at the start of `text` and its length,
+/// either in angle brackets or up to whitespace or `)`
+fn link_destination(text: &str) -> Option<(String, usize)> {
+    let Some(inner) = text.strip_prefix('<') else {
+        let len = text.find(|c: char| c == ')' || c.is_whitespace())?;
+        return Some((text[..len].to_string(), len));
+    };
+    let mut url = String::new();
+    let mut escaped = false;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '\n' => return None,
+            _ if escaped => {
+                if !c.is_ascii_punctuation() {
+                    url.push('\\');
+                }
+                url.push(c);
+                escaped = false;
+            }
+            '\\' => escaped = true,
+            '>' => return Some((url, i + 2)),
+            _ => url.push(c),
+        }
+    }
+    None
+}
+
+/// Copy the local images of blocks parsed from Markdown into the store.
+/// Paths are resolved against `base` and must lead to an image inside
+/// `root`, so a note can't pull other local files into the store.
+/// Remote images and images that can't be read become text blocks
+/// linking to them. Returns why each local image couldn't be read.
+pub fn import_images(
+    blocks: &mut [StoredBlock],
+    images: &AttachmentStore,
+    base: &Path,
+    root: &Path,
+) -> Vec<String> {
+    let mut failed = Vec::new();
+    for block in blocks.iter_mut().filter(|b| b.kind == "image") {
+        let Some(src) = block.props.get("src").and_then(Value::as_str).map(str::to_string) else {
+            continue;
+        };
+        let local = match src.strip_prefix("file://") {
+            Some(path) => Some(path),
+            None => (!src.contains("://") && !src.starts_with("data:")).then_some(src.as_str()),
+        };
+        // Remote images are kept as links rather than downloaded
+        let file = local.map(|path| {
+            local_image(&percent_decode(path), base, root).and_then(|path| images.import_file(&path))
+        });
+        match file {
+            Some(Ok(file)) => block.props = json!({ "file": file }),
+            file => {
+                if let Some(Err(e)) = file {
+                    failed.push(format!("{}: {}", src, e));
+                }
+                link_image(block, src);
+            }
+        }
+    }
+    failed
+}
+
+/// The image file at `src`, relative to `base`, if it lies inside `root`
+fn local_image(src: &str, base: &Path, root: &Path) -> Result<PathBuf> {
+    let path = Path::new(src);
+    let is_image = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ImageBlock::EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    if !is_image {
+        bail!("Not an image");
+    }
+    // Resolving links and `..` first, so neither can lead outside
+    let path = base
+        .join(path)
+        .canonicalize()
+        .with_context(|| format!("Failed to read {}", src))?;
+    let root = root
+        .canonicalize()
+        .with_context(|| format!("Failed to read {}", root.display()))?;
+    if !path.starts_with(&root) {
+        bail!("Outside the imported folder");
+    }
+    Ok(path)
+}
+
+/// Decode the `%XX` escapes of a URL
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes[i] == b'%'
+            && bytes.get(i + 1..i + 3).is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit));
+        if escaped {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            decoded.push(u8::from_str_radix(hex, 16).unwrap_or_default());
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Store the images of blocks pasted as Markdown. Only images embedded as
+/// `data:` URIs and files already in the store are resolved, as pasted
+/// paths don't say what they are relative to; other images become text
+/// blocks linking to them.
+pub fn paste_images(blocks: &mut [StoredBlock], images: &AttachmentStore) -> Result<()> {
+    for block in blocks.iter_mut().filter(|b| b.kind == "image") {
+        let Some(src) = block.props.get("src").and_then(Value::as_str).map(str::to_string) else {
+            continue;
+        };
+        let file = match src.strip_prefix("data:") {
+            Some(data) => match decode_data_uri(data) {
+                Some((bytes, extension)) => Some(images.import_bytes(&bytes, &extension)?),
+                None => None,
+            },
+            None => stored_name(&src, images),
+        };
+        match file {
+            Some(file) => block.props = json!({ "file": file }),
+            None => link_image(block, src),
+        }
+    }
+    Ok(())
+}
+
+/// The contents and file extension of a base64 `data:` URI, without its
+/// scheme
+fn decode_data_uri(data: &str) -> Option<(Vec<u8>, String)> {
+    let (header, encoded) = data.split_once(',')?;
+    let media_type = header.strip_suffix(";base64")?;
+    let subtype = media_type.strip_prefix("image/")?;
+    let extension = match subtype {
+        "jpeg" => "jpg",
+        "svg+xml" => "svg",
+        subtype => subtype,
+    };
+    let encoded: String = encoded.split_whitespace().collect();
+    let bytes = BASE64.decode(encoded).ok()?;
+    Some((bytes, extension.to_string()))
+}
+
+/// The name of the stored file `src` refers to, by name or by its path in
+/// the store, if there is one
+fn stored_name(src: &str, images: &AttachmentStore) -> Option<String> {
+    let path = Path::new(src.strip_prefix("file://").unwrap_or(src));
+    let name = path.file_name()?.to_str()?;
+    let in_store = path.parent().is_none_or(|parent| {
+        parent.as_os_str().is_empty() || parent == images.root()
+    });
+    (in_store && !name.ends_with(".tmp") && images.path(name).is_file()).then(|| name.to_string())
+}
+
+/// Turn an image block that can't be stored into a text block linking to
+/// `src`, captioned like the image
+fn link_image(block: &mut StoredBlock, src: String) {
+    let caption = if block.content.is_empty() {
+        src.clone()
+    } else {
+        block.content.clone()
+    };
+    let mut marks = Marks::from_props(&Value::Null, &caption);
+    marks.add(0..caption.len(), MarkKind::Link(src));
+    block.kind = "text".to_string();
+    block.content = caption;
+    block.props = marks
+        .to_props()
+        .map_or(Value::Null, |marks| json!({ "marks": marks }));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use super::*;
+
+    fn marked(kind: &str, text: &str, marks: Vec<(Range<usize>, MarkKind)>) -> StoredBlock {
+        let mut formatted = Marks::from_props(&Value::Null, text);
+        for (range, kind) in marks {
+            formatted.add(range, kind);
+        }
+        StoredBlock::new(kind, text).with_props(formatted.add_to_props(Value::Null))
+    }
+
+    fn write(blocks: &[StoredBlock]) -> String {
+        to_markdown(blocks, |file| format!("images/{}", file), |_| None)
+    }
+
+    /// What a round trip has to keep: kind, text, properties and the
+    /// index of the parent
+    fn summary(blocks: &[StoredBlock]) -> Vec<(String, String, Value, Option<usize>)> {
+        blocks
+            .iter()
+            .map(|block| {
+                let parent = block.parent.and_then(|id| blocks.iter().position(|b| b.id == id));
+                (block.kind.clone(), block.content.clone(), block.props.clone(), parent)
+            })
+            .collect()
+    }
+
+    fn assert_round_trip(blocks: &[StoredBlock]) {
+        let markdown = write(blocks);
+        assert_eq!(summary(&from_markdown(&markdown)), summary(blocks), "{}", markdown);
+    }
+
+    #[test]
+    fn round_trips_block_kinds() {
+        let mut code = StoredBlock::new("code", "fn main() {}").with_props(json!({ "language": "rust" }));
+        code.content.push_str("\n```\nnot the end");
+        assert_round_trip(&[
+            StoredBlock::new("heading", "Title").with_props(json!({ "level": 2 })),
+            StoredBlock::new("text", "Plain *not bold* [text]"),
+            StoredBlock::new("todo", "Done").with_props(json!({ "checked": true })),
+            StoredBlock::new("todo", "Open").with_props(json!({ "checked": false })),
+            code,
+            StoredBlock::new("math", r"E = mc^2"),
+            StoredBlock::new("quote", "Quoted\nover two lines"),
+            StoredBlock::new("callout", "Careful").with_props(json!({ "style": "warning" })),
+            StoredBlock::new("divider", ""),
+        ]);
+    }
+
+    #[test]
+    fn round_trips_inline_formatting() {
+        assert_round_trip(&[marked(
+            "text",
+            "bold italic code link",
+            vec![
+                (0..4, MarkKind::Bold),
+                (5..11, MarkKind::Italic),
+                (12..16, MarkKind::Code),
+                (17..21, MarkKind::Link("https://example.com/a_(b) c".to_string())),
+            ],
+        )]);
+    }
+
+    #[test]
+    fn fences_code_spans_holding_backticks() {
+        let block = marked("text", "run `ls` or ``", vec![(4..8, MarkKind::Code), (12..14, MarkKind::Code)]);
+        let markdown = write(std::slice::from_ref(&block));
+        assert_eq!(markdown, "run `` `ls` `` or ``` `` ```\n");
+        assert_round_trip(&[block]);
+    }
+
+    #[test]
+    fn round_trips_nested_lists_and_toggles() {
+        let mut blocks = vec![
+            StoredBlock::new("bullet", "Outer"),
+            StoredBlock::new("numbered", "First"),
+            StoredBlock::new("numbered", "Second"),
+            StoredBlock::new("bullet", "Deeper"),
+            StoredBlock::new("bullet", "Outer again"),
+            StoredBlock::new("toggle", "More"),
+            StoredBlock::new("text", "Hidden"),
+        ];
+        blocks[1].parent = Some(blocks[0].id);
+        blocks[2].parent = Some(blocks[0].id);
+        blocks[3].parent = Some(blocks[2].id);
+        blocks[6].parent = Some(blocks[5].id);
+
+        let markdown = write(&blocks);
+        assert!(markdown.starts_with("- Outer\n  1. First\n  2. Second\n     - Deeper\n- Outer again\n"));
+        assert_round_trip(&blocks);
+    }
+
+    #[test]
+    fn round_trips_tables() {
+        let data = TableData::with_header(
+            vec!["Name".to_string(), "Note".to_string()],
+            vec![vec!["a|b".to_string(), "c".to_string()]],
+        );
+        let props = serde_json::to_value(&data).unwrap();
+        assert_round_trip(&[StoredBlock::new("table", "").with_props(props)]);
+    }
+
+    #[test]
+    fn writes_image_paths_that_need_brackets() {
+        let image = StoredBlock::new("image", "Plot").with_props(json!({ "file": "a b.png" }));
+        let markdown = write(&[image]);
+        assert_eq!(markdown, "![Plot](<images/a b.png>)\n");
+
+        let parsed = from_markdown(&markdown);
+        assert_eq!(parsed[0].kind, "image");
+        assert_eq!(parsed[0].props["src"], "images/a b.png");
+    }
+}
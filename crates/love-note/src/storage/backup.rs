@@ -0,0 +1,395 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, Timelike, Utc};
+use love_note_plugin_api::PluginInfo;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+use zip::read::ZipFile;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::files::{content_hash, Hashing};
+use super::{AttachmentStore, Document};
+
+/// Version of the archive layout, raised when it changes in a way older
+/// versions can't read
+pub const BACKUP_FORMAT: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+
+/// Automatic backups are named after when they were made, in UTC
+const BACKUP_NAME: &str = "lovenote-%Y%m%d-%H%M%S";
+
+/// Contents of `manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format: u32,
+    /// Version of Love Note that wrote the archive
+    pub app_version: String,
+    pub created_at: DateTime<Utc>,
+    pub documents: Vec<ArchivedFile>,
+    pub attachments: Vec<ArchivedFile>,
+    #[serde(default)]
+    pub settings: Map<String, Value>,
+    #[serde(default)]
+    pub plugins: Vec<PluginInfo>,
+    /// Toggle blocks shown collapsed
+    #[serde(default)]
+    pub collapsed: Vec<Uuid>,
+}
+
+/// A file in the archive and the SHA-256 of its contents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    pub path: String,
+    pub sha256: String,
+}
+
+/// How a backup is combined with what's already stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreMode {
+    /// Keep stored data, taking each document from whichever side was
+    /// edited last and adding what's missing. Stored settings and plugins
+    /// win over the backup's.
+    Merge,
+    /// Discard stored data and restore the backup as it is
+    Replace,
+}
+
+/// How often backups are made automatically and how many are kept, read
+/// from the `backup.interval_hours` and `backup.keep` settings
+#[derive(Debug, Clone, Copy)]
+pub struct BackupSchedule {
+    /// Hours between backups; 0 turns automatic backups off
+    pub interval_hours: u64,
+    pub keep: usize,
+}
+
+impl BackupSchedule {
+    pub const INTERVAL_SETTING: &str = "backup.interval_hours";
+    pub const KEEP_SETTING: &str = "backup.keep";
+
+    pub fn from_settings(settings: &Map<String, Value>) -> Self {
+        let number = |key: &str, default: u64| {
+            settings.get(key).and_then(Value::as_u64).unwrap_or(default)
+        };
+        Self {
+            interval_hours: number(Self::INTERVAL_SETTING, 24),
+            keep: number(Self::KEEP_SETTING, 7).max(1) as usize,
+        }
+    }
+}
+
+/// Everything in the vault apart from attachment files
+#[derive(Default)]
+pub(super) struct Vault {
+    pub documents: Vec<Document>,
+    pub collapsed: HashSet<Uuid>,
+    pub settings: Map<String, Value>,
+    pub plugins: Vec<PluginInfo>,
+}
+
+/// Write `vault` and every file in `images` to a zip archive at `path`
+pub(super) fn write_archive(path: &Path, vault: &Vault, images: &AttachmentStore) -> Result<BackupManifest> {
+    // Write next to the destination first so a failed backup never
+    // replaces a good one
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let file = File::create(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    let now = Local::now();
+    let mut options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // Zip times count from 1980 and have no time zone
+    if let Ok(time) = zip::DateTime::from_date_and_time(
+        now.year().clamp(1980, 2107) as u16,
+        now.month() as u8,
+        now.day() as u8,
+        now.hour() as u8,
+        now.minute() as u8,
+        now.second() as u8,
+    ) {
+        options = options.last_modified_time(time);
+    }
+
+    let mut documents = Vec::with_capacity(vault.documents.len());
+    for document in &vault.documents {
+        let path = format!("documents/{}.json", document.id);
+        let bytes = serde_json::to_vec_pretty(document)?;
+        zip.start_file(path.as_str(), options)?;
+        zip.write_all(&bytes)?;
+        documents.push(ArchivedFile {
+            sha256: content_hash(&bytes),
+            path,
+        });
+    }
+
+    let mut names = images.list()?;
+    names.retain(|name| !name.ends_with(".tmp"));
+    names.sort();
+    let mut attachments = Vec::with_capacity(names.len());
+    for name in names {
+        let mut file = File::open(images.path(&name))
+            .with_context(|| format!("Failed to read attachment {}", name))?;
+        // Files of 4 GiB and more need zip64 headers
+        let large = file.metadata()?.len() >= u32::MAX as u64;
+        let path = format!("images/{}", name);
+        zip.start_file(path.as_str(), options.large_file(large))?;
+        let mut out = Hashing::new(&mut zip);
+        io::copy(&mut file, &mut out).with_context(|| format!("Failed to read attachment {}", name))?;
+        attachments.push(ArchivedFile {
+            sha256: out.finish()?,
+            path,
+        });
+    }
+
+    let mut collapsed: Vec<Uuid> = vault.collapsed.iter().copied().collect();
+    collapsed.sort();
+    let manifest = BackupManifest {
+        format: BACKUP_FORMAT,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+        documents,
+        attachments,
+        settings: vault.settings.clone(),
+        plugins: vault.plugins.clone(),
+        collapsed,
+    };
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+    zip.finish()?.flush()?;
+
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(manifest)
+}
+
+/// Read and check the archive at `path`, storing its attachments in
+/// `images`. Nothing is stored unless every file in the archive is intact.
+/// Documents referring to an attachment stored under another name are
+/// pointed at the stored file.
+pub(super) fn read_archive(path: &Path, images: &AttachmentStore) -> Result<Vault> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut zip = ZipArchive::new(BufReader::new(file))
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let manifest: BackupManifest = serde_json::from_reader(entry(&mut zip, MANIFEST)?)
+        .context("Failed to read the backup manifest")?;
+    if manifest.format > BACKUP_FORMAT {
+        bail!(
+            "The backup was made by Love Note {} and needs a newer version to restore",
+            manifest.app_version
+        );
+    }
+
+    let mut documents = Vec::with_capacity(manifest.documents.len());
+    for file in &manifest.documents {
+        let mut bytes = Vec::new();
+        entry(&mut zip, &file.path)?
+            .read_to_end(&mut bytes)
+            .with_context(|| format!("Failed to read {}", file.path))?;
+        if content_hash(&bytes) != file.sha256 {
+            bail!("{} doesn't match its checksum", file.path);
+        }
+        let document: Document = serde_json::from_slice(&bytes)
+            .with_context(|| format!("Failed to read {}", file.path))?;
+        documents.push(document);
+    }
+
+    // Check every attachment before storing any, without holding them
+    // in memory
+    let mut attachments = Vec::with_capacity(manifest.attachments.len());
+    for file in &manifest.attachments {
+        let name = file
+            .path
+            .strip_prefix("images/")
+            .filter(|name| !name.is_empty() && !name.contains(['/', '\\']))
+            .with_context(|| format!("Unexpected attachment path {}", file.path))?;
+        let mut out = Hashing::new(io::sink());
+        io::copy(&mut entry(&mut zip, &file.path)?, &mut out)
+            .with_context(|| format!("Failed to read {}", file.path))?;
+        if out.finish()? != file.sha256 {
+            bail!("{} doesn't match its checksum", file.path);
+        }
+        attachments.push((name, &file.path));
+    }
+
+    // Older stores may have named files differently
+    let mut renamed = HashMap::new();
+    for (name, path) in attachments {
+        let extension = name.split_once('.').map_or("", |(_, extension)| extension);
+        let stored = images.import_reader(&mut entry(&mut zip, path)?, extension)?;
+        if stored != name {
+            renamed.insert(name, stored);
+        }
+    }
+    for block in documents.iter_mut().flat_map(|document| document.blocks.iter_mut()) {
+        if let Some(stored) = block.attachment().and_then(|file| renamed.get(file)) {
+            block.props["file"] = Value::String(stored.clone());
+        }
+    }
+
+    Ok(Vault {
+        documents,
+        collapsed: manifest.collapsed.into_iter().collect(),
+        settings: manifest.settings,
+        plugins: manifest.plugins,
+    })
+}
+
+/// A file in the archive, opened for reading
+fn entry<'a, R: Read + Seek>(zip: &'a mut ZipArchive<R>, path: &str) -> Result<ZipFile<'a, R>> {
+    zip.by_name(path)
+        .with_context(|| format!("{} is missing from the archive", path))
+}
+
+/// Automatic backups in `dir` with when they were made, oldest first
+pub(super) fn backups(dir: &Path) -> Result<Vec<(DateTime<Utc>, PathBuf)>> {
+    let mut backups = Vec::new();
+    if !dir.exists() {
+        return Ok(backups);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "zip") {
+            continue;
+        }
+        let made = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| NaiveDateTime::parse_from_str(stem, BACKUP_NAME).ok());
+        if let Some(made) = made {
+            backups.push((made.and_utc(), path));
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Backups to delete once `new` has been written, so that `keep`
+/// backups remain counting it. `backups` are the ones there were before,
+/// oldest first; `new` itself is never among those returned.
+pub(super) fn outdated(mut backups: Vec<(DateTime<Utc>, PathBuf)>, new: &Path, keep: usize) -> Vec<PathBuf> {
+    backups.retain(|(_, path)| path != new);
+    let excess = (backups.len() + 1).saturating_sub(keep.max(1));
+    backups.into_iter().take(excess).map(|(_, path)| path).collect()
+}
+
+/// File name for an automatic backup made at `time`
+pub(super) fn backup_name(time: DateTime<Utc>) -> String {
+    format!("{}.zip", time.format(BACKUP_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::storage::StoredBlock;
+
+    /// An empty directory of its own under the system temp directory
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lovenote-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn backup_at(dir: &Path, day: u32) -> (DateTime<Utc>, PathBuf) {
+        let time = Utc.with_ymd_and_hms(2026, 1, day, 12, 0, 0).unwrap();
+        (time, dir.join(backup_name(time)))
+    }
+
+    #[test]
+    fn prunes_the_oldest_backups() {
+        let dir = Path::new("backups");
+        let old: Vec<_> = (1..=4).map(|day| backup_at(dir, day)).collect();
+        let (_, new) = backup_at(dir, 5);
+
+        let paths = |days: &[usize]| days.iter().map(|day| old[day - 1].1.clone()).collect::<Vec<_>>();
+        assert_eq!(outdated(old.clone(), &new, 3), paths(&[1, 2]));
+        assert_eq!(outdated(old.clone(), &new, 10), paths(&[]));
+        // The new backup is always kept
+        assert_eq!(outdated(old.clone(), &new, 0), paths(&[1, 2, 3, 4]));
+        assert_eq!(outdated(old.clone(), &old[3].1, 1), paths(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn lists_backups_oldest_first() {
+        let dir = scratch_dir();
+        let (_, second) = backup_at(&dir, 2);
+        let (_, first) = backup_at(&dir, 1);
+        for path in [&second, &first, &dir.join("notes.zip"), &dir.join("other.txt")] {
+            std::fs::write(path, b"").unwrap();
+        }
+
+        let listed: Vec<_> = backups(&dir).unwrap().into_iter().map(|(_, path)| path).collect();
+        assert_eq!(listed, [first, second]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn restores_what_was_archived() {
+        let dir = scratch_dir();
+        let images = AttachmentStore::open(dir.join("images")).unwrap();
+        let file = images.import_bytes(b"not really a png", "png").unwrap();
+
+        let mut document = Document::new("Archived");
+        document.blocks.push(StoredBlock::new("text", "Hello"));
+        document.blocks.push(StoredBlock::new("image", "").with_props(serde_json::json!({ "file": file })));
+        let vault = Vault {
+            documents: vec![document.clone()],
+            ..Default::default()
+        };
+        let archive = dir.join("backup.zip");
+        let manifest = write_archive(&archive, &vault, &images).unwrap();
+        assert_eq!(manifest.documents.len(), 1);
+        assert_eq!(manifest.attachments.len(), 1);
+
+        let restored_images = AttachmentStore::open(dir.join("restored")).unwrap();
+        let restored = read_archive(&archive, &restored_images).unwrap();
+        assert_eq!(restored.documents.len(), 1);
+        assert_eq!(restored.documents[0].id, document.id);
+        assert_eq!(restored.documents[0].blocks.len(), 2);
+        let file = restored.documents[0].blocks[1].attachment().unwrap();
+        assert_eq!(std::fs::read(restored_images.path(file)).unwrap(), b"not really a png");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_archives_not_matching_their_checksums() {
+        let dir = scratch_dir();
+        let images = AttachmentStore::open(dir.join("images")).unwrap();
+        images.import_bytes(b"attachment", "png").unwrap();
+        let vault = Vault {
+            documents: vec![Document::new("Original")],
+            ..Default::default()
+        };
+        let archive = dir.join("backup.zip");
+        let manifest = write_archive(&archive, &vault, &images).unwrap();
+
+        // Copy the archive with the document swapped for another
+        let mut zip = ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+        let tampered = dir.join("tampered.zip");
+        let mut out = ZipWriter::new(File::create(&tampered).unwrap());
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i).unwrap();
+            let name = file.name().to_string();
+            out.start_file(name.as_str(), SimpleFileOptions::default()).unwrap();
+            if name == manifest.documents[0].path {
+                serde_json::to_writer(&mut out, &Document::new("Swapped")).unwrap();
+            } else {
+                io::copy(&mut file, &mut out).unwrap();
+            }
+        }
+        out.finish().unwrap();
+
+        let restored_images = AttachmentStore::open(dir.join("restored")).unwrap();
+        let error = read_archive(&tampered, &restored_images).err().unwrap();
+        assert!(error.to_string().contains("checksum"));
+        assert_eq!(std::fs::read_dir(dir.join("restored")).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
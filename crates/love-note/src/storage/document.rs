@@ -1,5 +1,8 @@
+use std::ops::Range;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use uuid::Uuid;
 
 /// A block stored in the database
@@ -8,6 +11,13 @@ pub struct StoredBlock {
     pub id: Uuid,
     pub kind: String,
     pub content: String,
+    /// Kind-specific properties (e.g. heading level)
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub props: Value,
+    /// Toggle block this block is nested in; children directly follow
+    /// their parent in the document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -19,11 +29,60 @@ impl StoredBlock {
             id: Uuid::new_v4(),
             kind: kind.into(),
             content: content.into(),
+            props: Value::Null,
+            parent: None,
             created_at: now,
             updated_at: now,
         }
     }
 
+    pub fn with_props(mut self, props: Value) -> Self {
+        self.props = props;
+        self
+    }
+
+    /// Get the attachment file this block references, if any
+    pub fn attachment(&self) -> Option<&str> {
+        self.props.get("file").and_then(Value::as_str)
+    }
+
+    /// The first text shown in the block that contains `query`, which
+    /// must be lowercase: the block text or, in tables, a column name or
+    /// cell. Other properties, such as mark types, code languages or
+    /// attachment names, aren't searched.
+    pub fn matching_text(&self, query: &str) -> Option<&str> {
+        let contains = |text: &&str| text.to_lowercase().contains(query);
+        if contains(&self.content.as_str()) {
+            return Some(&self.content);
+        }
+        if self.kind != "table" {
+            return None;
+        }
+        let names = self
+            .props
+            .get("columns")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|column| column.get("name")?.as_str());
+        let cells = self
+            .props
+            .get("rows")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_array)
+            .flatten()
+            .filter_map(Value::as_str);
+        names.chain(cells).find(contains)
+    }
+
+    /// Check whether any text shown in the block contains `query`, which
+    /// must be lowercase
+    pub fn matches(&self, query: &str) -> bool {
+        self.matching_text(query).is_some()
+    }
+
     pub fn update_content(&mut self, content: impl Into<String>) {
         self.content = content.into();
         self.updated_at = Utc::now();
@@ -36,6 +95,11 @@ pub struct Document {
     pub id: Uuid,
     pub title: String,
     pub blocks: Vec<StoredBlock>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Other properties, such as those read from Markdown front-matter
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub metadata: Map<String, Value>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -47,11 +111,23 @@ impl Document {
             id: Uuid::new_v4(),
             title: title.into(),
             blocks: Vec::new(),
+            tags: Vec::new(),
+            metadata: Map::new(),
             created_at: now,
             updated_at: now,
         }
     }
 
+    /// The text of the first heading, or the stored title
+    pub fn display_title(&self) -> String {
+        self.blocks
+            .iter()
+            .find(|block| block.kind == "heading")
+            .map(|block| block.content.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| self.title.clone())
+    }
+
     pub fn add_block(&mut self, block: StoredBlock) {
         self.blocks.push(block);
         self.updated_at = Utc::now();
@@ -84,6 +160,62 @@ impl Document {
     pub fn get_block(&self, block_id: Uuid) -> Option<&StoredBlock> {
         self.blocks.iter().find(|b| b.id == block_id)
     }
+
+    /// Move a block, together with the blocks nested in it, so that it
+    /// sits before the block now at `to` and is nested in `parent`.
+    /// Returns false if the block doesn't exist or the target lies inside
+    /// the moved blocks.
+    pub fn move_block(&mut self, block_id: Uuid, to: usize, parent: Option<Uuid>) -> bool {
+        let Some(index) = self.blocks.iter().position(|b| b.id == block_id) else {
+            return false;
+        };
+        let mut nested = vec![block_id];
+        let mut end = index + 1;
+        while let Some(parent) = self.blocks.get(end).and_then(|b| b.parent)
+            && nested.contains(&parent)
+        {
+            nested.push(self.blocks[end].id);
+            end += 1;
+        }
+        self.move_blocks(index..end, to, parent).is_some()
+    }
+
+    /// Move a run of blocks so it starts before the block now at `to`.
+    /// Blocks whose parent is outside the run are nested in `parent`.
+    /// Returns the new start, or `None` if the target lies inside the run
+    /// or past the end.
+    pub fn move_blocks(&mut self, range: Range<usize>, to: usize, parent: Option<Uuid>) -> Option<usize> {
+        let ids: Vec<Uuid> = self.blocks.get(range.clone())?.iter().map(|b| b.id).collect();
+        if parent.is_some_and(|p| ids.contains(&p)) {
+            return None;
+        }
+        let start = move_range(&mut self.blocks, range.clone(), to)?;
+
+        for block in &mut self.blocks[start..start + range.len()] {
+            if !block.parent.is_some_and(|p| ids.contains(&p)) {
+                block.parent = parent;
+            }
+        }
+        self.updated_at = Utc::now();
+        Some(start)
+    }
+}
+
+/// Move `items[range]` so it starts before the item now at `to`, or at
+/// the end if `to` is the length. Returns the new start, or `None` if
+/// `to` lies inside the range or either is out of bounds.
+fn move_range<T>(items: &mut Vec<T>, range: Range<usize>, to: usize) -> Option<usize> {
+    if range.start > range.end || range.end > items.len() || to > items.len() {
+        return None;
+    }
+    if range.start < to && to < range.end {
+        return None;
+    }
+    let len = range.len();
+    let moved: Vec<T> = items.drain(range.clone()).collect();
+    let start = if to >= range.end { to - len } else { to };
+    items.splice(start..start, moved);
+    Some(start)
 }
 
 impl Default for Document {
@@ -91,3 +223,73 @@ impl Default for Document {
         Self::new("Untitled")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(contents: &[&str]) -> Document {
+        let mut document = Document::new("Test");
+        document.blocks = contents.iter().map(|text| StoredBlock::new("text", *text)).collect();
+        document
+    }
+
+    fn texts(document: &Document) -> Vec<&str> {
+        document.blocks.iter().map(|b| b.content.as_str()).collect()
+    }
+
+    #[test]
+    fn moves_ranges_both_ways() {
+        let mut items = vec![0, 1, 2, 3, 4];
+        assert_eq!(move_range(&mut items, 1..3, 5), Some(3));
+        assert_eq!(items, [0, 3, 4, 1, 2]);
+
+        let mut items = vec![0, 1, 2, 3, 4];
+        assert_eq!(move_range(&mut items, 3..5, 0), Some(0));
+        assert_eq!(items, [3, 4, 0, 1, 2]);
+
+        // Either edge of the range leaves it in place
+        let mut items = vec![0, 1, 2];
+        assert_eq!(move_range(&mut items, 1..2, 1), Some(1));
+        assert_eq!(move_range(&mut items, 1..2, 2), Some(1));
+        assert_eq!(items, [0, 1, 2]);
+    }
+
+    #[test]
+    fn refuses_invalid_moves() {
+        let mut items = vec![0, 1, 2, 3];
+        assert_eq!(move_range(&mut items, 0..3, 1), None);
+        assert_eq!(move_range(&mut items, 0..1, 5), None);
+        assert_eq!(move_range(&mut items, 2..5, 0), None);
+        assert_eq!(items, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn moves_blocks_into_a_parent() {
+        let mut doc = document(&["a", "b", "toggle", "c"]);
+        let toggle = doc.blocks[2].id;
+        assert_eq!(doc.move_blocks(0..2, 4, Some(toggle)), Some(2));
+        assert_eq!(texts(&doc), ["toggle", "c", "a", "b"]);
+        assert_eq!(doc.blocks[2].parent, Some(toggle));
+        assert_eq!(doc.blocks[3].parent, Some(toggle));
+
+        // A run can't be nested in one of its own blocks
+        assert_eq!(doc.move_blocks(0..2, 4, Some(toggle)), None);
+    }
+
+    #[test]
+    fn moves_a_block_with_its_children() {
+        let mut doc = document(&["toggle", "child", "grandchild", "after"]);
+        let (toggle, child) = (doc.blocks[0].id, doc.blocks[1].id);
+        doc.blocks[1].parent = Some(toggle);
+        doc.blocks[2].parent = Some(child);
+
+        assert!(doc.move_block(toggle, 4, None));
+        assert_eq!(texts(&doc), ["after", "toggle", "child", "grandchild"]);
+        assert_eq!(doc.blocks[2].parent, Some(toggle));
+        assert_eq!(doc.blocks[3].parent, Some(child));
+
+        assert!(!doc.move_block(toggle, 3, None));
+        assert!(!doc.move_block(Uuid::new_v4(), 0, None));
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{self, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use uuid::Uuid;
+
+/// Content-addressed file store (e.g. `images/` next to the database).
+///
+/// Files are named `<sha256>.<ext>`, so importing the same file twice
+/// stores it once and the name doubles as a stable reference.
+pub struct AttachmentStore {
+    root: PathBuf,
+    /// Number of `CleanupPause`s held
+    pauses: AtomicUsize,
+}
+
+/// Keeps `AttachmentStore::retain` from removing anything while held, for
+/// work that stores files before saving the documents that reference them
+pub struct CleanupPause<'a> {
+    pauses: &'a AtomicUsize,
+}
+
+impl Drop for CleanupPause<'_> {
+    fn drop(&mut self) {
+        self.pauses.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl AttachmentStore {
+    /// Open the store, creating its directory if needed
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create {}", root.display()))?;
+        Ok(Self {
+            root,
+            pauses: AtomicUsize::new(0),
+        })
+    }
+
+    /// Get the directory holding the files
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Copy a file from disk into the store and return its name
+    pub fn import_file(&self, path: &Path) -> Result<String> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("bin");
+        self.import_bytes(&bytes, extension)
+    }
+
+    /// Store raw bytes and return the file name
+    pub fn import_bytes(&self, bytes: &[u8], extension: &str) -> Result<String> {
+        let name = file_name(&content_hash(bytes), extension);
+        let path = self.root.join(&name);
+        if !path.exists() {
+            // Write to a temp file first so a crash never leaves a partial file
+            // under a valid content address
+            let tmp = self.root.join(format!("{}.tmp", name));
+            std::fs::write(&tmp, bytes).context("Failed to write attachment")?;
+            std::fs::rename(&tmp, &path).context("Failed to store attachment")?;
+        }
+
+        Ok(name)
+    }
+
+    /// Store everything `reader` yields and return the file name, copying
+    /// it to disk as it is read rather than holding it in memory
+    pub fn import_reader(&self, reader: &mut impl Read, extension: &str) -> Result<String> {
+        // The name is only known once everything is read
+        let tmp = self.root.join(format!("{}.tmp", Uuid::new_v4()));
+        let stored = File::create(&tmp)
+            .map(|file| Hashing::new(BufWriter::new(file)))
+            .and_then(|mut out| {
+                io::copy(reader, &mut out)?;
+                out.finish()
+            })
+            .context("Failed to write attachment")
+            .and_then(|hash| {
+                let name = file_name(&hash, extension);
+                let path = self.root.join(&name);
+                if path.exists() {
+                    std::fs::remove_file(&tmp)?;
+                } else {
+                    std::fs::rename(&tmp, &path).context("Failed to store attachment")?;
+                }
+                Ok(name)
+            });
+        if stored.is_err() {
+            std::fs::remove_file(&tmp).ok();
+        }
+        stored
+    }
+
+    /// Get the path of a stored file
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.root.join(name)
+    }
+
+    /// List the names of all stored files
+    pub fn list(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file()
+                && let Some(name) = entry.file_name().to_str()
+            {
+                names.push(name.to_string());
+            }
+        }
+        Ok(names)
+    }
+
+    /// Pause cleanup until the returned guard is dropped
+    pub fn pause_cleanup(&self) -> CleanupPause<'_> {
+        self.pauses.fetch_add(1, Ordering::SeqCst);
+        CleanupPause {
+            pauses: &self.pauses,
+        }
+    }
+
+    /// Delete every file not in `referenced`, apart from files still being
+    /// written. Nothing is removed while cleanup is paused. Returns the
+    /// number removed.
+    pub fn retain(&self, referenced: &HashSet<String>) -> Result<usize> {
+        if self.pauses.load(Ordering::SeqCst) > 0 {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        for name in self.list()? {
+            if !referenced.contains(&name) && !name.ends_with(".tmp") {
+                std::fs::remove_file(self.root.join(&name))
+                    .with_context(|| format!("Failed to remove {}", name))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// The SHA-256 of `bytes` in lowercase hex, which names stored files
+pub(super) fn content_hash(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+fn hex(digest: &[u8]) -> String {
+    let mut hash = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        let _ = write!(hash, "{:02x}", byte);
+    }
+    hash
+}
+
+/// Name of a stored file with content hash `hash`
+fn file_name(hash: &str, extension: &str) -> String {
+    let extension: String = extension
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if extension.is_empty() {
+        hash.to_string()
+    } else {
+        format!("{}.{}", hash, extension)
+    }
+}
+
+/// Hashes what is written through it, for checking and naming files
+/// without holding them in memory
+pub(super) struct Hashing<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: io::Write> Hashing<W> {
+    pub(super) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Flush and return the SHA-256 of everything written, in lowercase hex
+    pub(super) fn finish(mut self) -> io::Result<String> {
+        self.inner.flush()?;
+        Ok(hex(&self.hasher.finalize()))
+    }
+}
+
+impl<W: io::Write> io::Write for Hashing<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
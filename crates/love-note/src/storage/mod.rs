@@ -1,5 +1,16 @@
+/// Backups of the whole vault as a single zip archive.
+///
+/// An archive holds every document as `documents/<id>.json` and every
+/// attachment as `images/<name>`, next to a `manifest.json` listing them
+/// with their SHA-256 alongside settings, the plugin registry and which
+/// toggles are collapsed. Archives are read completely and checked
+/// before anything is restored.
+mod backup;
 mod document;
+mod files;
 mod redb_store;
 
+pub use backup::{ArchivedFile, BackupManifest, BackupSchedule, RestoreMode, BACKUP_FORMAT};
 pub use document::{Document, StoredBlock};
-pub use redb_store::Storage;
+pub use files::{AttachmentStore, CleanupPause};
+pub use redb_store::{SearchHit, Storage};
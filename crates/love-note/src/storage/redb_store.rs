@@ -1,17 +1,45 @@
 use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
 use directories::ProjectDirs;
+use love_note_plugin_api::PluginInfo;
 use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
-use std::path::PathBuf;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
-use super::Document;
+use super::backup::{self, BackupManifest, BackupSchedule, RestoreMode, Vault};
+use super::{AttachmentStore, Document};
 
 /// Table for storing documents (key: UUID bytes, value: JSON bytes)
 const DOCUMENTS_TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("documents");
 
+/// Table of collapsed toggle blocks (key: block UUID bytes). This is local
+/// view state, kept out of documents so it isn't shared with other users.
+const COLLAPSED_TABLE: TableDefinition<&[u8], ()> = TableDefinition::new("collapsed_blocks");
+
+/// Table of settings (key: setting name, value: JSON bytes)
+const SETTINGS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("settings");
+
+/// Table of installed plugins (key: plugin id, value: `PluginInfo` JSON bytes)
+const PLUGINS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("plugins");
+
+/// A block found by `Storage::search`
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub document_id: Uuid,
+    pub document_title: String,
+    pub block_id: Uuid,
+    /// The text in the block that matched
+    pub text: String,
+}
+
 /// Storage manager using redb
 pub struct Storage {
     db: Database,
+    images: AttachmentStore,
+    /// Directory automatic backups are written to
+    backups: PathBuf,
 }
 
 impl Storage {
@@ -19,11 +47,16 @@ impl Storage {
     pub fn open() -> Result<Self> {
         let db_path = Self::db_path()?;
 
-        // Create parent directory if needed
-        if let Some(parent) = db_path.parent() {
-            std::fs::create_dir_all(parent)
-                .context("Failed to create data directory")?;
-        }
+        let data_dir = db_path
+            .parent()
+            .context("Database path has no parent directory")?;
+
+        // Create data directory if needed
+        std::fs::create_dir_all(data_dir)
+            .context("Failed to create data directory")?;
+
+        let images = AttachmentStore::open(data_dir.join("images"))?;
+        let backups = data_dir.join("backups");
 
         let db = Database::create(&db_path)
             .context("Failed to open database")?;
@@ -32,10 +65,18 @@ impl Storage {
         {
             let write_txn = db.begin_write()?;
             let _ = write_txn.open_table(DOCUMENTS_TABLE)?;
+            let _ = write_txn.open_table(COLLAPSED_TABLE)?;
+            let _ = write_txn.open_table(SETTINGS_TABLE)?;
+            let _ = write_txn.open_table(PLUGINS_TABLE)?;
             write_txn.commit()?;
         }
 
-        Ok(Self { db })
+        Ok(Self { db, images, backups })
+    }
+
+    /// Get the store for image files
+    pub fn images(&self) -> &AttachmentStore {
+        &self.images
     }
 
     /// Get the database file path
@@ -81,6 +122,15 @@ impl Storage {
 
     /// List all document IDs and titles
     pub fn list_documents(&self) -> Result<Vec<(Uuid, String)>> {
+        Ok(self
+            .documents()?
+            .into_iter()
+            .map(|doc| (doc.id, doc.title))
+            .collect())
+    }
+
+    /// Load every document in the database
+    pub fn documents(&self) -> Result<Vec<Document>> {
         let read_txn = self.db.begin_read()?;
         let table = read_txn.open_table(DOCUMENTS_TABLE)?;
 
@@ -88,13 +138,256 @@ impl Storage {
         for result in table.iter()? {
             let (_, value_guard) = result?;
             let bytes: &[u8] = value_guard.value();
-            let doc: Document = serde_json::from_slice(bytes)?;
-            docs.push((doc.id, doc.title));
+            docs.push(serde_json::from_slice(bytes)?);
         }
 
         Ok(docs)
     }
 
+    /// Find blocks whose text contains `query` (case-insensitive) across
+    /// all documents. Blocks inside collapsed toggles are included.
+    pub fn search(&self, query: &str) -> Result<Vec<SearchHit>> {
+        let query = query.to_lowercase();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut hits = Vec::new();
+        for doc in self.documents()? {
+            let title = doc.display_title();
+            for block in &doc.blocks {
+                if let Some(text) = block.matching_text(&query) {
+                    hits.push(SearchHit {
+                        document_id: doc.id,
+                        document_title: title.clone(),
+                        block_id: block.id,
+                        text: text.to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(hits)
+    }
+
+    /// Get the ids of all collapsed toggle blocks
+    pub fn collapsed_blocks(&self) -> Result<HashSet<Uuid>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(COLLAPSED_TABLE)?;
+
+        let mut ids = HashSet::new();
+        for result in table.iter()? {
+            let (key_guard, _) = result?;
+            ids.insert(Uuid::from_slice(key_guard.value())?);
+        }
+
+        Ok(ids)
+    }
+
+    /// Remember whether a toggle block is collapsed
+    pub fn set_collapsed(&self, block_id: Uuid, collapsed: bool) -> Result<()> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(COLLAPSED_TABLE)?;
+            let key = block_id.as_bytes().as_slice();
+            if collapsed {
+                table.insert(key, ())?;
+            } else {
+                table.remove(key)?;
+            }
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Get a setting's value
+    pub fn setting(&self, key: &str) -> Result<Option<Value>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(SETTINGS_TABLE)?;
+
+        match table.get(key)? {
+            Some(guard) => Ok(Some(serde_json::from_slice(guard.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get every setting by name
+    pub fn settings(&self) -> Result<Map<String, Value>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(SETTINGS_TABLE)?;
+
+        let mut settings = Map::new();
+        for result in table.iter()? {
+            let (key_guard, value_guard) = result?;
+            settings.insert(key_guard.value().to_string(), serde_json::from_slice(value_guard.value())?);
+        }
+
+        Ok(settings)
+    }
+
+    /// Change a setting
+    pub fn set_setting(&self, key: &str, value: &Value) -> Result<()> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(SETTINGS_TABLE)?;
+            table.insert(key, serde_json::to_vec(value)?.as_slice())?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Get the installed plugins
+    pub fn plugins(&self) -> Result<Vec<PluginInfo>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(PLUGINS_TABLE)?;
+
+        let mut plugins = Vec::new();
+        for result in table.iter()? {
+            let (_, value_guard) = result?;
+            plugins.push(serde_json::from_slice(value_guard.value())?);
+        }
+
+        Ok(plugins)
+    }
+
+    /// Record an installed plugin, replacing an earlier version
+    pub fn save_plugin(&self, plugin: &PluginInfo) -> Result<()> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(PLUGINS_TABLE)?;
+            table.insert(plugin.id.as_str(), serde_json::to_vec(plugin)?.as_slice())?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Write every document, attachment, setting and installed plugin to
+    /// a zip archive at `path`
+    pub fn write_backup(&self, path: &Path) -> Result<BackupManifest> {
+        let vault = Vault {
+            documents: self.documents()?,
+            collapsed: self.collapsed_blocks()?,
+            settings: self.settings()?,
+            plugins: self.plugins()?,
+        };
+        backup::write_archive(path, &vault, &self.images)
+    }
+
+    /// Restore a backup written by `write_backup`. Returns the number of
+    /// documents taken from the backup.
+    ///
+    /// Merging keeps the settings and plugins already stored and only
+    /// adds those missing from them; replacing takes them all from the
+    /// backup.
+    pub fn restore_backup(&self, path: &Path, mode: RestoreMode) -> Result<usize> {
+        // Restored attachments aren't referenced until the documents are
+        let pause = self.images.pause_cleanup();
+        let vault = backup::read_archive(path, &self.images)?;
+        let existing = match mode {
+            RestoreMode::Merge => self.documents()?,
+            RestoreMode::Replace => Vec::new(),
+        };
+
+        let write_txn = self.db.begin_write()?;
+        let mut restored = 0;
+        {
+            let mut documents = write_txn.open_table(DOCUMENTS_TABLE)?;
+            let mut collapsed = write_txn.open_table(COLLAPSED_TABLE)?;
+            let mut settings = write_txn.open_table(SETTINGS_TABLE)?;
+            let mut plugins = write_txn.open_table(PLUGINS_TABLE)?;
+            if mode == RestoreMode::Replace {
+                documents.retain(|_, _| false)?;
+                collapsed.retain(|_, _| false)?;
+                settings.retain(|_, _| false)?;
+                plugins.retain(|_, _| false)?;
+            }
+
+            for doc in &vault.documents {
+                // When merging, a document edited since the backup is kept
+                let newer = existing
+                    .iter()
+                    .any(|stored| stored.id == doc.id && stored.updated_at > doc.updated_at);
+                if !newer {
+                    documents.insert(doc.id.as_bytes().as_slice(), serde_json::to_vec(doc)?.as_slice())?;
+                    restored += 1;
+                }
+            }
+            for id in &vault.collapsed {
+                collapsed.insert(id.as_bytes().as_slice(), ())?;
+            }
+            // Settings and plugins already stored are kept
+            for (key, value) in &vault.settings {
+                if settings.get(key.as_str())?.is_none() {
+                    settings.insert(key.as_str(), serde_json::to_vec(value)?.as_slice())?;
+                }
+            }
+            for plugin in &vault.plugins {
+                if plugins.get(plugin.id.as_str())?.is_none() {
+                    plugins.insert(plugin.id.as_str(), serde_json::to_vec(plugin)?.as_slice())?;
+                }
+            }
+        }
+        write_txn.commit()?;
+        drop(pause);
+
+        if mode == RestoreMode::Replace {
+            self.collect_garbage()?;
+        }
+        Ok(restored)
+    }
+
+    /// Write an automatic backup if the last one is older than the
+    /// schedule in settings allows, then delete the oldest beyond the
+    /// number to keep. Returns the path of the new backup, if one was made.
+    pub fn backup_if_due(&self) -> Result<Option<PathBuf>> {
+        let schedule = BackupSchedule::from_settings(&self.settings()?);
+        if schedule.interval_hours == 0 {
+            return Ok(None);
+        }
+
+        let now = Utc::now();
+        let interval = Duration::hours(schedule.interval_hours as i64);
+        let backups = backup::backups(&self.backups)?;
+        if backups.last().is_some_and(|(made, _)| now - *made < interval) {
+            return Ok(None);
+        }
+
+        std::fs::create_dir_all(&self.backups)
+            .with_context(|| format!("Failed to create {}", self.backups.display()))?;
+        let path = self.backups.join(backup::backup_name(now));
+        self.write_backup(&path)?;
+
+        for old in backup::outdated(backups, &path, schedule.keep) {
+            std::fs::remove_file(&old)
+                .with_context(|| format!("Failed to remove {}", old.display()))?;
+        }
+
+        Ok(Some(path))
+    }
+
+    /// Remove image files no longer referenced by any document.
+    /// Returns the number of files removed.
+    pub fn collect_garbage(&self) -> Result<usize> {
+        self.collect_garbage_except(HashSet::new())
+    }
+
+    /// Remove image files no longer referenced by any document, apart
+    /// from those in `kept`. Returns the number of files removed.
+    pub fn collect_garbage_except(&self, mut kept: HashSet<String>) -> Result<usize> {
+        kept.extend(
+            self.documents()?
+                .iter()
+                .flat_map(|doc| doc.blocks.iter())
+                .filter_map(|block| block.attachment())
+                .map(str::to_string),
+        );
+
+        self.images.retain(&kept)
+    }
+
     /// Delete a document from the database
     pub fn delete_document(&self, doc_id: Uuid) -> Result<bool> {
         let write_txn = self.db.begin_write()?;
@@ -108,15 +401,10 @@ impl Storage {
         Ok(removed)
     }
 
-    /// Get or create a default document
+    /// Get the most recently edited document, or create one
     pub fn get_or_create_default(&self) -> Result<Document> {
-        // Try to load the first document
-        let docs = self.list_documents()?;
-
-        if let Some((id, _)) = docs.first() {
-            if let Some(doc) = self.load_document(*id)? {
-                return Ok(doc);
-            }
+        if let Some(doc) = self.documents()?.into_iter().max_by_key(|doc| doc.updated_at) {
+            return Ok(doc);
         }
 
         // Create a new default document